@@ -0,0 +1,12 @@
+-- Tags and an optional category per poll, both normalized by `models::tags`
+
+ALTER TABLE polls ADD COLUMN category TEXT;
+CREATE INDEX polls_category ON polls (category);
+
+CREATE TABLE poll_tags (
+    poll_id TEXT NOT NULL REFERENCES polls (id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    position BIGINT NOT NULL,
+    PRIMARY KEY (poll_id, tag)
+);
+CREATE INDEX poll_tags_tag ON poll_tags (tag);
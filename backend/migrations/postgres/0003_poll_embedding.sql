@@ -0,0 +1,5 @@
+-- Whether a poll may be framed through `/embed/:poll_id`, and by which
+-- origins (a JSON array; only the backend's own pages when empty)
+
+ALTER TABLE polls ADD COLUMN embeddable BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE polls ADD COLUMN embed_origins TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,125 @@
+-- Relational schema for STORAGE=sqlite. Timestamps are Unix milliseconds,
+-- IDs are ObjectId hex strings and user IDs hyphenated UUIDs, as in MongoDB.
+
+CREATE TABLE users (
+    unique_id TEXT PRIMARY KEY,
+    username TEXT NOT NULL,
+    username_key TEXT NOT NULL UNIQUE,
+    is_admin INTEGER NOT NULL DEFAULT 0,
+    disabled INTEGER NOT NULL DEFAULT 0,
+    -- JSON arrays; `version` guards their read-modify-write updates
+    passkeys TEXT NOT NULL,
+    passkey_meta TEXT NOT NULL,
+    recovery_codes TEXT NOT NULL,
+    version BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE polls (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    creator_id TEXT NOT NULL,
+    is_closed INTEGER NOT NULL DEFAULT 0,
+    created_at BIGINT NOT NULL,
+    recurrence TEXT,
+    next_occurrence_at BIGINT,
+    series_id TEXT,
+    previous_instance_id TEXT,
+    visibility TEXT NOT NULL,
+    share_token TEXT UNIQUE,
+    org_id TEXT
+);
+CREATE INDEX polls_creator_id ON polls (creator_id);
+CREATE INDEX polls_org_id ON polls (org_id);
+CREATE INDEX polls_series_id ON polls (series_id);
+CREATE INDEX polls_next_occurrence_at ON polls (next_occurrence_at);
+
+CREATE TABLE poll_options (
+    poll_id TEXT NOT NULL REFERENCES polls (id) ON DELETE CASCADE,
+    option_id BIGINT NOT NULL,
+    position BIGINT NOT NULL,
+    text TEXT NOT NULL,
+    votes BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (poll_id, option_id)
+);
+
+CREATE TABLE poll_invites (
+    poll_id TEXT NOT NULL REFERENCES polls (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    position BIGINT NOT NULL,
+    PRIMARY KEY (poll_id, user_id)
+);
+CREATE INDEX poll_invites_user_id ON poll_invites (user_id);
+
+CREATE TABLE poll_collaborators (
+    poll_id TEXT NOT NULL REFERENCES polls (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    position BIGINT NOT NULL,
+    PRIMARY KEY (poll_id, user_id)
+);
+CREATE INDEX poll_collaborators_user_id ON poll_collaborators (user_id);
+
+CREATE TABLE votes (
+    id TEXT PRIMARY KEY,
+    poll_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    option_id BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    UNIQUE (poll_id, user_id)
+);
+CREATE INDEX votes_user_id ON votes (user_id);
+
+CREATE TABLE organizations (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE org_members (
+    org_id TEXT NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    position BIGINT NOT NULL,
+    PRIMARY KEY (org_id, user_id)
+);
+CREATE INDEX org_members_user_id ON org_members (user_id);
+
+CREATE TABLE user_sessions (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL UNIQUE,
+    user_id TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    last_seen_at BIGINT NOT NULL,
+    user_agent TEXT,
+    ip TEXT
+);
+CREATE INDEX user_sessions_user_id ON user_sessions (user_id);
+
+CREATE TABLE api_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    -- JSON array of scope names
+    scopes TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    expires_at BIGINT NOT NULL,
+    last_used_at BIGINT
+);
+CREATE INDEX api_tokens_user_id ON api_tokens (user_id);
+
+CREATE TABLE admin_log (
+    id TEXT PRIMARY KEY,
+    admin_id TEXT NOT NULL,
+    admin_username TEXT NOT NULL,
+    action TEXT NOT NULL,
+    target TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+CREATE INDEX admin_log_created_at ON admin_log (created_at);
+
+-- tower-sessions records, serialized as JSON
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    expiry_date BIGINT NOT NULL
+);
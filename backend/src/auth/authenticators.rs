@@ -0,0 +1,64 @@
+// src/auth/authenticators.rs
+use serde_cbor_2::Value;
+use uuid::Uuid;
+use webauthn_rs::prelude::RegisterPublicKeyCredential;
+
+// authenticatorData: rpIdHash (32) | flags (1) | signCount (4) | attestedCredentialData
+const FLAGS_OFFSET: usize = 32;
+const AAGUID_RANGE: std::ops::Range<usize> = 37..53;
+const FLAG_ATTESTED_CREDENTIAL: u8 = 0x40;
+
+// Well-known passkey providers and security keys, keyed by AAGUID
+const KNOWN_AUTHENTICATORS: &[(&str, &str)] = &[
+    ("fbfc3007-154e-4ecc-8c0b-6e020557d7bd", "iCloud Keychain"),
+    ("dd4ec289-e01d-41c9-bb89-70fa845d4bf2", "iCloud Keychain (Managed)"),
+    ("ea9b8d66-4d01-1d21-3ce4-b6b48cb575d4", "Google Password Manager"),
+    ("adce0002-35bc-c60a-648b-0b25f1f05503", "Chrome on Mac"),
+    ("771b48fd-d3d4-4f74-9232-fc157ab0507a", "Edge on Mac"),
+    ("08987058-cadc-4b81-b6e1-30de50dcbe96", "Windows Hello"),
+    ("9ddd1817-af5a-4672-a2b9-3e3dd95000a9", "Windows Hello"),
+    ("6028b017-b1d4-4c02-b4b3-afcdafc96bb2", "Windows Hello"),
+    ("53414d53-554e-4700-0000-000000000000", "Samsung Pass"),
+    ("bada5566-a7aa-401f-bd96-45619a55120d", "1Password"),
+    ("d548826e-79b4-db40-a3d8-11116f7e8349", "Bitwarden"),
+    ("531126d6-e717-415c-9320-3d9aa6981239", "Dashlane"),
+    ("0ea242b4-43c4-4a1b-8b17-dd6d0b6baec6", "Keeper"),
+    ("b84e4048-15dc-4dd0-8640-f4f60813c8af", "NordPass"),
+    ("f3809540-7f14-49c1-a8b3-8f813b225541", "Enpass"),
+    ("50726f74-6f6e-5061-7373-50726f746f6e", "Proton Pass"),
+    ("cb69481e-8ff7-4039-93ec-0a2729a154a8", "YubiKey 5 Series"),
+    ("ee882879-721c-4913-9775-3dfcce97072a", "YubiKey 5 Series"),
+    ("fa2b99dc-9e39-4257-8f92-4a30d23c4118", "YubiKey 5 Series with NFC"),
+    ("2fc0579f-8113-47ea-b116-bb5a8db9202a", "YubiKey 5 Series with NFC"),
+    ("c5ef55ff-ad9a-4b9f-b580-adebafe026d0", "YubiKey 5Ci"),
+    ("73bb0cd4-e502-49b8-9c6f-b59445bf720b", "YubiKey 5 FIPS Series"),
+    ("149a2021-8ef6-4133-96b8-81f8d5b7f1f5", "Security Key by Yubico with NFC"),
+    ("6d44ba9b-f6ec-2e49-b930-0c8fe920cb73", "Security Key by Yubico with NFC"),
+    ("0bb43545-fd2c-4185-87dd-feb0b2916ace", "Security Key NFC by Yubico"),
+];
+
+// The authenticator model reported during registration, if the client passed
+// it through (some browsers zero it out when no attestation is requested).
+pub fn registration_aaguid(reg: &RegisterPublicKeyCredential) -> Option<Uuid> {
+    let Ok(Value::Map(attestation)) = serde_cbor_2::from_slice::<Value>(&reg.response.attestation_object) else {
+        return None;
+    };
+    let Some(Value::Bytes(auth_data)) = attestation.get(&Value::Text("authData".to_string())) else {
+        return None;
+    };
+    if auth_data.get(FLAGS_OFFSET)? & FLAG_ATTESTED_CREDENTIAL == 0 {
+        return None;
+    }
+    auth_data
+        .get(AAGUID_RANGE)
+        .and_then(|bytes| Uuid::from_slice(bytes).ok())
+        .filter(|aaguid| !aaguid.is_nil())
+}
+
+pub fn authenticator_name(aaguid: &Uuid) -> Option<&'static str> {
+    let aaguid = aaguid.to_string();
+    KNOWN_AUTHENTICATORS
+        .iter()
+        .find(|(id, _)| *id == aaguid)
+        .map(|(_, name)| *name)
+}
@@ -1,23 +1,44 @@
 // src/auth/mod.rs
+pub mod authenticators;
+pub mod passkeys;
+pub mod policy;
+pub mod recovery;
+pub mod sessions;
+pub mod tokens;
+pub mod username;
+
 use axum::{
-    extract::{Extension, Json, Path},
+    extract::{Extension, Json, Path, Query},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use tower_sessions::Session;
+use serde::{Deserialize, Serialize};
 use webauthn_rs::prelude::*;
-use mongodb::bson::doc;
 use uuid::Uuid;
-use crate::error::WebauthnError;
+use crate::auth::tokens::SessionUser;
+use crate::error::{ApiError, WebauthnError};
 use crate::startup::{AppState, UserData};
 use std::env;
+use std::sync::OnceLock;
 use dotenv::dotenv;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use webauthn_rs_proto::{AllowCredentials, AuthenticatorTransport};
+
+// Session key set while the pending login challenge is a decoy
+const DECOY_AUTH_KEY: &str = "decoy_auth";
+// Session key holding the unix time of the last passkey assertion
+pub const AUTHENTICATED_AT_KEY: &str = "authenticated_at";
+// How long a passkey assertion counts as fresh for sensitive actions, unless
+// REAUTH_WINDOW_MINUTES says otherwise
+const DEFAULT_REAUTH_WINDOW_MINUTES: i64 = 5;
 
 pub async fn start_register(
     Extension(app_state): Extension<AppState>,
     session: Session,
     Path(username): Path<String>,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     dotenv().ok();
     info!("Start register for username: {}", username);
 
@@ -32,41 +53,32 @@ pub async fn start_register(
         info!("Cleared previous reg_state from session");
     }
 
-    let exclude_credentials: Option<Vec<CredentialID>> = {
-        let collection = app_state.users_collection();
-        match collection.find_one(doc! { "username": &username }).await {
-            Ok(Some(user)) => {
-                info!("Found existing user {} with {} passkeys", username, user.passkeys.len());
-                Some(user.passkeys.into_iter().map(|sk| sk.cred_id().clone()).collect())
-            }
-            Ok(None) => {
-                info!("No existing user found for {}", username);
-                None
-            }
-            Err(e) => {
-                error!("Failed to query user {}: {:?}", username, e);
-                return Err(WebauthnError::MongoDBError(e));
-            }
+    // Passkeys are added to an existing account through `/api/passkeys/add_start`
+    let username = username::validate_username(&username)?;
+    match app_state.users.find_by_username(&username).await {
+        Ok(Some(_)) => {
+            error!("Refusing to register existing username {}", username);
+            return Err(ApiError::UsernameTaken);
         }
-    };
+        Ok(None) => info!("Username {} is available", username),
+        Err(e) => {
+            error!("Failed to query user {}: {:?}", username, e);
+            return Err(e);
+        }
+    }
 
-    let res = match app_state.webauthn.start_passkey_registration(
-        user_unique_id,
-        &username,
-        &username,
-        exclude_credentials,
-    ) {
+    let res = match passkeys::start_registration(&app_state, user_unique_id, &username, None) {
         Ok((ccr, reg_state)) => {
             if let Err(e) = session.insert("reg_state", (username.clone(), user_unique_id, reg_state)).await {
                 error!("Failed to insert reg_state into session: {:?}", e);
-                return Err(WebauthnError::InvalidSessionState(e));
+                return Err(ApiError::from(e));
             }
             info!("Successfully inserted reg_state into session for {} (UUID: {})", username, user_unique_id);
             Json(ccr)
         }
         Err(e) => {
             error!("Failed to start passkey registration for {}: {:?}", username, e);
-            return Err(WebauthnError::Unknown);
+            return Err(WebauthnError::Unknown.into());
         }
     };
 
@@ -75,14 +87,26 @@ pub async fn start_register(
     info!("Setting CORS headers: Access-Control-Allow-Origin: {}", env::var("RP_ORIGIN").unwrap_or_default());
     Ok(res)
 }
+#[derive(Deserialize)]
+pub struct FinishRegisterQuery {
+    // Recovery codes are opt-out: `?recoveryCodes=false` skips them
+    #[serde(rename = "recoveryCodes", default = "default_true")]
+    pub recovery_codes: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
 pub async fn finish_register(
     Extension(app_state): Extension<AppState>,
     session: Session,
+    Query(query): Query<FinishRegisterQuery>,
     Json(reg): Json<RegisterPublicKeyCredential>,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<Response, ApiError> {
     // CHANGE: Add logging to debug session retrieval
     info!("Attempting to retrieve reg_state from session");
-    let (username, user_unique_id, reg_state): (String, Uuid, PasskeyRegistration) = session
+    let (username, user_unique_id, reg_state): (String, Uuid, passkeys::PendingRegistration) = session
         .get("reg_state")
         .await?
         .ok_or_else(|| {
@@ -96,52 +120,45 @@ pub async fn finish_register(
         info!("Cleared reg_state from session for {}", username);
     }
 
-    let res = match app_state.webauthn.finish_passkey_registration(&reg, &reg_state) {
-        Ok(sk) => {
-            let collection = app_state.users_collection();
+    let res = match passkeys::finish_registration(&app_state, &reg, &reg_state) {
+        Ok((sk, verified_aaguid)) => {
+            let meta = passkeys::check_new_passkey(&app_state, &sk, &reg, verified_aaguid)?;
+            let (codes, code_hashes) = if query.recovery_codes {
+                recovery::generate_codes()
+            } else {
+                (Vec::new(), Vec::new())
+            };
             let user_data = UserData {
+                username_key: username::username_key(&username),
                 username: username.clone(),
                 unique_id: user_unique_id,
+                passkey_meta: vec![meta],
                 passkeys: vec![sk],
+                is_admin: false,
+                disabled: false,
+                recovery_codes: code_hashes,
             };
 
-            let update_doc = doc! { 
-                "$setOnInsert": { "unique_id": user_unique_id.to_string() },
-                "$push": { "passkeys": mongodb::bson::to_bson(&user_data.passkeys[0])? } 
-            };
-            info!("Attempting to upsert user {} with update: {:?}", username, update_doc);
-
-            match collection
-                .update_one(
-                    doc! { "username": &username },
-                    update_doc,
-                )
-                .with_options(mongodb::options::UpdateOptions::builder().upsert(true).build())
-                .await
-            {
-                Ok(result) => {
-                    info!(
-                        "MongoDB update result: matched={}, modified={}, upserted_id={:?}",
-                        result.matched_count, result.modified_count, result.upserted_id
-                    );
-                    if result.upserted_id.is_some() {
-                        info!("Inserted new user {} (UUID: {}) into MongoDB", username, user_unique_id);
-                    } else if result.modified_count > 0 {
-                        info!("Appended passkey to existing user {} in MongoDB", username);
-                    } else {
-                        warn!("No changes made to MongoDB for user {} - possible issue", username);
-                    }
-                    StatusCode::OK
+            // A concurrent registration of the same name fails here
+            match app_state.users.insert(&user_data).await {
+                Ok(()) => {
+                    info!("Inserted new user {} (UUID: {})", username, user_unique_id);
+                    // Shown once; only the hashes are stored
+                    Json(recovery::RecoveryCodesResponse { recovery_codes: codes }).into_response()
+                }
+                Err(ApiError::UsernameTaken) => {
+                    error!("Username {} was registered concurrently", username);
+                    return Err(ApiError::UsernameTaken);
                 }
                 Err(e) => {
-                    error!("Failed to upsert user {} into MongoDB: {:?}", username, e);
-                    return Err(WebauthnError::MongoDBError(e));
+                    error!("Failed to insert user {}: {:?}", username, e);
+                    return Err(e);
                 }
             }
         }
         Err(e) => {
             error!("Failed to finish passkey registration for {}: {:?}", username, e);
-            StatusCode::BAD_REQUEST
+            StatusCode::BAD_REQUEST.into_response()
         }
     };
     info!("Registration completed successfully for {}", username);
@@ -152,7 +169,7 @@ pub async fn start_authentication(
     Extension(app_state): Extension<AppState>,
     session: Session,
     Path(username): Path<String>,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     info!("Start authentication for username: {}", username);
 
     if let Err(e) = session.remove_value("auth_state").await {
@@ -160,20 +177,20 @@ pub async fn start_authentication(
     } else {
         info!("Cleared previous auth_state from session");
     }
+    session.remove_value(DECOY_AUTH_KEY).await?;
 
-    let collection = app_state.users_collection();
-    let user_data = match collection.find_one(doc! { "username": &username }).await {
-        Ok(Some(user)) => {
+    // Unknown usernames, and accounts without passkeys, get a decoy challenge
+    // shaped like a real one, so signing in does not reveal which accounts
+    // exist (registering a taken name still does, with `username_taken`). Disabled accounts get a real challenge and are refused only
+    // after the assertion checks out.
+    let user_data = match app_state.users.find_by_username(&username).await? {
+        Some(user) if !user.passkeys.is_empty() => {
             info!("Found user {} with {} passkeys for authentication", username, user.passkeys.len());
             user
         }
-        Ok(None) => {
-            error!("No user found for {} during authentication", username);
-            return Err(WebauthnError::UserNotFound);
-        }
-        Err(e) => {
-            error!("Failed to query user {} for authentication: {:?}", username, e);
-            return Err(WebauthnError::MongoDBError(e));
+        _ => {
+            error!("No user with passkeys found for {} during authentication", username);
+            return decoy_challenge(&app_state, &session, &username).await.map(Json);
         }
     };
 
@@ -181,24 +198,74 @@ pub async fn start_authentication(
         Ok((rcr, auth_state)) => {
             if let Err(e) = session.insert("auth_state", (user_data.unique_id, auth_state)).await {
                 error!("Failed to insert auth_state into session: {:?}", e);
-                return Err(WebauthnError::InvalidSessionState(e));
+                return Err(ApiError::from(e));
             }
             info!("Authentication challenge generated for {} (UUID: {})", username, user_data.unique_id);
             Json(rcr)
         }
         Err(e) => {
             error!("Failed to start passkey authentication for {}: {:?}", username, e);
-            return Err(WebauthnError::Unknown);
+            return Err(WebauthnError::Unknown.into());
         }
     };
     Ok(res)
 }
 
+// A challenge for a username that cannot sign in. Like a real one it lists one
+// or two credentials with transports. Everything is derived from the username
+// with a per-process key, so asking twice gives the same answer as it would
+// for a real account, and `finish_authentication` fails it like a bad
+// assertion.
+async fn decoy_challenge(
+    app_state: &AppState,
+    session: &Session,
+    username: &str,
+) -> Result<RequestChallengeResponse, ApiError> {
+    static DECOY_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+    let key = DECOY_KEY.get_or_init(rand::random);
+
+    let (mut rcr, _) = app_state.webauthn.start_discoverable_authentication().map_err(|e| {
+        error!("Failed to start decoy authentication: {:?}", e);
+        WebauthnError::Unknown
+    })?;
+    let derive = |index: u8| {
+        let mut hasher = Sha256::new();
+        hasher.update(key);
+        hasher.update(username::username_key(username).as_bytes());
+        hasher.update([index]);
+        hasher.finalize()
+    };
+    let count = 1 + derive(0)[0] % 2;
+    rcr.mediation = None;
+    rcr.public_key.extensions = None;
+    rcr.public_key.allow_credentials = (1..=count)
+        .map(|index| {
+            let id = derive(index);
+            let transports = if id[0] % 4 == 0 {
+                vec![AuthenticatorTransport::Nfc, AuthenticatorTransport::Usb]
+            } else {
+                vec![AuthenticatorTransport::Hybrid, AuthenticatorTransport::Internal]
+            };
+            AllowCredentials {
+                type_: "public-key".to_string(),
+                id: id.to_vec().into(),
+                transports: Some(transports),
+            }
+        })
+        .collect();
+    session.insert(DECOY_AUTH_KEY, true).await?;
+    Ok(rcr)
+}
+
 pub async fn finish_authentication(
     Extension(app_state): Extension<AppState>,
     session: Session,
     Json(auth): Json<PublicKeyCredential>,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
+    if session.remove::<bool>(DECOY_AUTH_KEY).await?.is_some() {
+        error!("Refusing assertion for a decoy authentication challenge");
+        return Ok(StatusCode::BAD_REQUEST);
+    }
     let (user_unique_id, auth_state): (Uuid, PasskeyAuthentication) = session
         .get("auth_state")
         .await?
@@ -215,46 +282,40 @@ pub async fn finish_authentication(
 
     let res = match app_state.webauthn.finish_passkey_authentication(&auth, &auth_state) {
         Ok(auth_result) => {
-            let collection = app_state.users_collection();
-            let user = match collection.find_one(doc! { "unique_id": user_unique_id.to_string() }).await {
+            let user = match app_state.users.get(user_unique_id).await {
+                Ok(Some(user)) if user.disabled => {
+                    error!("Refusing authentication for disabled user with UUID {}", user_unique_id);
+                    return Err(ApiError::AccountDisabled);
+                }
                 Ok(Some(user)) => {
                     info!("Found user with UUID {} for authentication update", user_unique_id);
                     user
                 }
                 Ok(None) => {
                     error!("No user found with UUID {} during authentication update", user_unique_id);
-                    return Err(WebauthnError::UserHasNoCredentials);
+                    return Err(WebauthnError::UserHasNoCredentials.into());
                 }
                 Err(e) => {
                     error!("Failed to query user with UUID {}: {:?}", user_unique_id, e);
-                    return Err(WebauthnError::MongoDBError(e));
+                    return Err(e);
                 }
             };
 
-            let mut user_data = user;
-            for sk in user_data.passkeys.iter_mut() {
-                sk.update_credential(&auth_result);
-            }
-
-            match collection
-                .update_one(
-                    doc! { "unique_id": user_unique_id.to_string() },
-                    doc! { "$set": { "passkeys": mongodb::bson::to_bson(&user_data.passkeys)? } },
-                )
-                .await
-            {
-                Ok(_) => {
-                    info!("Updated passkeys for user with UUID {} in MongoDB", user_unique_id);
+            match app_state.users.update_credential(user.unique_id, &auth_result).await {
+                Ok(false) => {
+                    warn!("Passkey of UUID {} was revoked during authentication", user_unique_id);
+                    StatusCode::BAD_REQUEST
+                }
+                Ok(true) => {
+                    info!("Updated passkey for user with UUID {}", user_unique_id);
+                    passkeys::touch_passkey(&app_state, user_unique_id, auth_result.cred_id()).await?;
                     // Set user_id in session after successful authentication
-                    if let Err(e) = session.insert("user_id", user_unique_id).await {
-                        error!("Failed to set user_id in session: {:?}", e);
-                        return Err(WebauthnError::InvalidSessionState(e));
-                    }
+                    sessions::start_session(&app_state, &session, user_unique_id).await?;
                     StatusCode::OK
                 }
                 Err(e) => {
-                    error!("Failed to update passkeys for UUID {} in MongoDB: {:?}", user_unique_id, e);
-                    return Err(WebauthnError::MongoDBError(e));
+                    error!("Failed to update passkey for UUID {}: {:?}", user_unique_id, e);
+                    return Err(e);
                 }
             }
         }
@@ -267,47 +328,227 @@ pub async fn finish_authentication(
     Ok(res)
 }
 
-use serde::Serialize;
+#[derive(Deserialize)]
+pub struct DiscoverableLoginQuery {
+    // Set to false when the login is triggered by a button rather than
+    // offered through the browser's autofill
+    #[serde(default = "default_true")]
+    pub conditional: bool,
+}
+
+// Starts a username-free login: the challenge has no allow-list, so the
+// browser offers whichever discoverable credential the user has for this site.
+pub async fn start_discoverable_authentication(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Query(query): Query<DiscoverableLoginQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Err(e) = session.remove_value("discoverable_auth_state").await {
+        error!("Failed to remove previous discoverable_auth_state from session: {:?}", e);
+    }
+
+    match app_state.webauthn.start_discoverable_authentication() {
+        Ok((mut rcr, auth_state)) => {
+            if !query.conditional {
+                rcr.mediation = None;
+            }
+            session.insert("discoverable_auth_state", auth_state).await?;
+            info!("Discoverable authentication challenge generated");
+            Ok(Json(rcr))
+        }
+        Err(e) => {
+            error!("Failed to start discoverable authentication: {:?}", e);
+            Err(WebauthnError::Unknown.into())
+        }
+    }
+}
+
+// Identifies the account from the credential's user handle, which is the
+// `unique_id` given at registration. Unknown handles fail the same way as a
+// bad assertion so the endpoint does not reveal which accounts exist.
+pub async fn finish_discoverable_authentication(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Json(auth): Json<PublicKeyCredential>,
+) -> Result<impl IntoResponse, ApiError> {
+    let auth_state: DiscoverableAuthentication = session
+        .remove("discoverable_auth_state")
+        .await?
+        .ok_or_else(|| {
+            error!("No discoverable_auth_state found in session");
+            WebauthnError::CorruptSession
+        })?;
+
+    let user_unique_id = match app_state.webauthn.identify_discoverable_authentication(&auth) {
+        Ok((user_unique_id, _)) => user_unique_id,
+        Err(e) => {
+            error!("Discoverable credential carried no usable user handle: {:?}", e);
+            return Ok(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    let Some(user) = app_state.users.get(user_unique_id).await? else {
+        error!("No user found for discoverable credential with UUID {}", user_unique_id);
+        return Ok(StatusCode::BAD_REQUEST);
+    };
+
+    let keys: Vec<DiscoverableKey> = user.passkeys.iter().map(DiscoverableKey::from).collect();
+    let auth_result = match app_state.webauthn.finish_discoverable_authentication(&auth, auth_state, &keys) {
+        Ok(auth_result) => auth_result,
+        Err(e) => {
+            error!("Failed to finish discoverable authentication for UUID {}: {:?}", user_unique_id, e);
+            return Ok(StatusCode::BAD_REQUEST);
+        }
+    };
+    // Only told once the caller has proven they hold the passkey
+    if user.disabled {
+        error!("Refusing authentication for disabled user with UUID {}", user_unique_id);
+        return Err(ApiError::AccountDisabled);
+    }
+
+    if !app_state.users.update_credential(user_unique_id, &auth_result).await? {
+        warn!("Passkey of UUID {} was revoked during authentication", user_unique_id);
+        return Ok(StatusCode::BAD_REQUEST);
+    }
+    passkeys::touch_passkey(&app_state, user_unique_id, auth_result.cred_id()).await?;
+
+    sessions::start_session(&app_state, &session, user_unique_id).await?;
+    info!("Discoverable authentication completed for {}", user.username);
+    Ok(StatusCode::OK)
+}
 
 #[derive(Serialize)]
 struct UserResponse {
     id: String,
     username: String,
+    #[serde(rename = "isAdmin")]
+    is_admin: bool,
 }
 
 pub async fn get_current_user(
     Extension(app_state): Extension<AppState>,
     session: Session,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
     let user_unique_id: Uuid = session
         .get("user_id")
         .await?
         .ok_or_else(|| {
-            error!("No user_id found in session for user fetch");
-            WebauthnError::CorruptSession
+            info!("No user_id found in session for user fetch");
+            ApiError::Unauthenticated
         })?;
 
-    let collection = app_state.users_collection();
-    let user = collection
-        .find_one(doc! { "unique_id": user_unique_id.to_string() })
-        .await
-        .map_err(|e| WebauthnError::MongoDBError(e))?
+    let user = app_state
+        .users
+        .get(user_unique_id)
+        .await?
         .ok_or_else(|| {
             error!("No user found with UUID {}", user_unique_id);
-            WebauthnError::UserNotFound
+            ApiError::NotFound("user")
         })?;
+    if user.disabled {
+        error!("User with UUID {} is disabled", user_unique_id);
+        return Err(ApiError::AccountDisabled);
+    }
 
     let response = UserResponse {
         id: user.unique_id.to_string(),
         username: user.username,
+        is_admin: user.is_admin,
     };
     Ok(Json(response))
 }
 
 pub async fn logout(
+    Extension(app_state): Extension<AppState>,
     session: Session,
-) -> Result<impl IntoResponse, WebauthnError> {
+) -> Result<impl IntoResponse, ApiError> {
+    sessions::forget_session(&app_state, &session).await?;
     session.clear().await; // No Result to handle, just call it
     info!("Session cleared successfully during logout");
     Ok(StatusCode::OK)
-}
\ No newline at end of file
+}
+
+fn reauth_window_seconds() -> i64 {
+    static WINDOW: OnceLock<i64> = OnceLock::new();
+    *WINDOW.get_or_init(|| {
+        let minutes = match env::var("REAUTH_WINDOW_MINUTES") {
+            Ok(value) => value.parse().ok().filter(|minutes| *minutes > 0).unwrap_or_else(|| {
+                warn!("Invalid REAUTH_WINDOW_MINUTES {:?}, using {}", value, DEFAULT_REAUTH_WINDOW_MINUTES);
+                DEFAULT_REAUTH_WINDOW_MINUTES
+            }),
+            Err(_) => DEFAULT_REAUTH_WINDOW_MINUTES,
+        };
+        info!("Sensitive actions require a passkey assertion from the last {} minutes", minutes);
+        minutes * 60
+    })
+}
+
+// Fails with `ReauthRequired` unless the session performed a passkey assertion
+// (login or `/reauth_finish`) within the reauthentication window.
+pub async fn require_recent_auth(session: &Session) -> Result<(), ApiError> {
+    let authenticated_at: Option<i64> = session.get(AUTHENTICATED_AT_KEY).await?;
+    match authenticated_at {
+        Some(at) if Utc::now().timestamp() - at <= reauth_window_seconds() => Ok(()),
+        _ => {
+            info!("Fresh passkey assertion required");
+            Err(ApiError::ReauthRequired)
+        }
+    }
+}
+
+pub async fn start_reauth(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Err(e) = session.remove_value("reauth_state").await {
+        error!("Failed to remove previous reauth_state from session: {:?}", e);
+    }
+
+    let user = app_state.users.get(user_unique_id).await?.ok_or(ApiError::NotFound("user"))?;
+
+    match app_state.webauthn.start_passkey_authentication(&user.passkeys) {
+        Ok((rcr, auth_state)) => {
+            session.insert("reauth_state", auth_state).await?;
+            info!("Reauthentication challenge generated for UUID {}", user_unique_id);
+            Ok(Json(rcr))
+        }
+        Err(e) => {
+            error!("Failed to start reauthentication for UUID {}: {:?}", user_unique_id, e);
+            Err(WebauthnError::Unknown.into())
+        }
+    }
+}
+
+pub async fn finish_reauth(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+    Json(auth): Json<PublicKeyCredential>,
+) -> Result<impl IntoResponse, ApiError> {
+    let auth_state: PasskeyAuthentication = session
+        .remove("reauth_state")
+        .await?
+        .ok_or_else(|| {
+            error!("No reauth_state found in session for UUID {}", user_unique_id);
+            WebauthnError::CorruptSession
+        })?;
+
+    let auth_result = match app_state.webauthn.finish_passkey_authentication(&auth, &auth_state) {
+        Ok(auth_result) => auth_result,
+        Err(e) => {
+            error!("Failed to finish reauthentication for UUID {}: {:?}", user_unique_id, e);
+            return Ok(StatusCode::BAD_REQUEST);
+        }
+    };
+
+    if !app_state.users.update_credential(user_unique_id, &auth_result).await? {
+        warn!("Passkey of UUID {} was revoked during reauthentication", user_unique_id);
+        return Ok(StatusCode::BAD_REQUEST);
+    }
+    passkeys::touch_passkey(&app_state, user_unique_id, auth_result.cred_id()).await?;
+
+    session.insert(AUTHENTICATED_AT_KEY, Utc::now().timestamp()).await?;
+    info!("Reauthentication completed for UUID {}", user_unique_id);
+    Ok(StatusCode::OK)
+}
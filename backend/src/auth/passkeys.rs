@@ -0,0 +1,330 @@
+// src/auth/passkeys.rs
+use axum::{
+    extract::{Extension, Json, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use uuid::Uuid;
+use webauthn_rs::prelude::*;
+use webauthn_rs_proto::ResidentKeyRequirement;
+use crate::auth::authenticators::{authenticator_name, registration_aaguid};
+use crate::auth::policy::AttestationDetails;
+use crate::auth::require_recent_auth;
+use crate::auth::tokens::SessionUser;
+use crate::error::{ApiError, WebauthnError};
+use crate::startup::{AppState, PasskeyMeta, UserData};
+
+const MAX_NICKNAME_LENGTH: usize = 64;
+
+#[derive(Deserialize)]
+pub struct RenamePasskeyRequest {
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct PasskeyResponse {
+    pub id: String,
+    pub name: String,
+    pub nickname: Option<String>,
+    pub authenticator: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<String>,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/passkeys", get(list_passkeys))
+        .route("/api/passkeys/add_start", post(start_add_passkey))
+        .route("/api/passkeys/add_finish", post(finish_add_passkey))
+        .route("/api/passkeys/:cred_id/rename", post(rename_passkey))
+        .route("/api/passkeys/:cred_id/revoke", post(revoke_passkey))
+}
+
+// The identifier used for a credential in URLs and `PasskeyMeta`
+pub fn credential_key(cred_id: &CredentialID) -> String {
+    URL_SAFE_NO_PAD.encode(cred_id.as_ref())
+}
+
+// A registration in progress. Attested when the policy pins authenticator
+// models, so the AAGUID is taken from a verified certificate chain.
+#[derive(Serialize, Deserialize)]
+pub enum PendingRegistration {
+    Passkey(PasskeyRegistration),
+    Attested(AttestedPasskeyRegistration),
+}
+
+// Starts a registration ceremony under the authenticator policy
+pub fn start_registration(
+    app_state: &AppState,
+    user_unique_id: Uuid,
+    username: &str,
+    exclude_credentials: Option<Vec<CredentialID>>,
+) -> WebauthnResult<(CreationChallengeResponse, PendingRegistration)> {
+    let (mut ccr, state) = match app_state.auth_policy.attestation_cas() {
+        Some(cas) => {
+            let (ccr, state) = app_state.webauthn.start_attested_passkey_registration(
+                user_unique_id,
+                username,
+                username,
+                exclude_credentials,
+                cas.clone(),
+                None,
+            )?;
+            (ccr, PendingRegistration::Attested(state))
+        }
+        None => {
+            let (ccr, state) =
+                app_state.webauthn.start_passkey_registration(user_unique_id, username, username, exclude_credentials)?;
+            (ccr, PendingRegistration::Passkey(state))
+        }
+    };
+    prepare_registration(app_state, &mut ccr);
+    Ok((ccr, state))
+}
+
+// Verifies the authenticator's response, returning the passkey and the AAGUID
+// its attestation vouched for, if it was attested
+pub fn finish_registration(
+    app_state: &AppState,
+    reg: &RegisterPublicKeyCredential,
+    state: &PendingRegistration,
+) -> WebauthnResult<(Passkey, Option<Uuid>)> {
+    match state {
+        PendingRegistration::Passkey(state) => Ok((app_state.webauthn.finish_passkey_registration(reg, state)?, None)),
+        PendingRegistration::Attested(state) => {
+            let attested = app_state.webauthn.finish_attested_passkey_registration(reg, state)?;
+            let aaguid = match attested.attestation().metadata {
+                AttestationMetadata::Packed { aaguid } | AttestationMetadata::Tpm { aaguid, .. } => Some(aaguid),
+                _ => None,
+            };
+            Ok((Passkey::from(attested), aaguid))
+        }
+    }
+}
+
+// Asks for a discoverable credential, so the account can later sign in
+// without typing a username, and for the attestation the policy wants.
+// Authenticators that cannot store a discoverable credential still register
+// a regular passkey.
+fn prepare_registration(app_state: &AppState, ccr: &mut CreationChallengeResponse) {
+    if let Some(selection) = ccr.public_key.authenticator_selection.as_mut() {
+        selection.resident_key = Some(ResidentKeyRequirement::Preferred);
+    }
+    app_state.auth_policy.apply(ccr);
+}
+
+// Enforces the authenticator policy on a just-registered passkey and
+// describes it for `passkey_meta`. The allow-list only trusts the AAGUID from
+// `finish_registration`; the claimed one is kept to name the authenticator.
+pub fn check_new_passkey(
+    app_state: &AppState,
+    passkey: &Passkey,
+    reg: &RegisterPublicKeyCredential,
+    verified_aaguid: Option<Uuid>,
+) -> Result<PasskeyMeta, ApiError> {
+    let aaguid = registration_aaguid(reg);
+    let details = AttestationDetails::of(passkey);
+    app_state.auth_policy.check(&details, verified_aaguid)?;
+    Ok(PasskeyMeta {
+        cred_id: credential_key(passkey.cred_id()),
+        nickname: None,
+        aaguid: aaguid.map(|aaguid| aaguid.to_string()),
+        created_at: Some(mongodb::bson::DateTime::now()),
+        last_used_at: None,
+        attestation_format: Some(details.format.to_string()),
+        attestation_type: Some(details.kind.to_string()),
+        user_verified: Some(details.user_verified),
+        backup_eligible: Some(details.backup_eligible),
+    })
+}
+
+// Records that a credential was just used. Credentials registered before
+// metadata was kept get an entry on first use.
+pub async fn touch_passkey(app_state: &AppState, user_unique_id: Uuid, cred_id: &CredentialID) -> Result<(), ApiError> {
+    app_state.users.touch_passkey(user_unique_id, &credential_key(cred_id)).await
+}
+
+async fn load_session_user(app_state: &AppState, session_user: SessionUser) -> Result<UserData, ApiError> {
+    app_state.users.get(session_user.id).await?.ok_or(ApiError::NotFound("user"))
+}
+
+fn passkey_response(key: String, meta: Option<&PasskeyMeta>) -> PasskeyResponse {
+    let format = |t: Option<mongodb::bson::DateTime>| t.and_then(|t| t.try_to_rfc3339_string().ok());
+    let nickname = meta.and_then(|m| m.nickname.clone());
+    let authenticator = meta
+        .and_then(|m| m.aaguid.as_deref())
+        .and_then(|aaguid| Uuid::parse_str(aaguid).ok())
+        .and_then(|aaguid| authenticator_name(&aaguid))
+        .map(str::to_string);
+    PasskeyResponse {
+        id: key,
+        name: nickname
+            .clone()
+            .or_else(|| authenticator.clone())
+            .unwrap_or_else(|| "Passkey".to_string()),
+        nickname,
+        authenticator,
+        created_at: format(meta.and_then(|m| m.created_at)),
+        last_used_at: format(meta.and_then(|m| m.last_used_at)),
+    }
+}
+
+// The user's passkeys without key material
+pub fn passkey_summaries(user: &UserData) -> Vec<PasskeyResponse> {
+    user.passkeys
+        .iter()
+        .map(|sk| {
+            let key = credential_key(sk.cred_id());
+            let meta = user.passkey_meta.iter().find(|m| m.cred_id == key);
+            passkey_response(key, meta)
+        })
+        .collect()
+}
+
+pub async fn list_passkeys(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+
+    Ok(Json(passkey_summaries(&user)))
+}
+
+// Registers another passkey for the logged-in user. Requires a recent passkey
+// assertion so an unattended session cannot be used to plant a credential.
+pub async fn start_add_passkey(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    require_recent_auth(&session).await?;
+
+    if let Err(e) = session.remove_value("add_passkey_state").await {
+        error!("Failed to remove previous add_passkey_state from session: {:?}", e);
+    }
+
+    let exclude_credentials: Vec<CredentialID> = user.passkeys.iter().map(|sk| sk.cred_id().clone()).collect();
+    match start_registration(&app_state, user.unique_id, &user.username, Some(exclude_credentials)) {
+        Ok((ccr, reg_state)) => {
+            session.insert("add_passkey_state", (user.unique_id, reg_state)).await?;
+            info!("Passkey registration started for existing user {}", user.username);
+            Ok(Json(ccr))
+        }
+        Err(e) => {
+            error!("Failed to start passkey registration for {}: {:?}", user.username, e);
+            Err(WebauthnError::Unknown.into())
+        }
+    }
+}
+
+pub async fn finish_add_passkey(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Json(reg): Json<RegisterPublicKeyCredential>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    let (state_user_id, reg_state): (Uuid, PendingRegistration) = session
+        .remove("add_passkey_state")
+        .await?
+        .ok_or_else(|| {
+            error!("No add_passkey_state found in session for {}", user.username);
+            WebauthnError::CorruptSession
+        })?;
+    if state_user_id != user.unique_id {
+        error!("add_passkey_state belongs to a different user than {}", user.username);
+        return Err(WebauthnError::CorruptSession.into());
+    }
+
+    let (passkey, verified_aaguid) = match finish_registration(&app_state, &reg, &reg_state) {
+        Ok(registered) => registered,
+        Err(e) => {
+            error!("Failed to finish passkey registration for {}: {:?}", user.username, e);
+            return Ok(StatusCode::BAD_REQUEST);
+        }
+    };
+    let meta = check_new_passkey(&app_state, &passkey, &reg, verified_aaguid)?;
+
+    app_state.users.add_passkey(user.unique_id, &passkey, &meta).await?;
+    info!("Added passkey {} to user {}", meta.cred_id, user.username);
+    Ok(StatusCode::OK)
+}
+
+pub async fn rename_passkey(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    Path(cred_id): Path<String>,
+    Json(request): Json<RenamePasskeyRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    if !user.passkeys.iter().any(|sk| credential_key(sk.cred_id()) == cred_id) {
+        warn!("User {} has no passkey {}", user.unique_id, cred_id);
+        return Err(ApiError::NotFound("passkey"));
+    }
+
+    let name = request.name.trim();
+    if name.chars().count() > MAX_NICKNAME_LENGTH {
+        return Err(ApiError::validation("name", "Name must be at most 64 characters"));
+    }
+    // An empty name clears the nickname
+    let nickname = (!name.is_empty()).then(|| name.to_string());
+
+    let mut meta = user
+        .passkey_meta
+        .iter()
+        .find(|m| m.cred_id == cred_id)
+        .cloned()
+        .unwrap_or_else(|| PasskeyMeta::unknown(cred_id.clone()));
+    meta.nickname = nickname;
+
+    app_state.users.save_passkey_meta(user.unique_id, &meta).await?;
+    info!("User {} renamed passkey {}", user.unique_id, cred_id);
+    Ok(Json(passkey_response(cred_id, Some(&meta))))
+}
+
+pub async fn revoke_passkey(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Path(cred_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    require_recent_auth(&session).await?;
+
+    let remaining: Vec<_> = user
+        .passkeys
+        .iter()
+        .filter(|sk| credential_key(sk.cred_id()) != cred_id)
+        .cloned()
+        .collect();
+    if remaining.len() == user.passkeys.len() {
+        warn!("User {} has no passkey {}", user.unique_id, cred_id);
+        return Err(ApiError::NotFound("passkey"));
+    }
+    if remaining.is_empty() {
+        error!("Refusing to revoke the last passkey of user {}", user.unique_id);
+        return Err(ApiError::conflict("last_passkey", "The last passkey cannot be removed"));
+    }
+    let remaining_meta: Vec<PasskeyMeta> = user.passkey_meta.iter().filter(|m| m.cred_id != cred_id).cloned().collect();
+
+    // Matching on the current passkey count keeps two concurrent revocations
+    // from removing the last credential between them.
+    let replaced = app_state
+        .users
+        .replace_passkeys(user.unique_id, user.passkeys.len(), &remaining, &remaining_meta)
+        .await?;
+    if !replaced {
+        warn!("Passkeys of user {} changed during revocation", user.unique_id);
+        return Err(ApiError::conflict("passkeys_changed", "Passkeys changed meanwhile, try again"));
+    }
+    info!("User {} revoked passkey {}", user.unique_id, cred_id);
+    Ok(StatusCode::OK)
+}
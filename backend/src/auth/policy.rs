@@ -0,0 +1,235 @@
+// src/auth/policy.rs
+use serde::Deserialize;
+use std::env;
+use uuid::Uuid;
+use webauthn_rs::prelude::*;
+use webauthn_rs_proto::AttestationConveyancePreference;
+use crate::error::ApiError;
+
+// Which authenticators may register passkeys. Loaded from the JSON file
+// named by AUTH_POLICY_FILE; without one, any authenticator is accepted.
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthenticatorPolicy {
+    // Passkey ceremonies always ask for user verification; this also rejects
+    // credentials whose registration did not report it
+    pub require_user_verification: bool,
+    // "none", "indirect" or "direct"
+    pub attestation: AttestationConveyancePreference,
+    // Reject registrations without an attestation statement that chains to
+    // `attestation_ca_files` (needs `allowed_aaguids` as well)
+    pub require_attestation: bool,
+    // Empty allows any authenticator model. Otherwise registrations must
+    // carry an attestation that chains to one of `attestation_ca_files`, and
+    // only the AAGUID that attestation vouches for is checked.
+    pub allowed_aaguids: Vec<Uuid>,
+    // PEM files of the vendor attestation roots trusted for `allowed_aaguids`
+    pub attestation_ca_files: Vec<String>,
+    // False restricts registration to device-bound credentials
+    pub allow_synced_passkeys: bool,
+    #[serde(skip)]
+    attestation_cas: Option<AttestationCaList>,
+}
+
+impl Default for AuthenticatorPolicy {
+    fn default() -> Self {
+        Self {
+            require_user_verification: true,
+            attestation: AttestationConveyancePreference::None,
+            require_attestation: false,
+            allowed_aaguids: Vec::new(),
+            attestation_ca_files: Vec::new(),
+            allow_synced_passkeys: true,
+            attestation_cas: None,
+        }
+    }
+}
+
+// What a passkey's registration proved about its authenticator
+pub struct AttestationDetails {
+    pub format: &'static str,
+    pub kind: &'static str,
+    pub user_verified: bool,
+    pub backup_eligible: bool,
+}
+
+impl AttestationDetails {
+    pub fn of(passkey: &Passkey) -> Self {
+        let credential = Credential::from(passkey.clone());
+        let format = match credential.attestation_format {
+            AttestationFormat::Packed => "packed",
+            AttestationFormat::Tpm => "tpm",
+            AttestationFormat::AndroidKey => "android-key",
+            AttestationFormat::AndroidSafetyNet => "android-safetynet",
+            AttestationFormat::FIDOU2F => "fido-u2f",
+            AttestationFormat::AppleAnonymous => "apple",
+            AttestationFormat::None => "none",
+        };
+        let kind = match credential.attestation.data {
+            ParsedAttestationData::Basic(_) => "basic",
+            ParsedAttestationData::Self_ => "self",
+            ParsedAttestationData::AttCa(_) => "attca",
+            ParsedAttestationData::AnonCa(_) => "anonca",
+            ParsedAttestationData::ECDAA => "ecdaa",
+            ParsedAttestationData::None => "none",
+            ParsedAttestationData::Uncertain => "uncertain",
+        };
+        Self {
+            format,
+            kind,
+            user_verified: credential.user_verified,
+            backup_eligible: credential.backup_eligible,
+        }
+    }
+
+    fn is_attested(&self) -> bool {
+        !matches!(self.kind, "none" | "self" | "uncertain")
+    }
+}
+
+impl AuthenticatorPolicy {
+    pub fn load() -> Self {
+        let Ok(path) = env::var("AUTH_POLICY_FILE") else {
+            info!("No AUTH_POLICY_FILE set, accepting any authenticator");
+            return Self::default();
+        };
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read AUTH_POLICY_FILE {}: {}", path, e));
+        let mut policy: Self = serde_json::from_str(&contents)
+            .unwrap_or_else(|e| panic!("Invalid AUTH_POLICY_FILE {}: {}", path, e));
+        policy.validate().unwrap_or_else(|e| panic!("Invalid AUTH_POLICY_FILE {}: {}", path, e));
+        policy.attestation_cas = policy.build_attestation_cas();
+        info!("Loaded authenticator policy from {}: {:?}", path, policy);
+        policy
+    }
+
+    // Attestation can only be verified against trusted roots, and the roots
+    // are only trusted for named authenticator models. An allow-list without
+    // roots could only be checked against the AAGUID the client claims, and
+    // required attestation without them would accept any self-made chain.
+    fn validate(&self) -> Result<(), &'static str> {
+        if !self.allowed_aaguids.is_empty() && self.attestation_ca_files.is_empty() {
+            return Err("allowed_aaguids requires attestation_ca_files");
+        }
+        if self.require_attestation && self.attestation_ca_files.is_empty() {
+            return Err("require_attestation requires attestation_ca_files");
+        }
+        if self.require_attestation && self.allowed_aaguids.is_empty() {
+            return Err("require_attestation requires allowed_aaguids to trust attestation_ca_files for");
+        }
+        Ok(())
+    }
+
+    // Trusts each configured root for every allowed AAGUID
+    fn build_attestation_cas(&self) -> Option<AttestationCaList> {
+        if self.allowed_aaguids.is_empty() {
+            return None;
+        }
+        let mut builder = AttestationCaListBuilder::new();
+        for path in &self.attestation_ca_files {
+            let pem = std::fs::read(path)
+                .unwrap_or_else(|e| panic!("Failed to read attestation CA {}: {}", path, e));
+            for aaguid in &self.allowed_aaguids {
+                builder
+                    .insert_device_pem(&pem, *aaguid, path.clone(), Default::default())
+                    .unwrap_or_else(|e| panic!("Invalid attestation CA {}: {:?}", path, e));
+            }
+        }
+        Some(builder.build())
+    }
+
+    // The roots registrations must be attested by, when models are pinned
+    pub fn attestation_cas(&self) -> Option<&AttestationCaList> {
+        self.attestation_cas.as_ref()
+    }
+
+    // Adjusts a registration challenge to ask for what the policy checks.
+    // Attested registrations already ask for direct attestation.
+    pub fn apply(&self, ccr: &mut CreationChallengeResponse) {
+        if self.attestation_cas.is_none() {
+            ccr.public_key.attestation = Some(self.attestation.clone());
+        }
+    }
+
+    // Rejects a freshly registered passkey that does not meet the policy.
+    // `aaguid` must come from a verified attestation, never from the
+    // client's unverified authenticator data.
+    pub fn check(&self, details: &AttestationDetails, aaguid: Option<Uuid>) -> Result<(), ApiError> {
+        if self.require_user_verification && !details.user_verified {
+            error!("Rejecting passkey registered without user verification");
+            return Err(ApiError::AuthenticatorNotAllowed);
+        }
+        if self.require_attestation && !details.is_attested() {
+            error!("Rejecting passkey with {} attestation ({})", details.kind, details.format);
+            return Err(ApiError::AuthenticatorNotAllowed);
+        }
+        if !self.allowed_aaguids.is_empty() && !aaguid.is_some_and(|aaguid| self.allowed_aaguids.contains(&aaguid)) {
+            error!("Rejecting passkey from authenticator {:?} not on the allow-list", aaguid);
+            return Err(ApiError::AuthenticatorNotAllowed);
+        }
+        if !self.allow_synced_passkeys && details.backup_eligible {
+            error!("Rejecting synced passkey");
+            return Err(ApiError::AuthenticatorNotAllowed);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(json: &str) -> AuthenticatorPolicy {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn details(kind: &'static str, user_verified: bool, backup_eligible: bool) -> AttestationDetails {
+        AttestationDetails { format: "packed", kind, user_verified, backup_eligible }
+    }
+
+    #[test]
+    fn missing_fields_take_the_permissive_defaults() {
+        let policy = policy(r#"{ "attestation": "direct" }"#);
+        assert!(matches!(policy.attestation, AttestationConveyancePreference::Direct));
+        assert!(policy.require_user_verification && policy.allow_synced_passkeys);
+        assert!(policy.validate().is_ok());
+        assert!(serde_json::from_str::<AuthenticatorPolicy>(r#"{ "allowed_aaguid": [] }"#).is_err());
+    }
+
+    #[test]
+    fn attestation_settings_need_trusted_roots() {
+        let aaguid = Uuid::new_v4();
+        assert!(policy(&format!(r#"{{ "allowed_aaguids": ["{}"] }}"#, aaguid)).validate().is_err());
+        assert!(policy(r#"{ "require_attestation": true, "attestation_ca_files": ["ca.pem"] }"#).validate().is_err());
+        assert!(policy(r#"{ "require_attestation": true }"#).validate().is_err());
+        let pinned = format!(
+            r#"{{ "require_attestation": true, "allowed_aaguids": ["{}"], "attestation_ca_files": ["ca.pem"] }}"#,
+            aaguid
+        );
+        assert!(policy(&pinned).validate().is_ok());
+    }
+
+    #[test]
+    fn check_enforces_each_rule() {
+        let rejected = |policy: &AuthenticatorPolicy, details: &AttestationDetails, aaguid: Option<Uuid>| {
+            matches!(policy.check(details, aaguid), Err(ApiError::AuthenticatorNotAllowed))
+        };
+        let default = AuthenticatorPolicy::default();
+        assert!(default.check(&details("none", true, true), None).is_ok());
+        assert!(rejected(&default, &details("none", false, true), None));
+
+        let attested = AuthenticatorPolicy { require_attestation: true, ..AuthenticatorPolicy::default() };
+        assert!(rejected(&attested, &details("self", true, false), None));
+        assert!(attested.check(&details("basic", true, false), None).is_ok());
+
+        let aaguid = Uuid::new_v4();
+        let pinned = AuthenticatorPolicy { allowed_aaguids: vec![aaguid], ..AuthenticatorPolicy::default() };
+        assert!(pinned.check(&details("basic", true, false), Some(aaguid)).is_ok());
+        assert!(rejected(&pinned, &details("basic", true, false), Some(Uuid::new_v4())));
+        assert!(rejected(&pinned, &details("basic", true, false), None));
+
+        let device_bound = AuthenticatorPolicy { allow_synced_passkeys: false, ..AuthenticatorPolicy::default() };
+        assert!(rejected(&device_bound, &details("none", true, true), None));
+        assert!(device_bound.check(&details("none", true, false), None).is_ok());
+    }
+}
@@ -0,0 +1,204 @@
+// src/auth/recovery.rs
+use axum::{
+    extract::{Extension, Json},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tower_sessions::Session;
+use uuid::Uuid;
+use webauthn_rs::prelude::*;
+use crate::auth::tokens::SessionUser;
+use crate::auth::{passkeys, require_recent_auth, sessions};
+use crate::error::{ApiError, WebauthnError};
+use crate::startup::AppState;
+
+const CODE_COUNT: usize = 10;
+const CODE_LENGTH: usize = 10;
+// No 0/O or 1/I, so codes survive being copied by hand
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[derive(Deserialize)]
+pub struct RecoverStartRequest {
+    pub username: String,
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RecoveryStatusResponse {
+    pub remaining: usize,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/recover_start", post(start_recovery))
+        .route("/recover_finish", post(finish_recovery))
+        .route("/api/recovery_codes", get(get_recovery_status))
+        .route("/api/recovery_codes/regenerate", post(regenerate_codes))
+}
+
+// Returns the codes to show the user and the hashes to store
+pub fn generate_codes() -> (Vec<String>, Vec<String>) {
+    let mut rng = rand::thread_rng();
+    let codes: Vec<String> = (0..CODE_COUNT)
+        .map(|_| {
+            let raw: String = (0..CODE_LENGTH)
+                .map(|_| CODE_ALPHABET[rng.gen_range(0..CODE_ALPHABET.len())] as char)
+                .collect();
+            format!("{}-{}", &raw[..CODE_LENGTH / 2], &raw[CODE_LENGTH / 2..])
+        })
+        .collect();
+    let hashes = codes.iter().map(|code| hash_code(code)).collect();
+    (codes, hashes)
+}
+
+// Codes are random enough that an unsalted digest is safe to store.
+// Separators, spacing and case are ignored.
+fn hash_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+// Checks a recovery code and starts registering a replacement passkey. The
+// code is only consumed once that passkey is saved.
+pub async fn start_recovery(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Json(request): Json<RecoverStartRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Err(e) = session.remove_value("recovery_state").await {
+        error!("Failed to remove previous recovery_state from session: {:?}", e);
+    }
+
+    let code_hash = hash_code(&request.code);
+    // Unknown users and wrong codes fail alike
+    let user = app_state
+        .users
+        .find_by_recovery_code(&request.username, &code_hash)
+        .await?
+        .ok_or_else(|| {
+            error!("Invalid recovery attempt for {}", request.username);
+            ApiError::InvalidRecoveryCode
+        })?;
+    if user.disabled {
+        error!("Refusing recovery for disabled user {}", user.username);
+        return Err(ApiError::AccountDisabled);
+    }
+
+    let exclude_credentials: Vec<CredentialID> = user.passkeys.iter().map(|sk| sk.cred_id().clone()).collect();
+    match passkeys::start_registration(&app_state, user.unique_id, &user.username, Some(exclude_credentials)) {
+        Ok((ccr, reg_state)) => {
+            session.insert("recovery_state", (user.unique_id, code_hash, reg_state)).await?;
+            info!("Recovery started for {}", user.username);
+            Ok(Json(ccr))
+        }
+        Err(e) => {
+            error!("Failed to start recovery registration for {}: {:?}", user.username, e);
+            Err(WebauthnError::Unknown.into())
+        }
+    }
+}
+
+// Saves the replacement passkey, burns the code and signs the user in
+pub async fn finish_recovery(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    Json(reg): Json<RegisterPublicKeyCredential>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (user_unique_id, code_hash, reg_state): (Uuid, String, passkeys::PendingRegistration) = session
+        .remove("recovery_state")
+        .await?
+        .ok_or_else(|| {
+            error!("No recovery_state found in session");
+            WebauthnError::CorruptSession
+        })?;
+
+    let (passkey, verified_aaguid) = match passkeys::finish_registration(&app_state, &reg, &reg_state) {
+        Ok(registered) => registered,
+        Err(e) => {
+            error!("Failed to finish recovery registration for UUID {}: {:?}", user_unique_id, e);
+            return Ok(StatusCode::BAD_REQUEST);
+        }
+    };
+    let meta = passkeys::check_new_passkey(&app_state, &passkey, &reg, verified_aaguid)?;
+
+    // Burning the code with the same write makes a concurrent recovery with it fail
+    let added = app_state
+        .users
+        .add_passkey_with_recovery_code(user_unique_id, &code_hash, &passkey, &meta)
+        .await?;
+    if !added {
+        error!("Recovery code for UUID {} was already used", user_unique_id);
+        return Err(ApiError::InvalidRecoveryCode);
+    }
+
+    sessions::start_session(&app_state, &session, user_unique_id).await?;
+    info!("Recovery completed for UUID {}", user_unique_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn get_recovery_status(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = app_state.users.get(user_unique_id).await?.ok_or(ApiError::NotFound("user"))?;
+    Ok(Json(RecoveryStatusResponse { remaining: user.recovery_codes.len() }))
+}
+
+// Replaces all recovery codes, invalidating the old ones
+pub async fn regenerate_codes(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    require_recent_auth(&session).await?;
+
+    let (codes, hashes) = generate_codes();
+    if !app_state.users.set_recovery_codes(user_unique_id, &hashes).await? {
+        return Err(ApiError::NotFound("user"));
+    }
+    info!("Regenerated recovery codes for UUID {}", user_unique_id);
+    Ok(Json(RecoveryCodesResponse { recovery_codes: codes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_codes_match_their_hashes() {
+        let (codes, hashes) = generate_codes();
+        assert_eq!(codes.len(), CODE_COUNT);
+        for (code, hash) in codes.iter().zip(&hashes) {
+            assert_eq!(code.len(), CODE_LENGTH + 1);
+            assert!(code.bytes().filter(|&b| b != b'-').all(|b| CODE_ALPHABET.contains(&b)));
+            assert_eq!(&hash_code(code), hash);
+        }
+        let mut unique = hashes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), CODE_COUNT);
+    }
+
+    #[test]
+    fn hash_ignores_separators_spacing_and_case() {
+        let hash = hash_code("ABCDE-FGHJK");
+        assert_eq!(hash_code("abcde fghjk"), hash);
+        assert_eq!(hash_code(" AbCdEfGhJk\n"), hash);
+        assert_ne!(hash_code("ABCDE-FGHJL"), hash);
+    }
+}
@@ -0,0 +1,312 @@
+// src/auth/sessions.rs
+use std::env;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use axum::{
+    extract::{ConnectInfo, Extension, Json, Path, Request},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Router,
+};
+use chrono::Utc;
+use serde::Serialize;
+use tower_sessions::{session::Id, Session, SessionStore};
+use crate::routes::polls::parse_id;
+use uuid::Uuid;
+use crate::auth::tokens::SessionUser;
+use crate::auth::AUTHENTICATED_AT_KEY;
+use crate::error::ApiError;
+use crate::models::UserSession;
+use crate::startup::AppState;
+
+// Session key holding the unix time the session index was last updated
+const SEEN_AT_KEY: &str = "seen_at";
+// How often a request refreshes `last_seen_at`
+const TOUCH_INTERVAL_SECONDS: i64 = 60;
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "lastSeenAt")]
+    pub last_seen_at: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn from_record(record: UserSession, current_session_id: Option<&str>) -> Self {
+        Self {
+            id: record.id.map(|id| id.to_hex()).unwrap_or_default(),
+            created_at: record.created_at.try_to_rfc3339_string().unwrap_or_default(),
+            last_seen_at: record.last_seen_at.try_to_rfc3339_string().unwrap_or_default(),
+            user_agent: record.user_agent,
+            ip: record.ip,
+            current: current_session_id == Some(record.session_id.as_str()),
+        }
+    }
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/sessions", get(list_sessions).delete(sign_out_everywhere))
+        .route("/api/sessions/:id", delete(revoke_session))
+}
+
+// Signs the session in as the user. The session ID is replaced so an ID
+// planted before login cannot be reused; the old ID's index entry goes with
+// it and the next request indexes the new one.
+pub async fn start_session(
+    app_state: &AppState,
+    session: &Session,
+    user_unique_id: Uuid,
+) -> Result<(), ApiError> {
+    forget_session(app_state, session).await?;
+    session.remove::<i64>(SEEN_AT_KEY).await?;
+    session.insert("user_id", user_unique_id).await?;
+    session.insert(AUTHENTICATED_AT_KEY, Utc::now().timestamp()).await?;
+    session.cycle_id().await?;
+    Ok(())
+}
+
+// Keeps `user_sessions` in step with logged-in sessions and ends sessions
+// whose entry was removed by a remote sign-out. This runs before the handler
+// so a revoked session is already signed out when the handler reads it.
+pub async fn track_session(
+    Extension(app_state): Extension<AppState>,
+    session: Session,
+    headers: HeaderMap,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let ip = client_ip(&headers, connect_info.map(|ConnectInfo(addr)| addr), trusted_proxies());
+    if let Err(e) = record_activity(&app_state, &session, &headers, ip).await {
+        error!("Failed to record session activity: {:?}", e);
+    }
+    next.run(request).await
+}
+
+// Proxy addresses from TRUSTED_PROXIES whose X-Forwarded-For is believed
+fn trusted_proxies() -> &'static [IpAddr] {
+    static PROXIES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+    PROXIES.get_or_init(|| {
+        let Ok(value) = env::var("TRUSTED_PROXIES") else {
+            return Vec::new();
+        };
+        let proxies = parse_proxies(&value);
+        info!("Trusting X-Forwarded-For from {:?}", proxies);
+        proxies
+    })
+}
+
+fn parse_proxies(value: &str) -> Vec<IpAddr> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .filter_map(|ip| {
+            ip.parse()
+                .map_err(|_| warn!("Ignoring invalid TRUSTED_PROXIES entry {:?}", ip))
+                .ok()
+        })
+        .collect()
+}
+
+// The peer address, unless the peer is a trusted proxy. Then it is the
+// nearest X-Forwarded-For entry that is not itself a trusted proxy, since
+// entries further left are whatever the client sent.
+fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, proxies: &[IpAddr]) -> Option<String> {
+    let peer = peer.map(|addr| addr.ip());
+    if !peer.is_some_and(|ip| proxies.contains(&ip)) {
+        return peer.map(|ip| ip.to_string());
+    }
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        // An unparseable entry ends the trusted part of the chain
+        .map_while(|ip| ip)
+        .find(|ip| !proxies.contains(ip))
+        .or(peer)
+        .map(|ip| ip.to_string())
+}
+
+async fn record_activity(
+    app_state: &AppState,
+    session: &Session,
+    headers: &HeaderMap,
+    ip: Option<String>,
+) -> Result<(), ApiError> {
+    let Some(user_unique_id) = session.get::<Uuid>("user_id").await? else {
+        return Ok(());
+    };
+    let Some(session_id) = session.id() else {
+        return Ok(());
+    };
+    let now = Utc::now().timestamp();
+    let seen_at: Option<i64> = session.get(SEEN_AT_KEY).await?;
+    if seen_at.is_some_and(|at| now - at < TOUCH_INTERVAL_SECONDS) {
+        return Ok(());
+    }
+
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    if seen_at.is_some() {
+        let indexed = app_state
+            .user_sessions
+            .touch(&session_id.to_string(), user_agent, ip)
+            .await?;
+        if !indexed {
+            info!("Ending revoked session of UUID {}", user_unique_id);
+            session.flush().await?;
+            return Ok(());
+        }
+    } else {
+        // First request after login, or a session from before sessions were indexed
+        app_state
+            .user_sessions
+            .record(&session_id.to_string(), &user_unique_id.to_string(), user_agent, ip)
+            .await?;
+    }
+    session.insert(SEEN_AT_KEY, now).await?;
+    Ok(())
+}
+
+async fn end_session(app_state: &AppState, record: &UserSession) {
+    match Id::from_str(&record.session_id) {
+        Ok(id) => {
+            if let Err(e) = app_state.session_store.delete(&id).await {
+                error!("Failed to delete session from store: {:?}", e);
+            }
+        }
+        Err(e) => error!("Invalid session id in user_sessions: {:?}", e),
+    }
+}
+
+// Signs the user out of every session. Returns how many were ended.
+pub async fn revoke_user_sessions(app_state: &AppState, user_id: &str) -> Result<usize, ApiError> {
+    let records = app_state.user_sessions.remove_for_user(user_id).await?;
+    for record in &records {
+        end_session(app_state, record).await;
+    }
+    info!("Revoked {} sessions of UUID {}", records.len(), user_id);
+    Ok(records.len())
+}
+
+// Drops the current session's index entry, e.g. on logout
+pub async fn forget_session(app_state: &AppState, session: &Session) -> Result<(), ApiError> {
+    if let Some(session_id) = session.id() {
+        app_state.user_sessions.remove(&session_id.to_string()).await?;
+    }
+    Ok(())
+}
+
+pub async fn list_sessions(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let current_id = session.id().map(|id| id.to_string());
+
+    let records = app_state.user_sessions.list_for_user(&user_unique_id.to_string()).await?;
+    let response: Vec<SessionResponse> = records
+        .into_iter()
+        .map(|record| SessionResponse::from_record(record, current_id.as_deref()))
+        .collect();
+    Ok(Json(response))
+}
+
+pub async fn revoke_session(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = parse_id(&id, "session ID")?;
+
+    let record = app_state
+        .user_sessions
+        .remove_by_id(id, &user_unique_id.to_string())
+        .await?
+        .ok_or_else(|| {
+            warn!("Session {} not found for UUID {}", id, user_unique_id);
+            ApiError::NotFound("session")
+        })?;
+    end_session(&app_state, &record).await;
+    if session.id().map(|id| id.to_string()) == Some(record.session_id) {
+        session.flush().await?;
+    }
+    info!("UUID {} revoked session {}", user_unique_id, id);
+    Ok(StatusCode::OK)
+}
+
+// Signs out every session of the user, including this one
+pub async fn sign_out_everywhere(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    revoke_user_sessions(&app_state, &user_unique_id.to_string()).await?;
+    session.flush().await?;
+    Ok(StatusCode::OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("x-forwarded-for", value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn peer(ip: &str) -> Option<SocketAddr> {
+        Some(SocketAddr::new(ip.parse().unwrap(), 443))
+    }
+
+    #[test]
+    fn invalid_proxy_entries_are_skipped() {
+        let proxies = parse_proxies(" 10.0.0.1, nope,,::1 ");
+        assert_eq!(proxies, vec!["10.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()]);
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_from_untrusted_peers() {
+        let headers = forwarded(&["1.2.3.4"]);
+        assert_eq!(client_ip(&headers, peer("5.6.7.8"), &[]).as_deref(), Some("5.6.7.8"));
+        let proxies = parse_proxies("10.0.0.1");
+        assert_eq!(client_ip(&headers, peer("5.6.7.8"), &proxies).as_deref(), Some("5.6.7.8"));
+        assert_eq!(client_ip(&headers, None, &proxies), None);
+    }
+
+    #[test]
+    fn nearest_untrusted_hop_is_the_client() {
+        let proxies = parse_proxies("10.0.0.1,10.0.0.2");
+        // The client made up the leftmost entry
+        let headers = forwarded(&["6.6.6.6, 1.2.3.4", "10.0.0.2"]);
+        assert_eq!(client_ip(&headers, peer("10.0.0.1"), &proxies).as_deref(), Some("1.2.3.4"));
+        // Only proxies in the chain leaves the peer
+        let headers = forwarded(&["10.0.0.2"]);
+        assert_eq!(client_ip(&headers, peer("10.0.0.1"), &proxies).as_deref(), Some("10.0.0.1"));
+        // Nothing left of an unparseable entry is believed
+        let headers = forwarded(&["1.2.3.4, garbage, 10.0.0.2"]);
+        assert_eq!(client_ip(&headers, peer("10.0.0.1"), &proxies).as_deref(), Some("10.0.0.1"));
+    }
+}
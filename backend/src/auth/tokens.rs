@@ -0,0 +1,286 @@
+// src/auth/tokens.rs
+use axum::{
+    async_trait,
+    extract::{Extension, FromRequestParts, Json, Path},
+    http::{header, request::Parts, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get},
+    Router,
+};
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tower_sessions::Session;
+use crate::routes::polls::parse_id;
+use uuid::Uuid;
+use crate::auth::require_recent_auth;
+use crate::error::ApiError;
+use crate::models::{ApiToken, Scope};
+use crate::startup::AppState;
+
+const TOKEN_PREFIX: &str = "pat_";
+const TOKEN_LENGTH: usize = 40;
+const MAX_TOKEN_NAME_LENGTH: usize = 64;
+const DEFAULT_EXPIRY_DAYS: i64 = 30;
+const MAX_EXPIRY_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct TokenResponse {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreatedTokenResponse {
+    // The only time the token itself is returned
+    pub token: String,
+    #[serde(flatten)]
+    pub details: TokenResponse,
+}
+
+impl From<ApiToken> for TokenResponse {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            id: token.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: token.name,
+            scopes: token.scopes,
+            created_at: token.created_at.try_to_rfc3339_string().unwrap_or_default(),
+            expires_at: token.expires_at.try_to_rfc3339_string().unwrap_or_default(),
+            last_used_at: token.last_used_at.and_then(|at| at.try_to_rfc3339_string().ok()),
+        }
+    }
+}
+
+// The signed-in user of a request: either the session's user or the owner of
+// the `Authorization: Bearer` token. Session users hold every scope.
+pub struct AuthUser {
+    pub id: Uuid,
+    scopes: Option<Vec<Scope>>,
+}
+
+impl AuthUser {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.as_ref().is_none_or(|scopes| scopes.contains(&scope))
+    }
+
+    pub fn require(&self, scope: Scope) -> Result<(), ApiError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            warn!("Token of UUID {} lacks scope {:?}", self.id, scope);
+            Err(ApiError::InsufficientScope)
+        }
+    }
+
+    // Step-up check for destructive actions. A token cannot prove a recent
+    // passkey assertion, so token requests are refused outright.
+    pub async fn require_recent_auth(&self, session: &Session) -> Result<(), ApiError> {
+        if self.scopes.is_some() {
+            warn!("Token of UUID {} used for an action that needs a session", self.id);
+            return Err(ApiError::SessionRequired);
+        }
+        require_recent_auth(session).await
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let bearer = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+        let app_state = request_app_state(parts)?;
+        if let Some(token) = bearer {
+            return authenticate_token(&app_state, &token).await;
+        }
+
+        let id = session_user_id(parts, state, &app_state).await?;
+        Ok(Self { id, scopes: None })
+    }
+}
+
+// The signed-in user of a browser session, for account management that a
+// token must not reach. Bearer requests are refused.
+pub struct SessionUser {
+    pub id: Uuid,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SessionUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(header::AUTHORIZATION) {
+            warn!("Token used for {}, which needs a session", parts.uri.path());
+            return Err(ApiError::SessionRequired);
+        }
+        let app_state = request_app_state(parts)?;
+        let id = session_user_id(parts, state, &app_state).await?;
+        Ok(Self { id })
+    }
+}
+
+fn request_app_state(parts: &Parts) -> Result<AppState, ApiError> {
+    parts.extensions.get::<AppState>().cloned().ok_or_else(|| {
+        ApiError::Internal("AppState missing from request extensions".to_string())
+    })
+}
+
+async fn session_user_id<S: Send + Sync>(
+    parts: &mut Parts,
+    state: &S,
+    app_state: &AppState,
+) -> Result<Uuid, ApiError> {
+    let session = Session::from_request_parts(parts, state).await.map_err(|(_, reason)| {
+        debug!("No session for request: {}", reason);
+        ApiError::Unauthenticated
+    })?;
+    let id: Uuid = session.get("user_id").await?.ok_or_else(|| {
+        info!("No user_id found in session for {}", parts.uri.path());
+        ApiError::Unauthenticated
+    })?;
+    // A session that outlived its user being disabled or deleted is refused
+    let user = app_state.users.get(id).await?.ok_or_else(|| {
+        warn!("Session refers to unknown user {}", id);
+        ApiError::Unauthenticated
+    })?;
+    if user.disabled {
+        warn!("Refusing session of disabled user {}", user.username);
+        return Err(ApiError::AccountDisabled);
+    }
+    Ok(id)
+}
+
+async fn authenticate_token(app_state: &AppState, token: &str) -> Result<AuthUser, ApiError> {
+    let record = app_state
+        .api_tokens
+        .use_token(&hash_token(token))
+        .await?
+        .ok_or_else(|| {
+            warn!("Unknown or expired API token");
+            ApiError::InvalidToken
+        })?;
+    let user_id = Uuid::parse_str(&record.user_id).map_err(|_| ApiError::InvalidToken)?;
+    let user = app_state.users.get(user_id).await?.ok_or(ApiError::InvalidToken)?;
+    if user.disabled {
+        warn!("Refusing API token of disabled user {}", user.username);
+        return Err(ApiError::AccountDisabled);
+    }
+    Ok(AuthUser { id: user.unique_id, scopes: Some(record.scopes) })
+}
+
+// Tokens are random enough that an unsalted digest is safe to store
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn generate_token() -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", TOKEN_PREFIX, random)
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/tokens", get(list_tokens).post(create_token))
+        .route("/api/tokens/:id", delete(revoke_token))
+}
+
+// Tokens are managed from a browser session only, so a leaked token cannot
+// mint others
+pub async fn list_tokens(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let tokens = app_state.api_tokens.list_for_user(&user_unique_id.to_string()).await?;
+    let response: Vec<TokenResponse> = tokens.into_iter().map(TokenResponse::from).collect();
+    Ok(Json(response))
+}
+
+pub async fn create_token(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    session: Session,
+    Json(request): Json<CreateTokenRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    require_recent_auth(&session).await?;
+
+    let name = request.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_LENGTH {
+        return Err(ApiError::validation("name", "Name must be between 1 and 64 characters"));
+    }
+    let mut scopes: Vec<Scope> = Vec::new();
+    for scope in request.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(ApiError::validation("scopes", "A token needs at least one scope"));
+    }
+    let days = request.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS);
+    if !(1..=MAX_EXPIRY_DAYS).contains(&days) {
+        return Err(ApiError::validation("expiresInDays", "Lifetime must be between 1 and 365 days"));
+    }
+
+    let token = generate_token();
+    let mut record = ApiToken {
+        id: None,
+        user_id: user_unique_id.to_string(),
+        name,
+        token_hash: hash_token(&token),
+        scopes,
+        created_at: mongodb::bson::DateTime::now(),
+        expires_at: mongodb::bson::DateTime::from_system_time((Utc::now() + Duration::days(days)).into()),
+        last_used_at: None,
+    };
+    record.id = Some(app_state.api_tokens.insert(&record).await?);
+    info!("UUID {} created API token {:?}", user_unique_id, record.id);
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedTokenResponse { token, details: TokenResponse::from(record) }),
+    ))
+}
+
+pub async fn revoke_token(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let id = parse_id(&id, "token ID")?;
+    if !app_state.api_tokens.delete(id, &user_unique_id.to_string()).await? {
+        warn!("API token {} not found for UUID {}", id, user_unique_id);
+        return Err(ApiError::NotFound("API token"));
+    }
+    info!("UUID {} revoked API token {}", user_unique_id, id);
+    Ok(StatusCode::OK)
+}
+
+// Drops every token of a user, e.g. when the account is deleted
+pub async fn revoke_user_tokens(app_state: &AppState, user_id: &str) -> Result<(), ApiError> {
+    app_state.api_tokens.delete_for_user(user_id).await
+}
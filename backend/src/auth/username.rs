@@ -0,0 +1,68 @@
+// src/auth/username.rs
+use unicode_normalization::UnicodeNormalization;
+use crate::error::ApiError;
+
+const MIN_LENGTH: usize = 3;
+const MAX_LENGTH: usize = 32;
+
+// Names that could be mistaken for the service or its staff. Compared
+// case-insensitively.
+const RESERVED_USERNAMES: &[&str] = &[
+    "admin", "administrator", "anonymous", "api", "deleted", "help", "me", "mod", "moderator",
+    "null", "owner", "root", "security", "staff", "support", "system", "undefined", "unknown",
+];
+
+// Applies NFKC, so full-width and other compatibility forms collapse onto
+// their plain equivalents, and trims surrounding whitespace.
+fn normalize(raw: &str) -> String {
+    raw.nfkc().collect::<String>().trim().to_string()
+}
+
+// The case-insensitive form usernames are unique and looked up by
+pub fn username_key(raw: &str) -> String {
+    normalize(raw).to_lowercase()
+}
+
+// Checks a username chosen at registration or rename and returns it in the
+// form to store. Only ASCII letters, digits, `_`, `-` and `.` are allowed,
+// which also rules out look-alike characters from other scripts.
+pub fn validate_username(raw: &str) -> Result<String, ApiError> {
+    let username = normalize(raw);
+    let length = username.chars().count();
+    if length < MIN_LENGTH {
+        return Err(ApiError::validation("username", "Username must be at least 3 characters"));
+    }
+    if length > MAX_LENGTH {
+        return Err(ApiError::validation("username", "Username must be at most 32 characters"));
+    }
+    if !username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+        return Err(ApiError::validation("username", "Username may only contain letters, digits, '_', '-' and '.'"));
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(ApiError::validation("username", "Username must start with a letter or digit"));
+    }
+    if RESERVED_USERNAMES.contains(&username.to_lowercase().as_str()) {
+        return Err(ApiError::validation("username", "Username is reserved"));
+    }
+    Ok(username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_are_trimmed_and_folded_to_ascii() {
+        assert_eq!(validate_username("  alice ").unwrap(), "alice");
+        assert_eq!(validate_username("ａｌｉｃｅ").unwrap(), "alice");
+        assert_eq!(username_key("Alice"), username_key("ALICE"));
+    }
+
+    #[test]
+    fn invalid_usernames_are_rejected() {
+        for name in ["al", &"a".repeat(33), "al ice", "аlice", ".alice", "Admin"] {
+            assert!(validate_username(name).is_err(), "{:?} was accepted", name);
+        }
+        assert!(validate_username("al.ice_-1").is_ok());
+    }
+}
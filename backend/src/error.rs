@@ -1,41 +1,250 @@
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
+    Json,
 };
+use mongodb::error::{ErrorKind, WriteFailure};
+use serde::Serialize;
+use serde_json::json;
 use thiserror::Error;
+use crate::request_id;
 
+// A passkey ceremony that could not be completed
 #[derive(Error, Debug)]
 pub enum WebauthnError {
     #[error("unknown webauthn error")]
     Unknown,
     #[error("Corrupt Session")]
     CorruptSession,
-    #[error("User Not Found")]
-    UserNotFound,
     #[error("User Has No Credentials")]
     UserHasNoCredentials,
-    #[error("Deserialising Session failed: {0}")]
-    InvalidSessionState(#[from] tower_sessions::session::Error),
-    #[error("MongoDB error: {0}")]
-    MongoDBError(#[from] mongodb::error::Error),
-    #[error("BSON serialization error: {0}")]
-    BsonError(#[from] mongodb::bson::ser::Error),
-    #[error("UUID parsing error: {0}")]
-    UuidError(#[from] mongodb::bson::uuid::Error),
 }
 
 impl IntoResponse for WebauthnError {
     fn into_response(self) -> Response {
-        let (status, body) = match self {
-            WebauthnError::CorruptSession => (StatusCode::UNAUTHORIZED, "Corrupt Session"),
-            WebauthnError::UserNotFound => (StatusCode::NOT_FOUND, "User Not Found"),
-            WebauthnError::Unknown => (StatusCode::BAD_REQUEST, "Unknown Error"),
-            WebauthnError::UserHasNoCredentials => (StatusCode::BAD_REQUEST, "User Has No Credentials"),
-            WebauthnError::InvalidSessionState(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Deserialising Session failed"),
-            WebauthnError::MongoDBError(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
-            WebauthnError::BsonError(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
-            WebauthnError::UuidError(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        ApiError::from(self).into_response()
+    }
+}
+
+// Error returned by the API. Serialized as `{code, message, details,
+// request_id}`, where `code` is stable for clients to match on. Internal
+// failures are logged with the request ID and answered with a generic 500.
+#[derive(Debug)]
+pub enum ApiError {
+    // An ID in the path or body is not a valid ObjectId
+    InvalidId(&'static str),
+    Validation { field: &'static str, message: &'static str },
+    // The named resource does not exist or is hidden from the caller
+    NotFound(&'static str),
+    // The request has no signed-in session or token
+    Unauthenticated,
+    Forbidden,
+    AccountDisabled,
+    // The action needs a passkey assertion newer than the reauth window
+    ReauthRequired,
+    InvalidToken,
+    InsufficientScope,
+    // A token was used where only a browser session is accepted
+    SessionRequired,
+    InvalidRecoveryCode,
+    AuthenticatorNotAllowed,
+    UsernameTaken,
+    Conflict { code: &'static str, message: &'static str },
+    Auth(WebauthnError),
+    Internal(String),
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: &'static str,
+    message: String,
+    details: Option<serde_json::Value>,
+    request_id: Option<String>,
+}
+
+impl ApiError {
+    pub fn validation(field: &'static str, message: &'static str) -> Self {
+        ApiError::Validation { field, message }
+    }
+
+    pub fn conflict(code: &'static str, message: &'static str) -> Self {
+        ApiError::Conflict { code, message }
+    }
+
+    // The code, message and details a client would receive, or the error
+    // itself back when it is internal and must not be shown
+    pub fn client_parts(self) -> Result<(&'static str, String, Option<serde_json::Value>), ApiError> {
+        self.parts()
+            .map(|(_, code, message, details)| (code, message, details))
+            .map_err(ApiError::Internal)
+    }
+
+    fn parts(self) -> Result<(StatusCode, &'static str, String, Option<serde_json::Value>), String> {
+        let parts = match self {
+            ApiError::InvalidId(name) => (StatusCode::BAD_REQUEST, "invalid_id", format!("Invalid {}", name), None),
+            ApiError::Validation { field, message } => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", message.to_string(), Some(json!({ "field": field })))
+            }
+            ApiError::NotFound(resource) => {
+                let mut chars = resource.chars();
+                let first = chars.next().map(|c| c.to_uppercase().to_string()).unwrap_or_default();
+                let message = format!("{}{} not found", first, chars.as_str());
+                (StatusCode::NOT_FOUND, "not_found", message, Some(json!({ "resource": resource })))
+            }
+            ApiError::Unauthenticated => (StatusCode::UNAUTHORIZED, "unauthenticated", "Not Signed In".to_string(), None),
+            ApiError::Forbidden => (StatusCode::FORBIDDEN, "forbidden", "Forbidden".to_string(), None),
+            ApiError::AccountDisabled => (StatusCode::FORBIDDEN, "account_disabled", "Account Disabled".to_string(), None),
+            ApiError::ReauthRequired => {
+                (StatusCode::UNAUTHORIZED, "reauth_required", "Reauthentication Required".to_string(), None)
+            }
+            ApiError::InvalidToken => (StatusCode::UNAUTHORIZED, "invalid_token", "Invalid API Token".to_string(), None),
+            ApiError::InsufficientScope => {
+                (StatusCode::FORBIDDEN, "insufficient_scope", "Insufficient Token Scope".to_string(), None)
+            }
+            ApiError::SessionRequired => {
+                (StatusCode::FORBIDDEN, "session_required", "Requires A Signed-In Session".to_string(), None)
+            }
+            ApiError::InvalidRecoveryCode => {
+                (StatusCode::UNAUTHORIZED, "invalid_recovery_code", "Invalid Recovery Code".to_string(), None)
+            }
+            ApiError::AuthenticatorNotAllowed => {
+                (StatusCode::FORBIDDEN, "authenticator_not_allowed", "Authenticator Not Allowed".to_string(), None)
+            }
+            ApiError::UsernameTaken => {
+                (StatusCode::CONFLICT, "username_taken", "Username is already taken".to_string(), None)
+            }
+            ApiError::Conflict { code, message } => (StatusCode::CONFLICT, code, message.to_string(), None),
+            ApiError::Internal(reason) => return Err(reason),
+            ApiError::Auth(e) => {
+                let code = match e {
+                    WebauthnError::Unknown => "bad_request",
+                    WebauthnError::CorruptSession => "corrupt_session",
+                    WebauthnError::UserHasNoCredentials => "no_credentials",
+                };
+                let message = match e {
+                    WebauthnError::Unknown => "Bad Request".to_string(),
+                    e => e.to_string(),
+                };
+                (StatusCode::BAD_REQUEST, code, message, None)
+            }
         };
-        (status, body).into_response()
+        Ok(parts)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let request_id = request_id::current();
+        let (status, code, message, details) = match self.parts() {
+            Ok(parts) => parts,
+            Err(reason) => {
+                error!("Internal error (request {}): {}", request_id.as_deref().unwrap_or("-"), reason);
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", "Internal Server Error".to_string(), None)
+            }
+        };
+        (status, Json(ErrorBody { code, message, details, request_id })).into_response()
+    }
+}
+
+impl From<WebauthnError> for ApiError {
+    fn from(e: WebauthnError) -> Self {
+        ApiError::Auth(e)
+    }
+}
+
+impl From<mongodb::error::Error> for ApiError {
+    fn from(e: mongodb::error::Error) -> Self {
+        ApiError::Internal(format!("MongoDB error: {}", e))
+    }
+}
+
+impl From<mongodb::bson::ser::Error> for ApiError {
+    fn from(e: mongodb::bson::ser::Error) -> Self {
+        ApiError::Internal(format!("BSON serialization error: {}", e))
     }
-}
\ No newline at end of file
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        ApiError::Internal(format!("SQL error: {}", e))
+    }
+}
+
+impl From<mongodb::bson::uuid::Error> for ApiError {
+    fn from(e: mongodb::bson::uuid::Error) -> Self {
+        ApiError::Internal(format!("UUID parsing error: {}", e))
+    }
+}
+
+impl From<tower_sessions::session::Error> for ApiError {
+    fn from(e: tower_sessions::session::Error) -> Self {
+        ApiError::Internal(format!("Deserialising Session failed: {}", e))
+    }
+}
+
+// Whether a MongoDB write failed on a unique index
+pub fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == 11000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_and_code(error: ApiError) -> (StatusCode, &'static str) {
+        let (status, code, _, _) = error.parts().unwrap();
+        (status, code)
+    }
+
+    async fn respond(error: ApiError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[test]
+    fn errors_map_to_their_status_and_code() {
+        let cases = [
+            (ApiError::InvalidId("poll ID"), StatusCode::BAD_REQUEST, "invalid_id"),
+            (ApiError::validation("title", "Title must not be empty"), StatusCode::UNPROCESSABLE_ENTITY, "validation_failed"),
+            (ApiError::NotFound("poll"), StatusCode::NOT_FOUND, "not_found"),
+            (ApiError::Unauthenticated, StatusCode::UNAUTHORIZED, "unauthenticated"),
+            (ApiError::ReauthRequired, StatusCode::UNAUTHORIZED, "reauth_required"),
+            (ApiError::InsufficientScope, StatusCode::FORBIDDEN, "insufficient_scope"),
+            (ApiError::SessionRequired, StatusCode::FORBIDDEN, "session_required"),
+            (ApiError::UsernameTaken, StatusCode::CONFLICT, "username_taken"),
+            (ApiError::conflict("poll_closed", "Poll is closed"), StatusCode::CONFLICT, "poll_closed"),
+            (ApiError::Auth(WebauthnError::Unknown), StatusCode::BAD_REQUEST, "bad_request"),
+            (ApiError::Auth(WebauthnError::CorruptSession), StatusCode::BAD_REQUEST, "corrupt_session"),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!(status_and_code(error), (status, code));
+        }
+    }
+
+    #[tokio::test]
+    async fn client_errors_carry_their_details() {
+        let (status, body) = respond(ApiError::NotFound("poll")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["message"], "Poll not found");
+        assert_eq!(body["details"], json!({ "resource": "poll" }));
+
+        let (_, body) = respond(ApiError::validation("tags", "Too many tags")).await;
+        assert_eq!(body["details"], json!({ "field": "tags" }));
+    }
+
+    #[tokio::test]
+    async fn internal_errors_hide_their_reason() {
+        let error = ApiError::Internal("connection refused".to_string());
+        assert!(matches!(error.client_parts(), Err(ApiError::Internal(reason)) if reason == "connection refused"));
+
+        let (status, body) = respond(ApiError::Internal("connection refused".to_string())).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body["code"], "internal_error");
+        assert_eq!(body["message"], "Internal Server Error");
+    }
+}
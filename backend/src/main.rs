@@ -1,12 +1,15 @@
 // src/main.rs
 use crate::auth::{
-    finish_authentication, finish_register, get_current_user, start_authentication, start_register,
+    finish_authentication, finish_discoverable_authentication, finish_reauth, finish_register, get_current_user,
+    passkeys, recovery, sessions, start_authentication, start_discoverable_authentication, start_reauth, start_register,
+    tokens,
 };
-use crate::routes::polls;
+use crate::routes::{account, admin, charts, embed, export, import, orgs, polls};
 use crate::startup::AppState;
 use axum::{
     extract::Extension,
     http::StatusCode,
+    middleware,
     response::IntoResponse,
     routing::{get, post},
     Router,
@@ -21,7 +24,6 @@ use tower_sessions::{
     cookie::{time::Duration, SameSite},
     Expiry, SessionManagerLayer,
 };
-use tower_sessions_mongodb_store::{mongodb::Client, MongoDBStore};
 
 #[macro_use]
 extern crate tracing;
@@ -29,8 +31,11 @@ extern crate tracing;
 mod auth;
 mod error;
 mod models;
+mod request_id;
 mod routes;
+mod scheduler;
 mod startup;
+mod store;
 mod websocket;
 
 #[tokio::main]
@@ -43,10 +48,53 @@ async fn main() {
 
     let app_state = AppState::new().await;
 
-    // Load MongoDB session store
-    let mongo_uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set in environment variables");
-    let client = Client::with_uri_str(&mongo_uri).await.expect("Failed to connect to MongoDB");
-    let session_store = MongoDBStore::new(client, "polling-app".to_string());
+    // `backend grant-admin <username>` / `backend revoke-admin <username>`
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => {}
+        [command, username] if command == "grant-admin" || command == "revoke-admin" => {
+            let is_admin = command == "grant-admin";
+            match admin::set_admin(&app_state, username, is_admin).await {
+                Ok(true) => println!("Admin role for {} set to {}", username, is_admin),
+                Ok(false) => {
+                    eprintln!("No user named {}", username);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    eprintln!("Failed to update {}: {:?}", username, e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        _ => {
+            eprintln!("Usage: backend [grant-admin|revoke-admin <username>]");
+            std::process::exit(2);
+        }
+    }
+
+    // Comma-separated usernames promoted to admin on every start. A name
+    // nobody holds yet stops the server, or whoever registered it next would
+    // become admin on the following restart.
+    if let Ok(admins) = env::var("ADMIN_USERNAMES") {
+        for username in admins.split(',').map(str::trim).filter(|u| !u.is_empty()) {
+            match admin::set_admin(&app_state, username, true).await {
+                Ok(true) => info!("Bootstrapped admin {}", username),
+                Ok(false) => {
+                    error!("ADMIN_USERNAMES lists unknown user {}", username);
+                    std::process::exit(1);
+                }
+                Err(e) => {
+                    error!("Failed to bootstrap admin {}: {:?}", username, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+
+    scheduler::spawn(app_state.clone());
+
+    let session_store = app_state.session_store.clone();
 
     // CORS Configuration
     let rp_origin = env::var("RP_ORIGIN").expect("RP_ORIGIN must be set in environment variables");
@@ -56,8 +104,9 @@ async fn main() {
         .allow_origin(rp_origin
             .parse::<HeaderValue>()
             .expect("RP_ORIGIN must be a valid header value"),)
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS]) // Ensure OPTIONS is handled
+        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS]) // Ensure OPTIONS is handled
         .allow_headers(vec![header::CONTENT_TYPE, header::ACCEPT, header::AUTHORIZATION])
+        .expose_headers([request_id::REQUEST_ID_HEADER])
         .allow_credentials(true);
 
     // Session management
@@ -75,13 +124,30 @@ async fn main() {
         .route("/register_finish", post(finish_register).options(preflight_response))
         .route("/login_start/:username", post(start_authentication).options(preflight_response))
         .route("/login_finish", post(finish_authentication).options(preflight_response))
+        .route("/login_discoverable_start", post(start_discoverable_authentication).options(preflight_response))
+        .route("/login_discoverable_finish", post(finish_discoverable_authentication).options(preflight_response))
         .route("/api/user", get(get_current_user).options(preflight_response))
         .route("/api/logout", get(crate::auth::logout).options(preflight_response))
+        .route("/reauth_start", post(start_reauth).options(preflight_response))
+        .route("/reauth_finish", post(finish_reauth).options(preflight_response))
+        .merge(passkeys::router())
+        .merge(recovery::router())
+        .merge(sessions::router())
+        .merge(tokens::router())
         .merge(polls::router(app_state.broadcast_tx.clone()))
+        .merge(export::router())
+        .merge(import::router())
+        .merge(charts::router())
+        .merge(embed::router())
+        .merge(orgs::router())
+        .merge(admin::router())
+        .merge(account::router())
         .route("/ws", get(crate::websocket::websocket_handler))
+        .layer(middleware::from_fn(sessions::track_session))
         .layer(Extension(app_state))
         .layer(cors) // Attach CORS
         .layer(session_layer)
+        .layer(middleware::from_fn(request_id::assign_request_id))
         .fallback(handler_404);
 
     let port: u16 = env::var("PORT")
@@ -96,7 +162,7 @@ async fn main() {
         .await
         .expect("Unable to spawn TCP listener");
 
-    axum::serve(listener, app.into_make_service())
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap();
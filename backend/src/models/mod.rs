@@ -1,7 +1,10 @@
 // src/models/mod.rs
-// src/models/mod.rs
+pub mod tags;
+
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use mongodb::bson::oid::ObjectId;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PollOption {
@@ -16,58 +19,466 @@ pub struct Poll {
     pub id: Option<mongodb::bson::oid::ObjectId>,
     pub title: String,
     pub options: Vec<PollOption>,
-    #[serde(with = "uuid_binary")]
+    // Stored as a string to match `users.unique_id`; see `uuid_string`
+    #[serde(with = "uuid_string")]
     pub creator_id: Uuid,
     pub is_closed: bool,
     pub created_at: mongodb::bson::DateTime,
+    // Creator's username, resolved by the store on reads and never stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    // When the scheduler should close this instance and open the next one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_occurrence_at: Option<mongodb::bson::DateTime>,
+    // First instance of a recurring series, shared by every instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<ObjectId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_instance_id: Option<ObjectId>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    // Random token for the `/api/p/:token` share link of non-public polls
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub author: Option<String>, // Already correct
+    pub share_token: Option<String>,
+    // `unique_id`s of users allowed to see a private poll
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub invited_user_ids: Vec<String>,
+    // Organization owning the poll; its owners and editors manage it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<ObjectId>,
+    // `unique_id`s of users the creator granted manage rights to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collaborator_ids: Vec<String>,
+    // Normalized by `tags::normalize_tags`, as is the category
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    // Whether `/embed/:poll_id` may be framed by other sites
+    #[serde(default)]
+    pub embeddable: bool,
+    // Origins allowed to frame the embed; none but the backend's own when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub embed_origins: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    // Listed in `/api/polls/all` and readable by ID
+    #[default]
+    Public,
+    // Only reachable through the share link
+    Unlisted,
+    // Only the creator and invited users
+    Private,
+}
+
+// Who is looking at a poll, as far as access checks are concerned
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Viewer {
+    pub user_id: Option<Uuid>,
+    // Whether the session opened the poll's share link
+    pub has_share_link: bool,
+    // Role in the organization owning the poll, if any
+    pub org_role: Option<OrgRole>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PollPermission {
+    // Close, reset, edit and view voters: also granted to collaborators
+    Manage,
+    // Delete, and change visibility, schedule or collaborators
+    Own,
+}
+
+impl Poll {
+    pub fn is_visible_to(&self, viewer: &Viewer) -> bool {
+        if viewer.user_id == Some(self.creator_id) || viewer.org_role.is_some() {
+            return true;
+        }
+        if viewer.user_id.is_some_and(|id| self.is_collaborator(id)) {
+            return true;
+        }
+        match self.visibility {
+            Visibility::Public => true,
+            Visibility::Unlisted => viewer.has_share_link,
+            Visibility::Private => viewer
+                .user_id
+                .is_some_and(|id| self.invited_user_ids.contains(&id.to_string())),
+        }
+    }
+
+    // Organization polls only take votes from members
+    pub fn accepts_votes_from(&self, viewer: &Viewer) -> bool {
+        self.is_visible_to(viewer) && (self.org_id.is_none() || viewer.org_role.is_some())
+    }
+
+    pub fn is_collaborator(&self, user_id: Uuid) -> bool {
+        self.collaborator_ids.contains(&user_id.to_string())
+    }
+
+    // The single ownership check behind every management route
+    pub fn permits(&self, user_id: Uuid, org_role: Option<OrgRole>, permission: PollPermission) -> bool {
+        if self.creator_id == user_id || org_role.is_some_and(OrgRole::can_manage_polls) {
+            return true;
+        }
+        permission == PollPermission::Manage && self.is_collaborator(user_id)
+    }
+
+    // Strips fields only the creator should see before a poll leaves the server
+    pub fn redacted(mut self) -> Self {
+        self.share_token = None;
+        self.invited_user_ids.clear();
+        self.collaborator_ids.clear();
+        self
+    }
+}
+
+pub fn new_share_token() -> String {
+    Uuid::new_v4().simple().to_string()
 }
 
-mod uuid_binary {
+pub fn share_link_key(poll_id: &ObjectId) -> String {
+    format!("shared_{}", poll_id)
+}
+
+// Session key set once the session has voted on the poll
+pub fn voted_key(poll_id: &ObjectId) -> String {
+    format!("voted_{}", poll_id)
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// Schedule for a recurring poll. Times are UTC.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weekdays: Vec<Weekday>,
+    #[serde(rename = "dayOfMonth", default, skip_serializing_if = "Option::is_none")]
+    pub day_of_month: Option<u32>,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Recurrence {
+    pub fn is_valid(&self) -> bool {
+        if self.hour > 23 || self.minute > 59 {
+            return false;
+        }
+        match self.frequency {
+            Frequency::Daily => true,
+            Frequency::Weekly => !self.weekdays.is_empty(),
+            Frequency::Monthly => matches!(self.day_of_month, Some(1..=31)),
+        }
+    }
+
+    // Next scheduled time strictly after `after`. Monthly schedules on a day the
+    // month doesn't have (e.g. the 31st) fall back to the month's last day.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if !self.is_valid() {
+            return None;
+        }
+        let today = after.date_naive();
+        match self.frequency {
+            Frequency::Daily => (0..=1)
+                .filter_map(|d| self.at(today + Duration::days(d)))
+                .find(|t| *t > after),
+            Frequency::Weekly => (0..=7)
+                .map(|d| today + Duration::days(d))
+                .filter(|date| self.weekdays.contains(&date.weekday()))
+                .filter_map(|date| self.at(date))
+                .find(|t| *t > after),
+            Frequency::Monthly => {
+                let day = self.day_of_month?;
+                (0..=12)
+                    .filter_map(|m| {
+                        let months = today.month0() + m;
+                        let year = today.year() + (months / 12) as i32;
+                        let month = months % 12 + 1;
+                        let last_day = days_in_month(year, month)?;
+                        NaiveDate::from_ymd_opt(year, month, day.min(last_day))
+                    })
+                    .filter_map(|date| self.at(date))
+                    .find(|t| *t > after)
+            }
+        }
+    }
+
+    fn at(&self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        date.and_hms_opt(self.hour, self.minute, 0)
+            .map(|naive| Utc.from_utc_datetime(&naive))
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> Option<u32> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    Some((next - first).num_days() as u32)
+}
+
+// Older poll documents hold the creator as a binary UUID; those still read
+// until `store::mongo` rewrites them as strings on startup
+mod uuid_string {
     use serde::{Serialize, Deserialize, Serializer, Deserializer};
     use uuid::Uuid;
-    use mongodb::bson::Binary;
+    use mongodb::bson::Bson;
 
     pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let binary = Binary {
-            subtype: mongodb::bson::spec::BinarySubtype::Uuid,
-            bytes: uuid.as_bytes().to_vec(),
-        };
-        binary.serialize(serializer)
+        uuid.to_string().serialize(serializer)
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let binary = Binary::deserialize(deserializer)?;
-        Uuid::from_slice(&binary.bytes).map_err(serde::de::Error::custom)
+        match Bson::deserialize(deserializer)? {
+            Bson::String(s) => Uuid::parse_str(&s).map_err(serde::de::Error::custom),
+            Bson::Binary(binary) => Uuid::from_slice(&binary.bytes).map_err(serde::de::Error::custom),
+            other => Err(serde::de::Error::custom(format!("invalid creator_id: {}", other))),
+        }
+    }
+}
+
+// One ballot, kept so managers can see who voted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Vote {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub poll_id: ObjectId,
+    pub user_id: String,
+    pub option_id: i32,
+    pub created_at: mongodb::bson::DateTime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Owner,
+    Editor,
+    Viewer,
+}
+
+impl OrgRole {
+    pub fn can_manage_polls(self) -> bool {
+        matches!(self, OrgRole::Owner | OrgRole::Editor)
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct UserData {
-    pub username: String,
-    #[serde(deserialize_with = "deserialize_uuid", serialize_with = "serialize_uuid")]
-    pub unique_id: Uuid,
-    pub passkeys: Vec<webauthn_rs::prelude::Passkey>,
-}
-
-fn deserialize_uuid<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let s: String = Deserialize::deserialize(deserializer)?;
-    Uuid::parse_str(&s).map_err(serde::de::Error::custom)
-}
-
-fn serialize_uuid<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    serializer.serialize_str(&uuid.to_string())
-}
\ No newline at end of file
+pub struct OrgMember {
+    pub user_id: String,
+    pub role: OrgRole,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Organization {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub name: String,
+    pub members: Vec<OrgMember>,
+    pub created_at: mongodb::bson::DateTime,
+}
+
+impl Organization {
+    pub fn role_of(&self, user_id: Uuid) -> Option<OrgRole> {
+        let user_id = user_id.to_string();
+        self.members.iter().find(|m| m.user_id == user_id).map(|m| m.role)
+    }
+}
+
+// Entry in the admin action log
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AdminAction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub admin_id: String,
+    pub admin_username: String,
+    pub action: String,
+    pub target: String,
+    pub created_at: mongodb::bson::DateTime,
+}
+
+// A logged-in session, indexed by user so it can be listed and signed out
+// remotely. `session_id` is the key in the session store and never leaves
+// the server; clients refer to sessions by `_id`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UserSession {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub session_id: String,
+    pub user_id: String,
+    pub created_at: mongodb::bson::DateTime,
+    pub last_seen_at: mongodb::bson::DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    // Read polls, their history, voters and collaborators
+    #[serde(rename = "polls:read")]
+    PollsRead,
+    // Create, edit, close, reset and delete polls
+    #[serde(rename = "polls:write")]
+    PollsWrite,
+    #[serde(rename = "votes:write")]
+    VotesWrite,
+}
+
+// A personal access token. Only the SHA-256 of the token is stored; the
+// token itself is shown once, when it is created.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ApiToken {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub user_id: String,
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: mongodb::bson::DateTime,
+    pub expires_at: mongodb::bson::DateTime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<mongodb::bson::DateTime>,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // An open public poll with two options and no votes
+    pub(crate) fn poll(creator_id: Uuid) -> Poll {
+        Poll {
+            id: Some(ObjectId::new()),
+            title: "Lunch".to_string(),
+            options: vec![
+                PollOption { id: 1, text: "Pizza".to_string(), votes: 0 },
+                PollOption { id: 2, text: "Sushi".to_string(), votes: 0 },
+            ],
+            creator_id,
+            is_closed: false,
+            created_at: mongodb::bson::DateTime::now(),
+            author: None,
+            recurrence: None,
+            next_occurrence_at: None,
+            series_id: None,
+            previous_instance_id: None,
+            visibility: Visibility::Public,
+            share_token: None,
+            invited_user_ids: Vec::new(),
+            org_id: None,
+            collaborator_ids: Vec::new(),
+            tags: Vec::new(),
+            category: None,
+            embeddable: false,
+            embed_origins: Vec::new(),
+        }
+    }
+
+    fn schedule(frequency: Frequency) -> Recurrence {
+        Recurrence { frequency, weekdays: Vec::new(), day_of_month: None, hour: 9, minute: 30 }
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn daily_schedule_is_strictly_after() {
+        let daily = schedule(Frequency::Daily);
+        assert_eq!(daily.next_after(utc(2026, 3, 1, 8, 0)), Some(utc(2026, 3, 1, 9, 30)));
+        assert_eq!(daily.next_after(utc(2026, 3, 1, 9, 30)), Some(utc(2026, 3, 2, 9, 30)));
+        assert_eq!(daily.next_after(utc(2026, 12, 31, 23, 0)), Some(utc(2027, 1, 1, 9, 30)));
+    }
+
+    #[test]
+    fn weekly_schedule_picks_the_next_listed_weekday() {
+        let weekly = Recurrence { weekdays: vec![Weekday::Mon, Weekday::Thu], ..schedule(Frequency::Weekly) };
+        // 2026-10-19 is a Monday
+        assert_eq!(weekly.next_after(utc(2026, 10, 19, 10, 0)), Some(utc(2026, 10, 22, 9, 30)));
+        assert_eq!(weekly.next_after(utc(2026, 10, 22, 10, 0)), Some(utc(2026, 10, 26, 9, 30)));
+    }
+
+    #[test]
+    fn monthly_schedule_falls_back_to_the_last_day() {
+        let monthly = Recurrence { day_of_month: Some(31), ..schedule(Frequency::Monthly) };
+        assert_eq!(monthly.next_after(utc(2026, 1, 31, 10, 0)), Some(utc(2026, 2, 28, 9, 30)));
+        assert_eq!(monthly.next_after(utc(2028, 1, 31, 10, 0)), Some(utc(2028, 2, 29, 9, 30)));
+        assert_eq!(monthly.next_after(utc(2026, 12, 31, 10, 0)), Some(utc(2027, 1, 31, 9, 30)));
+    }
+
+    #[test]
+    fn invalid_schedules_have_no_next_time() {
+        assert_eq!(schedule(Frequency::Weekly).next_after(utc(2026, 1, 1, 0, 0)), None);
+        let late = Recurrence { hour: 24, ..schedule(Frequency::Daily) };
+        assert_eq!(late.next_after(utc(2026, 1, 1, 0, 0)), None);
+        let day_zero = Recurrence { day_of_month: Some(0), ..schedule(Frequency::Monthly) };
+        assert_eq!(day_zero.next_after(utc(2026, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn unlisted_polls_need_the_share_link() {
+        let poll = Poll { visibility: Visibility::Unlisted, ..poll(Uuid::new_v4()) };
+        assert!(!poll.is_visible_to(&Viewer::default()));
+        assert!(poll.is_visible_to(&Viewer { has_share_link: true, ..Viewer::default() }));
+        assert!(poll.is_visible_to(&Viewer { user_id: Some(poll.creator_id), ..Viewer::default() }));
+    }
+
+    #[test]
+    fn private_polls_are_only_visible_to_invited_users() {
+        let invited = Uuid::new_v4();
+        let collaborator = Uuid::new_v4();
+        let poll = Poll {
+            visibility: Visibility::Private,
+            invited_user_ids: vec![invited.to_string()],
+            collaborator_ids: vec![collaborator.to_string()],
+            ..poll(Uuid::new_v4())
+        };
+        assert!(poll.is_visible_to(&Viewer { user_id: Some(invited), ..Viewer::default() }));
+        assert!(poll.is_visible_to(&Viewer { user_id: Some(collaborator), ..Viewer::default() }));
+        assert!(poll.is_visible_to(&Viewer { org_role: Some(OrgRole::Viewer), ..Viewer::default() }));
+        assert!(!poll.is_visible_to(&Viewer { user_id: Some(Uuid::new_v4()), ..Viewer::default() }));
+        // A share link does not open a private poll
+        assert!(!poll.is_visible_to(&Viewer { has_share_link: true, ..Viewer::default() }));
+    }
+
+    #[test]
+    fn organization_editors_own_but_viewers_do_not() {
+        let poll = poll(Uuid::new_v4());
+        let member = Uuid::new_v4();
+        assert!(poll.permits(member, Some(OrgRole::Owner), PollPermission::Own));
+        assert!(poll.permits(member, Some(OrgRole::Editor), PollPermission::Own));
+        assert!(!poll.permits(member, Some(OrgRole::Viewer), PollPermission::Manage));
+    }
+
+    #[test]
+    fn collaborators_manage_but_do_not_own() {
+        let creator = Uuid::new_v4();
+        let collaborator = Uuid::new_v4();
+        let stranger = Uuid::new_v4();
+        let poll = Poll { collaborator_ids: vec![collaborator.to_string()], ..poll(creator) };
+
+        assert!(poll.permits(creator, None, PollPermission::Own));
+        assert!(poll.permits(collaborator, None, PollPermission::Manage));
+        assert!(!poll.permits(collaborator, None, PollPermission::Own));
+        assert!(!poll.permits(stranger, None, PollPermission::Manage));
+    }
+}
@@ -0,0 +1,72 @@
+// src/models/tags.rs
+use unicode_normalization::UnicodeNormalization;
+
+const MAX_LENGTH: usize = 32;
+const MAX_TAGS: usize = 10;
+
+// The form tags and categories are stored, filtered and counted by: NFKC,
+// trimmed, lowercased, with inner whitespace runs turned into `-`, so
+// `Q3 Planning` and `q3-planning` are the same tag. Letters and digits of any
+// script are allowed, plus `-` and `_`.
+pub fn normalize_tag(raw: &str) -> Result<String, &'static str> {
+    let normalized: String = raw.nfkc().collect();
+    let tag = normalized.split_whitespace().collect::<Vec<_>>().join("-").to_lowercase();
+    if tag.is_empty() {
+        return Err("Must not be empty");
+    }
+    if tag.chars().count() > MAX_LENGTH {
+        return Err("Must be at most 32 characters");
+    }
+    if !tag.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_')) {
+        return Err("May only contain letters, digits, '-' and '_'");
+    }
+    Ok(tag)
+}
+
+// Normalizes a poll's tags, dropping duplicates but keeping their order
+pub fn normalize_tags(raw: &[String]) -> Result<Vec<String>, &'static str> {
+    let mut tags: Vec<String> = Vec::with_capacity(raw.len());
+    for tag in raw {
+        let tag = normalize_tag(tag)?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    if tags.len() > MAX_TAGS {
+        return Err("A poll can have at most 10 tags");
+    }
+    Ok(tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(raw: &[&str]) -> Result<Vec<String>, &'static str> {
+        normalize_tags(&raw.iter().map(|tag| tag.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn spellings_of_a_tag_collapse_into_one() {
+        assert_eq!(tags(&["Q3 Planning", " q3-planning ", "Ｑ３  planning"]), Ok(vec!["q3-planning".to_string()]));
+    }
+
+    #[test]
+    fn order_of_first_use_is_kept() {
+        assert_eq!(tags(&["b", "a", "B"]), Ok(vec!["b".to_string(), "a".to_string()]));
+    }
+
+    #[test]
+    fn letters_of_any_script_are_allowed() {
+        assert_eq!(tags(&["Café", "日本"]), Ok(vec!["café".to_string(), "日本".to_string()]));
+    }
+
+    #[test]
+    fn invalid_tags_are_rejected() {
+        assert!(tags(&["  "]).is_err());
+        assert!(tags(&["c++"]).is_err());
+        assert!(tags(&[&"x".repeat(33)]).is_err());
+        let eleven: Vec<String> = (0..11).map(|i| format!("tag{}", i)).collect();
+        assert!(normalize_tags(&eleven).is_err());
+    }
+}
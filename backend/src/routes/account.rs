@@ -0,0 +1,259 @@
+use axum::{
+    extract::{Extension, Json, Query},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{delete, get, post},
+    Router,
+};
+use tower_sessions::Session;
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::auth::require_recent_auth;
+use crate::auth::passkeys::{passkey_summaries, PasskeyResponse};
+use crate::auth::sessions::{revoke_user_sessions, SessionResponse};
+use crate::auth::tokens::{revoke_user_tokens, SessionUser};
+use crate::auth::username::validate_username;
+use crate::error::ApiError;
+use crate::startup::{AppState, UserData};
+use crate::models::OrgRole;
+use crate::routes::polls::PollResponse;
+use uuid::Uuid;
+
+// Owner of the polls kept when their creator deletes their account
+pub const TOMBSTONE_USERNAME: &str = "[deleted]";
+
+#[derive(Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletedPollPolicy {
+    #[default]
+    Delete,
+    Anonymize,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountQuery {
+    #[serde(default)]
+    pub polls: DeletedPollPolicy,
+}
+
+#[derive(Deserialize)]
+pub struct ChangeUsernameRequest {
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct ExportedUser {
+    pub id: String,
+    pub username: String,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: bool,
+    pub disabled: bool,
+    pub passkeys: Vec<PasskeyResponse>,
+    #[serde(rename = "recoveryCodesRemaining")]
+    pub recovery_codes_remaining: usize,
+}
+
+#[derive(Serialize)]
+pub struct ExportedVote {
+    #[serde(rename = "pollId")]
+    pub poll_id: String,
+    #[serde(rename = "pollTitle")]
+    pub poll_title: Option<String>,
+    #[serde(rename = "optionId")]
+    pub option_id: i32,
+    #[serde(rename = "votedAt")]
+    pub voted_at: String,
+}
+
+#[derive(Serialize)]
+pub struct ExportedMembership {
+    #[serde(rename = "orgId")]
+    pub org_id: String,
+    pub name: String,
+    pub role: OrgRole,
+}
+
+#[derive(Serialize)]
+pub struct AccountExport {
+    #[serde(rename = "exportedAt")]
+    pub exported_at: String,
+    pub user: ExportedUser,
+    pub polls: Vec<PollResponse>,
+    pub votes: Vec<ExportedVote>,
+    pub organizations: Vec<ExportedMembership>,
+    pub sessions: Vec<SessionResponse>,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/me", delete(delete_account))
+        .route("/api/me/export", get(export_account))
+        .route("/api/me/username", post(change_username))
+}
+
+async fn load_session_user(app_state: &AppState, session_user: SessionUser) -> Result<UserData, ApiError> {
+    app_state.users.get(session_user.id).await?.ok_or(ApiError::NotFound("user"))
+}
+
+// Everything stored about the user, without passkey keys or recovery code hashes
+pub async fn export_account(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    let user_id = user.unique_id.to_string();
+    let polls = app_state.polls.list_by_creator(user.unique_id).await?;
+
+    let votes = app_state.votes.list_by_user(&user_id).await?;
+    let voted_poll_ids: Vec<ObjectId> = votes.iter().map(|v| v.poll_id).collect();
+    let voted_polls = app_state.polls.get_many(&voted_poll_ids).await?;
+
+    let orgs = app_state.orgs.list_for_member(&user_id).await?;
+
+    let sessions = app_state.user_sessions.list_for_user(&user_id).await?;
+    let current_id = session.id().map(|id| id.to_string());
+
+    let export = AccountExport {
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        user: ExportedUser {
+            id: user_id.clone(),
+            username: user.username.clone(),
+            is_admin: user.is_admin,
+            disabled: user.disabled,
+            passkeys: passkey_summaries(&user),
+            recovery_codes_remaining: user.recovery_codes.len(),
+        },
+        polls: polls.into_iter().map(PollResponse::for_owner).collect(),
+        votes: votes
+            .into_iter()
+            .map(|vote| ExportedVote {
+                poll_id: vote.poll_id.to_hex(),
+                poll_title: voted_polls
+                    .iter()
+                    .find(|p| p.id == Some(vote.poll_id))
+                    .map(|p| p.title.clone()),
+                option_id: vote.option_id,
+                voted_at: vote.created_at.try_to_rfc3339_string().unwrap_or_default(),
+            })
+            .collect(),
+        organizations: orgs
+            .into_iter()
+            .filter_map(|org| {
+                let role = org.role_of(user.unique_id)?;
+                Some(ExportedMembership {
+                    org_id: org.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    name: org.name,
+                    role,
+                })
+            })
+            .collect(),
+        sessions: sessions
+            .into_iter()
+            .map(|record| SessionResponse::from_record(record, current_id.as_deref()))
+            .collect(),
+    };
+    info!("Exported account data for {}", user.username);
+
+    let filename = format!("attachment; filename=\"account-{}.json\"", user_id);
+    Ok(([(header::CONTENT_DISPOSITION, filename)], Json(export)))
+}
+
+// The account that anonymized polls are handed to. Created on first use;
+// disabled and without passkeys, so nobody can sign in as it.
+async fn ensure_tombstone_user(app_state: &AppState) -> Result<(), ApiError> {
+    if app_state.users.get(Uuid::nil()).await?.is_some() {
+        return Ok(());
+    }
+    let tombstone = UserData {
+        username: TOMBSTONE_USERNAME.to_string(),
+        username_key: TOMBSTONE_USERNAME.to_string(),
+        unique_id: Uuid::nil(),
+        passkeys: Vec::new(),
+        is_admin: false,
+        disabled: true,
+        passkey_meta: Vec::new(),
+        recovery_codes: Vec::new(),
+    };
+    match app_state.users.insert(&tombstone).await {
+        // Created by a concurrent deletion
+        Ok(()) | Err(ApiError::UsernameTaken) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+// Deletes the account. `?polls=delete` (the default) removes the user's
+// polls; `?polls=anonymize` keeps them, with their vote counts, under the
+// tombstone user. Individual ballots are removed either way.
+pub async fn delete_account(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Query(query): Query<DeleteAccountQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    require_recent_auth(&session).await?;
+    let user_id = user.unique_id.to_string();
+
+    // An organization must not be left without an owner
+    let orgs = app_state.orgs.list_for_member(&user_id).await?;
+    for org in &orgs {
+        let sole_owner = org.role_of(user.unique_id) == Some(OrgRole::Owner)
+            && org.members.iter().filter(|m| m.role == OrgRole::Owner).count() == 1
+            && org.members.len() > 1;
+        if sole_owner {
+            debug!("User {} is the only owner of organization {:?}", user.username, org.id);
+            return Err(ApiError::conflict("sole_organization_owner", "The organization needs another owner first"));
+        }
+    }
+
+    match query.polls {
+        DeletedPollPolicy::Delete => {
+            let polls = app_state.polls.delete_by_creator(user.unique_id).await?;
+            let poll_ids: Vec<ObjectId> = polls.iter().filter_map(|p| p.id).collect();
+            app_state.votes.delete_for_polls(&poll_ids).await?;
+            // Clients treat a poll without title and options as deleted
+            for mut poll in polls {
+                poll.title = String::new();
+                poll.options.clear();
+                let _ = app_state.broadcast_tx.send(poll);
+            }
+            info!("Deleted {} polls of {}", poll_ids.len(), user.username);
+        }
+        DeletedPollPolicy::Anonymize => {
+            ensure_tombstone_user(&app_state).await?;
+            // Nobody would be left to stop a recurring poll
+            let count = app_state.polls.reassign_creator(user.unique_id, Uuid::nil()).await?;
+            info!("Anonymized {} polls of {}", count, user.username);
+        }
+    }
+
+    app_state.votes.delete_by_user(&user_id).await?;
+    app_state.polls.remove_user(&user_id).await?;
+    app_state.orgs.remove_member_everywhere(&user_id).await?;
+
+    revoke_user_sessions(&app_state, &user_id).await?;
+    revoke_user_tokens(&app_state, &user_id).await?;
+    app_state.users.delete(user.unique_id).await?;
+    session.flush().await?;
+    info!("Deleted account {} ({})", user.username, user_id);
+    Ok(StatusCode::OK)
+}
+
+// Renames the account. Poll authors are resolved from `creator_id` when polls
+// are read, so every poll shows the new name straight away.
+pub async fn change_username(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    Json(request): Json<ChangeUsernameRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = load_session_user(&app_state, session_user).await?;
+    let username = validate_username(&request.username)?;
+
+    if let Err(e) = app_state.users.rename(user.unique_id, &username).await {
+        error!("Failed to rename {} to {}: {:?}", user.username, username, e);
+        return Err(e);
+    }
+    info!("User {} renamed to {}", user.username, username);
+    Ok(StatusCode::OK)
+}
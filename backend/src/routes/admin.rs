@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Extension, Json, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use tower_sessions::Session;
+use serde::Serialize;
+use crate::auth::require_recent_auth;
+use crate::auth::sessions::revoke_user_sessions;
+use crate::auth::tokens::SessionUser;
+use crate::auth::username::username_key;
+use crate::error::ApiError;
+use crate::startup::{AppState, UserData};
+use crate::models::AdminAction;
+use crate::routes::polls::parse_id;
+use crate::store::PollAccess;
+
+const LOG_LIMIT: usize = 200;
+
+#[derive(Serialize)]
+pub struct AdminUserResponse {
+    pub id: String,
+    pub username: String,
+    #[serde(rename = "isAdmin")]
+    pub is_admin: bool,
+    pub disabled: bool,
+    #[serde(rename = "passkeyCount")]
+    pub passkey_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct AdminActionResponse {
+    pub admin: String,
+    pub action: String,
+    pub target: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/admin/users", get(list_users))
+        .route("/api/admin/users/:username/disable", post(disable_user))
+        .route("/api/admin/users/:username/enable", post(enable_user))
+        .route("/api/admin/polls/:poll_id/close", post(force_close_poll))
+        .route("/api/admin/polls/:poll_id/delete", post(force_delete_poll))
+        .route("/api/admin/log", get(get_action_log))
+}
+
+// Grants or revokes the admin role. Used by the CLI and the ADMIN_USERNAMES
+// bootstrap; returns false if no such user exists.
+pub async fn set_admin(app_state: &AppState, username: &str, is_admin: bool) -> Result<bool, ApiError> {
+    let Some(user) = app_state.users.find_by_username(username).await? else {
+        return Ok(false);
+    };
+    if user.is_admin != is_admin {
+        app_state.users.set_admin(user.unique_id, is_admin).await?;
+        let action = if is_admin { "grant_admin" } else { "revoke_admin" };
+        record_action(app_state, "system", "system", action, username.to_string()).await?;
+        info!("Admin role for {} set to {}", username, is_admin);
+    }
+    Ok(true)
+}
+
+// Every admin route needs an admin account and a fresh passkey assertion
+async fn require_admin(
+    app_state: &AppState,
+    session_user: SessionUser,
+    session: &Session,
+) -> Result<UserData, ApiError> {
+    let user = app_state.users.get(session_user.id).await?.ok_or(ApiError::NotFound("user"))?;
+    if !user.is_admin {
+        warn!("User {} is not an admin", session_user.id);
+        return Err(ApiError::Forbidden);
+    }
+    require_recent_auth(session).await?;
+    Ok(user)
+}
+
+async fn record_action(
+    app_state: &AppState,
+    admin_id: &str,
+    admin_username: &str,
+    action: &str,
+    target: String,
+) -> Result<(), ApiError> {
+    let entry = AdminAction {
+        id: None,
+        admin_id: admin_id.to_string(),
+        admin_username: admin_username.to_string(),
+        action: action.to_string(),
+        target,
+        created_at: mongodb::bson::DateTime::now(),
+    };
+    app_state.admin_log.record(&entry).await
+}
+
+async fn log_admin_action(app_state: &AppState, admin: &UserData, action: &str, target: String) -> Result<(), ApiError> {
+    info!("Admin {} performed {} on {}", admin.username, action, target);
+    record_action(app_state, &admin.unique_id.to_string(), &admin.username, action, target).await
+}
+
+pub async fn list_users(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    let admin = require_admin(&app_state, session_user, &session).await?;
+
+    let users = app_state.users.list().await?;
+    info!("Admin {} listed {} users", admin.username, users.len());
+
+    let response: Vec<AdminUserResponse> = users
+        .into_iter()
+        .map(|user| AdminUserResponse {
+            id: user.unique_id.to_string(),
+            username: user.username,
+            is_admin: user.is_admin,
+            disabled: user.disabled,
+            passkey_count: user.passkeys.len(),
+        })
+        .collect();
+    Ok(Json(response))
+}
+
+async fn set_disabled(
+    app_state: &AppState,
+    admin: &UserData,
+    username: &str,
+    disabled: bool,
+) -> Result<StatusCode, ApiError> {
+    if admin.username_key == username_key(username) {
+        debug!("Admin {} tried to change their own account status", username);
+        return Err(ApiError::Forbidden);
+    }
+    let user = app_state
+        .users
+        .find_by_username(username)
+        .await?
+        .ok_or(ApiError::NotFound("user"))?;
+    app_state.users.set_disabled(user.unique_id, disabled).await?;
+    if disabled {
+        revoke_user_sessions(app_state, &user.unique_id.to_string()).await?;
+    }
+    let action = if disabled { "disable_user" } else { "enable_user" };
+    log_admin_action(app_state, admin, action, username.to_string()).await?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn disable_user(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let admin = require_admin(&app_state, session_user, &session).await?;
+    set_disabled(&app_state, &admin, &username, true).await
+}
+
+pub async fn enable_user(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Path(username): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let admin = require_admin(&app_state, session_user, &session).await?;
+    set_disabled(&app_state, &admin, &username, false).await
+}
+
+pub async fn force_close_poll(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Path(poll_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let admin = require_admin(&app_state, session_user, &session).await?;
+    let poll_id = parse_id(&poll_id, "poll ID")?;
+    if !app_state.polls.close(poll_id, PollAccess::Any).await? {
+        debug!("Poll {} not found", poll_id);
+        return Err(ApiError::NotFound("poll"));
+    }
+    log_admin_action(&app_state, &admin, "close_poll", poll_id.to_hex()).await?;
+
+    if let Some(poll) = app_state.polls.get(poll_id).await? {
+        let _ = app_state.broadcast_tx.send(poll);
+    }
+    Ok(StatusCode::OK)
+}
+
+pub async fn force_delete_poll(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+    Path(poll_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let admin = require_admin(&app_state, session_user, &session).await?;
+    let poll_id = parse_id(&poll_id, "poll ID")?;
+    let mut poll = app_state
+        .polls
+        .delete(poll_id, PollAccess::Any)
+        .await?
+        .ok_or_else(|| {
+            debug!("Poll {} not found", poll_id);
+            ApiError::NotFound("poll")
+        })?;
+    app_state.votes.delete_for_polls(&[poll_id]).await?;
+    log_admin_action(&app_state, &admin, "delete_poll", poll_id.to_hex()).await?;
+
+    // Clients treat a poll without title and options as deleted
+    poll.title = String::new();
+    poll.options.clear();
+    let _ = app_state.broadcast_tx.send(poll);
+    Ok(StatusCode::OK)
+}
+
+pub async fn get_action_log(
+    Extension(app_state): Extension<AppState>,
+    session_user: SessionUser,
+    session: Session,
+) -> Result<impl IntoResponse, ApiError> {
+    require_admin(&app_state, session_user, &session).await?;
+
+    let entries = app_state.admin_log.recent(LOG_LIMIT).await?;
+    let response: Vec<AdminActionResponse> = entries
+        .into_iter()
+        .map(|entry| AdminActionResponse {
+            admin: entry.admin_username,
+            action: entry.action,
+            target: entry.target,
+            created_at: entry.created_at.try_to_rfc3339_string().unwrap_or_default(),
+        })
+        .collect();
+    Ok(Json(response))
+}
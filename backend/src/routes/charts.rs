@@ -0,0 +1,569 @@
+// src/routes/charts.rs
+//
+// Result charts as images for slides and link previews. The SVG is written
+// by hand and rasterized with resvg for PNG, so nothing outside the process
+// is involved. Charts are visible to whoever can see the poll.
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::sync::{Arc, OnceLock};
+use axum::{
+    extract::{Extension, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use resvg::{tiny_skia, usvg, usvg::fontdb};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tower_sessions::Session;
+use crate::auth::tokens::AuthUser;
+use crate::error::ApiError;
+use crate::models::Poll;
+use crate::routes::polls::{load_visible_poll, parse_id};
+use crate::startup::AppState;
+
+const DEFAULT_WIDTH: u32 = 800;
+const DEFAULT_HEIGHT: u32 = 450;
+const MIN_SIZE: u32 = 200;
+const MAX_SIZE: u32 = 2000;
+const PADDING: f64 = 32.0;
+const FONT_FAMILY: &str = "DejaVu Sans, Liberation Sans, Arial, Helvetica, sans-serif";
+pub(crate) const DEFAULT_PALETTE: [&str; 8] =
+    ["#4f46e5", "#0ea5e9", "#10b981", "#f59e0b", "#ef4444", "#8b5cf6", "#ec4899", "#14b8a6"];
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    #[default]
+    Bar,
+    Pie,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartTheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl ChartTheme {
+    // Background, text, secondary text and empty-bar colors
+    pub(crate) fn colors(self) -> (&'static str, &'static str, &'static str, &'static str) {
+        match self {
+            ChartTheme::Light => ("#ffffff", "#111827", "#6b7280", "#e5e7eb"),
+            ChartTheme::Dark => ("#111827", "#f9fafb", "#9ca3af", "#374151"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageFormat {
+    Svg,
+    Png,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ChartParams {
+    #[serde(rename = "type", default)]
+    pub kind: ChartKind,
+    #[serde(default)]
+    pub theme: ChartTheme,
+    // Hex colors, with or without the `#`, overriding the theme
+    pub background: Option<String>,
+    pub foreground: Option<String>,
+    // Comma-separated hex colors for the options, repeated as needed
+    pub palette: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+// Colors and size the chart is drawn with
+struct Style {
+    kind: ChartKind,
+    background: String,
+    foreground: String,
+    muted: String,
+    track: String,
+    palette: Vec<String>,
+    width: f64,
+    height: f64,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/polls/:poll_id/chart.svg", get(chart_svg))
+        .route("/api/polls/:poll_id/chart.png", get(chart_png))
+}
+
+fn parse_color(value: &str, field: &'static str) -> Result<String, ApiError> {
+    let hex = value.trim().trim_start_matches('#');
+    if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(ApiError::validation(field, "Colors must be hex, like 1f2937 or #fff"));
+    }
+    Ok(format!("#{}", hex.to_ascii_lowercase()))
+}
+
+fn parse_size(value: Option<u32>, default: u32, field: &'static str) -> Result<u32, ApiError> {
+    match value {
+        None => Ok(default),
+        Some(size) if (MIN_SIZE..=MAX_SIZE).contains(&size) => Ok(size),
+        Some(_) => Err(ApiError::validation(field, "Must be between 200 and 2000 pixels")),
+    }
+}
+
+impl Style {
+    fn new(params: &ChartParams, options: usize) -> Result<Self, ApiError> {
+        let (background, foreground, muted, track) = params.theme.colors();
+        let palette = match params.palette.as_deref() {
+            Some(palette) => palette
+                .split(',')
+                .map(|color| parse_color(color, "palette"))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => DEFAULT_PALETTE.iter().map(|c| c.to_string()).collect(),
+        };
+        // Tall enough by default that every option gets a readable row
+        let default_height = DEFAULT_HEIGHT.max(110 + 48 * options as u32).min(MAX_SIZE);
+        Ok(Style {
+            kind: params.kind,
+            background: match &params.background {
+                Some(color) => parse_color(color, "background")?,
+                None => background.to_string(),
+            },
+            foreground: match &params.foreground {
+                Some(color) => parse_color(color, "foreground")?,
+                None => foreground.to_string(),
+            },
+            muted: muted.to_string(),
+            track: track.to_string(),
+            palette,
+            width: parse_size(params.width, DEFAULT_WIDTH, "width")? as f64,
+            height: parse_size(params.height, default_height, "height")? as f64,
+        })
+    }
+
+    fn color(&self, index: usize) -> &str {
+        &self.palette[index % self.palette.len()]
+    }
+}
+
+// Changes whenever a vote is cast or anything drawn on the chart is edited
+fn etag(poll: &Poll) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(poll.title.as_bytes());
+    hasher.update([0, poll.is_closed as u8]);
+    hasher.update(poll.author.as_deref().unwrap_or_default().as_bytes());
+    for option in &poll.options {
+        hasher.update([0]);
+        hasher.update(option.id.to_be_bytes());
+        hasher.update(option.votes.to_be_bytes());
+        hasher.update(option.text.as_bytes());
+    }
+    format!("\"{}\"", hex::encode(&hasher.finalize()[..16]))
+}
+
+fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        })
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.chars().fold(String::with_capacity(text.len()), |mut out, c| {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+        out
+    })
+}
+
+// Shortens text to about what fits in `width` at `font_size`
+fn fit(text: &str, width: f64, font_size: f64) -> String {
+    let max_chars = (width / (font_size * 0.6)).max(1.0) as usize;
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+fn percentage(votes: i32, total: i32) -> f64 {
+    match total {
+        0 => 0.0,
+        total => votes as f64 * 100.0 / total as f64,
+    }
+}
+
+fn render_svg(poll: &Poll, style: &Style) -> String {
+    let (width, height) = (style.width, style.height);
+    let total: i32 = poll.options.iter().map(|o| o.votes).sum();
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="{font}">"#,
+        w = width,
+        h = height,
+        font = FONT_FAMILY,
+    );
+    let _ = write!(svg, r#"<rect width="{}" height="{}" fill="{}"/>"#, width, height, style.background);
+
+    let title_y = PADDING + 26.0;
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" font-size="26" font-weight="bold" fill="{}">{}</text>"#,
+        PADDING,
+        title_y,
+        style.foreground,
+        escape(&fit(&poll.title, width - 2.0 * PADDING, 26.0)),
+    );
+    let mut subtitle = format!(
+        "by {} · {} vote{}",
+        poll.author.as_deref().unwrap_or("Unknown"),
+        total,
+        if total == 1 { "" } else { "s" },
+    );
+    if poll.is_closed {
+        subtitle.push_str(" · closed");
+    }
+    let _ = write!(
+        svg,
+        r#"<text x="{}" y="{}" font-size="15" fill="{}">{}</text>"#,
+        PADDING,
+        title_y + 26.0,
+        style.muted,
+        escape(&fit(&subtitle, width - 2.0 * PADDING, 15.0)),
+    );
+
+    let top = title_y + 54.0;
+    let bottom = height - PADDING;
+    match style.kind {
+        ChartKind::Bar => draw_bars(&mut svg, poll, style, top, bottom, total),
+        ChartKind::Pie => draw_pie(&mut svg, poll, style, top, bottom, total),
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// One row per option: its text, then a bar scaled to the leading option
+fn draw_bars(svg: &mut String, poll: &Poll, style: &Style, top: f64, bottom: f64, total: i32) {
+    // Rows stack from the top rather than spreading out when there are few
+    let row = ((bottom - top) / poll.options.len().max(1) as f64).min(64.0);
+    let label_size = (row * 0.32).clamp(10.0, 16.0);
+    let bar_height = (row * 0.4).clamp(4.0, 28.0);
+    let value_width = 120.0;
+    let track_width = (style.width - 2.0 * PADDING - value_width).max(1.0);
+    let max_votes = poll.options.iter().map(|o| o.votes).max().unwrap_or(0).max(1);
+
+    for (i, option) in poll.options.iter().enumerate() {
+        let row_top = top + row * i as f64;
+        let bar_y = row_top + label_size + 6.0;
+        let bar_width = track_width * option.votes.max(0) as f64 / max_votes as f64;
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{:.1}" font-size="{:.1}" fill="{}">{}</text>"#,
+            PADDING,
+            row_top + label_size,
+            label_size,
+            style.foreground,
+            escape(&fit(&option.text, style.width - 2.0 * PADDING, label_size)),
+        );
+        let _ = write!(
+            svg,
+            r#"<rect x="{}" y="{:.1}" width="{:.1}" height="{:.1}" rx="{:.1}" fill="{}"/>"#,
+            PADDING,
+            bar_y,
+            track_width,
+            bar_height,
+            bar_height / 4.0,
+            style.track,
+        );
+        if bar_width > 0.0 {
+            let _ = write!(
+                svg,
+                r#"<rect x="{}" y="{:.1}" width="{:.1}" height="{:.1}" rx="{:.1}" fill="{}"/>"#,
+                PADDING,
+                bar_y,
+                bar_width,
+                bar_height,
+                bar_height / 4.0,
+                style.color(i),
+            );
+        }
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" fill="{}">{} ({:.0}%)</text>"#,
+            PADDING + track_width + 10.0,
+            bar_y + bar_height / 2.0 + label_size * 0.35,
+            label_size,
+            style.muted,
+            option.votes,
+            percentage(option.votes, total),
+        );
+    }
+}
+
+// A pie on the left and a legend with counts on the right
+fn draw_pie(svg: &mut String, poll: &Poll, style: &Style, top: f64, bottom: f64, total: i32) {
+    let area_height = bottom - top;
+    let radius = (area_height / 2.0).min((style.width - 2.0 * PADDING) * 0.25).max(1.0);
+    let (cx, cy) = (PADDING + radius, top + area_height / 2.0);
+
+    if total <= 0 {
+        let _ = write!(svg, r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#, cx, cy, radius, style.track);
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" font-size="15" text-anchor="middle" fill="{}">No votes yet</text>"#,
+            cx,
+            cy + 5.0,
+            style.muted,
+        );
+    } else {
+        let mut angle = -PI / 2.0;
+        for (i, option) in poll.options.iter().enumerate().filter(|(_, o)| o.votes > 0) {
+            let sweep = option.votes as f64 / total as f64 * 2.0 * PI;
+            if sweep >= 2.0 * PI - 1e-9 {
+                let _ = write!(svg, r#"<circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}"/>"#, cx, cy, radius, style.color(i));
+                break;
+            }
+            let (x0, y0) = (cx + radius * angle.cos(), cy + radius * angle.sin());
+            angle += sweep;
+            let (x1, y1) = (cx + radius * angle.cos(), cy + radius * angle.sin());
+            let _ = write!(
+                svg,
+                r#"<path d="M{:.2} {:.2} L{:.2} {:.2} A{:.2} {:.2} 0 {} 1 {:.2} {:.2} Z" fill="{}" stroke="{}" stroke-width="2"/>"#,
+                cx,
+                cy,
+                x0,
+                y0,
+                radius,
+                radius,
+                (sweep > PI) as u8,
+                x1,
+                y1,
+                style.color(i),
+                style.background,
+            );
+        }
+    }
+
+    let legend_x = cx + radius + 40.0;
+    let item = (area_height / poll.options.len().max(1) as f64).min(30.0);
+    let font_size = (item * 0.55).clamp(10.0, 15.0);
+    let legend_top = cy - item * poll.options.len() as f64 / 2.0;
+    for (i, option) in poll.options.iter().enumerate() {
+        let y = legend_top + item * i as f64;
+        let swatch = font_size * 0.9;
+        let _ = write!(
+            svg,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="2" fill="{}"/>"#,
+            legend_x,
+            y + (item - swatch) / 2.0,
+            swatch,
+            swatch,
+            style.color(i),
+        );
+        let label = format!("{} — {} ({:.0}%)", option.text, option.votes, percentage(option.votes, total));
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{:.1}" fill="{}">{}</text>"#,
+            legend_x + swatch + 8.0,
+            y + item / 2.0 + font_size * 0.35,
+            font_size,
+            style.foreground,
+            escape(&fit(&label, style.width - PADDING - legend_x - swatch - 8.0, font_size)),
+        );
+    }
+}
+
+// System fonts, plus any in `CHART_FONT_DIR`, loaded once
+fn fonts() -> Arc<fontdb::Database> {
+    static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut db = fontdb::Database::new();
+            db.load_system_fonts();
+            if let Ok(dir) = std::env::var("CHART_FONT_DIR") {
+                db.load_fonts_dir(dir);
+            }
+            // Fall back to any installed font when none of FONT_FAMILY is
+            // present, rather than drawing no text at all
+            let query = fontdb::Query { families: &[fontdb::Family::SansSerif], ..Default::default() };
+            if db.query(&query).is_none() {
+                let family = db.faces().next().and_then(|face| face.families.first()).map(|(name, _)| name.clone());
+                match family {
+                    Some(family) => db.set_sans_serif_family(family),
+                    None => warn!("No fonts found; PNG charts will have no text"),
+                }
+            }
+            Arc::new(db)
+        })
+        .clone()
+}
+
+fn rasterize(svg: &str) -> Result<Vec<u8>, String> {
+    let options = usvg::Options { fontdb: fonts(), ..Default::default() };
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|e| e.to_string())?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("Chart has no area")?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+async fn chart(
+    app_state: AppState,
+    user: Option<AuthUser>,
+    session: Session,
+    poll_id: String,
+    params: ChartParams,
+    headers: HeaderMap,
+    format: ImageFormat,
+) -> Result<Response, ApiError> {
+    let poll_id = parse_id(&poll_id, "poll ID")?;
+    let poll = load_visible_poll(&app_state, &session, user, poll_id).await?;
+    chart_response(&poll, &params, &headers, format).await
+}
+
+// The chart of a poll the caller has already been cleared to see
+pub(crate) async fn chart_response(
+    poll: &Poll,
+    params: &ChartParams,
+    headers: &HeaderMap,
+    format: ImageFormat,
+) -> Result<Response, ApiError> {
+    let style = Style::new(params, poll.options.len())?;
+
+    let etag = etag(poll);
+    if is_fresh(headers, &etag) {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let svg = render_svg(poll, &style);
+    let (content_type, body) = match format {
+        ImageFormat::Svg => ("image/svg+xml", svg.into_bytes()),
+        ImageFormat::Png => {
+            let png = tokio::task::spawn_blocking(move || rasterize(&svg))
+                .await
+                .map_err(|e| ApiError::Internal(format!("Chart rendering panicked: {}", e)))?
+                .map_err(|e| ApiError::Internal(format!("Failed to render chart: {}", e)))?;
+            ("image/png", png)
+        }
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::ETAG, etag),
+            // Cached copies are revalidated, which the ETag makes cheap
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+pub async fn chart_svg(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    Path(poll_id): Path<String>,
+    Query(params): Query<ChartParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    chart(app_state, user, session, poll_id, params, headers, ImageFormat::Svg).await
+}
+
+pub async fn chart_png(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    Path(poll_id): Path<String>,
+    Query(params): Query<ChartParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    chart(app_state, user, session, poll_id, params, headers, ImageFormat::Png).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    use crate::models::tests::poll;
+
+    fn if_none_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn etag_follows_what_the_chart_shows() {
+        let poll = poll(Uuid::new_v4());
+        let tag = etag(&poll);
+        // Not drawn, so not part of the tag
+        assert_eq!(etag(&Poll { id: None, tags: vec!["food".to_string()], ..poll.clone() }), tag);
+
+        let mut voted = poll.clone();
+        voted.options[0].votes += 1;
+        assert_ne!(etag(&voted), tag);
+        assert_ne!(etag(&Poll { title: "Dinner".to_string(), ..poll.clone() }), tag);
+        assert_ne!(etag(&Poll { is_closed: true, ..poll }), tag);
+    }
+
+    #[test]
+    fn if_none_match_accepts_lists_weak_tags_and_wildcards() {
+        let tag = "\"abc\"";
+        assert!(is_fresh(&if_none_match("\"abc\""), tag));
+        assert!(is_fresh(&if_none_match("\"xyz\", W/\"abc\""), tag));
+        assert!(is_fresh(&if_none_match("*"), tag));
+        assert!(!is_fresh(&if_none_match("\"xyz\""), tag));
+        assert!(!is_fresh(&HeaderMap::new(), tag));
+    }
+
+    #[tokio::test]
+    async fn fresh_copy_gets_not_modified() {
+        let poll = poll(Uuid::new_v4());
+        let params = ChartParams::default();
+        let response = chart_response(&poll, &params, &HeaderMap::new(), ImageFormat::Svg).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let tag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+
+        let response = chart_response(&poll, &params, &if_none_match(&tag), ImageFormat::Png).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], tag.as_str());
+    }
+
+    #[test]
+    fn option_texts_are_escaped_in_the_svg() {
+        let mut poll = poll(Uuid::new_v4());
+        poll.options[0].text = "<script>&".to_string();
+        let style = Style::new(&ChartParams::default(), poll.options.len()).unwrap();
+        let svg = render_svg(&poll, &style);
+        assert!(svg.contains("&lt;script&gt;&amp;"));
+        assert!(!svg.contains("<script>"));
+    }
+
+    #[test]
+    fn style_parameters_are_checked() {
+        let params = ChartParams {
+            background: Some("#FFF".to_string()),
+            palette: Some("123456,abc".to_string()),
+            ..ChartParams::default()
+        };
+        let style = Style::new(&params, 2).unwrap();
+        assert_eq!((style.background.as_str(), style.color(3)), ("#fff", "#abc"));
+
+        let bad_color = ChartParams { foreground: Some("red".to_string()), ..ChartParams::default() };
+        assert!(matches!(Style::new(&bad_color, 2), Err(ApiError::Validation { field: "foreground", .. })));
+        let too_wide = ChartParams { width: Some(5000), ..ChartParams::default() };
+        assert!(matches!(Style::new(&too_wide, 2), Err(ApiError::Validation { field: "width", .. })));
+    }
+}
@@ -0,0 +1,665 @@
+// src/routes/embed.rs
+//
+// Server-rendered poll pages for other sites: the `/embed/:poll_id` widget,
+// which only loads for polls whose owner allowed embedding and only inside
+// the origins they listed, and the `/p/:token` share page, whose OpenGraph
+// tags let chat apps unfurl share links. Both vote through `cast_vote`, with
+// a per-session CSRF token since the session cookie is sent cross-site.
+use std::env;
+use axum::{
+    extract::{Extension, Form, Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+use url::form_urlencoded;
+use uuid::Uuid;
+use crate::auth::tokens::AuthUser;
+use crate::error::ApiError;
+use crate::models::{new_share_token, share_link_key, voted_key, Poll, Viewer};
+use crate::routes::charts::{chart_response, escape, ChartParams, ChartTheme, ImageFormat, DEFAULT_PALETTE};
+use crate::routes::polls::{cast_vote, load_viewer, parse_id, reader_id};
+use crate::startup::AppState;
+
+const CSRF_KEY: &str = "embed_csrf";
+const DEFAULT_EMBED_WIDTH: u32 = 480;
+const MAX_EMBED_HEIGHT: u32 = 800;
+const THUMBNAIL_WIDTH: u32 = 800;
+const THUMBNAIL_HEIGHT: u32 = 450;
+
+#[derive(Deserialize, Default)]
+pub struct EmbedParams {
+    // The share token, which lets the widget show an unlisted poll
+    pub token: Option<String>,
+    #[serde(default)]
+    pub theme: ChartTheme,
+}
+
+#[derive(Deserialize)]
+pub struct VoteForm {
+    pub option_id: i32,
+    pub csrf: String,
+}
+
+#[derive(Deserialize)]
+pub struct OEmbedParams {
+    pub url: String,
+    pub format: Option<String>,
+    pub maxwidth: Option<u32>,
+    pub maxheight: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct OEmbedResponse {
+    pub version: &'static str,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub provider_name: String,
+    pub provider_url: String,
+    pub title: String,
+    pub author_name: String,
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_height: Option<u32>,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/embed/:poll_id", get(embed_page))
+        .route("/embed/:poll_id/vote", post(embed_vote))
+        .route("/p/:token", get(share_page))
+        .route("/p/:token/vote", post(share_vote))
+        .route("/p/:token/chart.png", get(share_chart))
+        .route("/oembed", get(oembed))
+}
+
+// Where the backend and the frontend are reached from outside. Without
+// `PUBLIC_URL` the backend is assumed to share the frontend's origin; the
+// Host header is never used, as the client controls it.
+struct Links {
+    backend: String,
+    frontend: String,
+    site_name: String,
+}
+
+impl Links {
+    fn new() -> Self {
+        let frontend = env::var("RP_ORIGIN").unwrap_or_default().trim_end_matches('/').to_string();
+        Links {
+            backend: env::var("PUBLIC_URL")
+                .map(|url| url.trim_end_matches('/').to_string())
+                .unwrap_or_else(|_| frontend.clone()),
+            frontend,
+            site_name: env::var("RP_NAME").unwrap_or_else(|_| "Polling App".to_string()),
+        }
+    }
+}
+
+// The page being served, which decides its URLs and who may frame it
+enum Page<'a> {
+    Embed { poll_id: ObjectId, params: &'a EmbedParams },
+    Share { token: &'a str },
+}
+
+impl Page<'_> {
+    fn path(&self) -> String {
+        match self {
+            Page::Embed { poll_id, params } => {
+                let mut query = form_urlencoded::Serializer::new(String::new());
+                if let Some(token) = &params.token {
+                    query.append_pair("token", token);
+                }
+                if params.theme == ChartTheme::Dark {
+                    query.append_pair("theme", "dark");
+                }
+                match query.finish() {
+                    query if query.is_empty() => format!("/embed/{}", poll_id),
+                    query => format!("/embed/{}?{}", poll_id, query),
+                }
+            }
+            Page::Share { token } => format!("/p/{}", token),
+        }
+    }
+
+    fn vote_path(&self) -> String {
+        match self {
+            Page::Embed { poll_id, .. } => match self.path().split_once('?') {
+                Some((_, query)) => format!("/embed/{}/vote?{}", poll_id, query),
+                None => format!("/embed/{}/vote", poll_id),
+            },
+            Page::Share { token } => format!("/p/{}/vote", token),
+        }
+    }
+
+    fn image_path(&self, poll_id: ObjectId) -> String {
+        let token = match self {
+            Page::Embed { params, .. } => params.token.as_deref(),
+            Page::Share { token } => Some(*token),
+        };
+        match token {
+            Some(token) => format!("/p/{}/chart.png", token),
+            None => format!("/api/polls/{}/chart.png", poll_id),
+        }
+    }
+
+    fn theme(&self) -> ChartTheme {
+        match self {
+            Page::Embed { params, .. } => params.theme,
+            Page::Share { .. } => ChartTheme::Light,
+        }
+    }
+
+    fn frame_ancestors(&self, poll: &Poll) -> String {
+        match self {
+            // Only the backend's own pages until the creator lists origins
+            Page::Embed { .. } if poll.embed_origins.is_empty() => "'self'".to_string(),
+            Page::Embed { .. } => poll.embed_origins.join(" "),
+            Page::Share { .. } => "'none'".to_string(),
+        }
+    }
+}
+
+// Opens the poll for the widget: it must allow embedding and be visible to
+// the session, which the share token in the URL may grant as the share link does
+async fn open_embedded(
+    app_state: &AppState,
+    session: &Session,
+    user_id: Option<Uuid>,
+    poll_id: ObjectId,
+    token: Option<&str>,
+) -> Result<Option<(Poll, Viewer)>, ApiError> {
+    let Some(poll) = app_state.polls.get(poll_id).await? else {
+        return Ok(None);
+    };
+    let mut viewer = load_viewer(app_state, session, user_id, &poll).await?;
+    if token.is_some() && token == poll.share_token.as_deref() {
+        session.insert(&share_link_key(&poll_id), true).await?;
+        viewer.has_share_link = true;
+    }
+    Ok((poll.embeddable && poll.is_visible_to(&viewer)).then_some((poll, viewer)))
+}
+
+// Opens the poll behind a share link, as `/api/p/:token` does
+async fn open_shared(
+    app_state: &AppState,
+    session: &Session,
+    user_id: Option<Uuid>,
+    token: &str,
+) -> Result<Option<(Poll, Viewer)>, ApiError> {
+    let Some(poll) = app_state.polls.get_by_share_token(token).await? else {
+        return Ok(None);
+    };
+    let Some(poll_id) = poll.id else {
+        return Ok(None);
+    };
+    let viewer = Viewer { has_share_link: true, ..load_viewer(app_state, session, user_id, &poll).await? };
+    if !poll.is_visible_to(&viewer) {
+        return Ok(None);
+    }
+    session.insert(&share_link_key(&poll_id), true).await?;
+    Ok(Some((poll, viewer)))
+}
+
+async fn csrf_token(session: &Session) -> Result<String, ApiError> {
+    match session.get::<String>(CSRF_KEY).await? {
+        Some(token) => Ok(token),
+        None => {
+            let token = new_share_token();
+            session.insert(CSRF_KEY, &token).await?;
+            Ok(token)
+        }
+    }
+}
+
+fn html_response(status: StatusCode, body: String, frame_ancestors: &str) -> Response {
+    let policy = format!(
+        "default-src 'none'; style-src 'unsafe-inline'; img-src 'self'; form-action 'self'; base-uri 'none'; \
+         frame-ancestors {}",
+        frame_ancestors,
+    );
+    (
+        status,
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (header::CONTENT_SECURITY_POLICY, policy),
+            // The page carries the session's CSRF token
+            (header::CACHE_CONTROL, "no-store".to_string()),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn not_found_page() -> Response {
+    let body = page_shell(
+        "Poll not available",
+        "",
+        ChartTheme::Light,
+        "<main><p class=\"meta\">This poll does not exist or is not available here.</p></main>",
+    );
+    html_response(StatusCode::NOT_FOUND, body, "*")
+}
+
+fn page_shell(title: &str, head: &str, theme: ChartTheme, main: &str) -> String {
+    let (background, foreground, muted, track) = theme.colors();
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+{head}<style>
+body {{ margin: 0; padding: 16px; background: {background}; color: {foreground}; font: 15px/1.4 system-ui, sans-serif; }}
+h1 {{ margin: 0 0 4px; font-size: 20px; }}
+.meta, .note {{ margin: 0 0 12px; color: {muted}; font-size: 13px; }}
+.error {{ margin: 0 0 12px; color: #dc2626; }}
+ul {{ list-style: none; margin: 0 0 12px; padding: 0; }}
+li {{ margin-bottom: 10px; }}
+.row {{ display: flex; justify-content: space-between; gap: 8px; }}
+.count {{ color: {muted}; white-space: nowrap; }}
+.bar {{ height: 8px; margin-top: 4px; border-radius: 4px; background: {track}; overflow: hidden; }}
+.bar div {{ height: 100%; }}
+button {{ display: block; width: 100%; margin-bottom: 8px; padding: 8px 12px; border: 1px solid {track}; border-radius: 6px; background: transparent; color: inherit; font: inherit; text-align: left; cursor: pointer; }}
+button:hover {{ border-color: {muted}; }}
+a {{ color: inherit; }}
+</style>
+</head>
+<body>
+{main}
+</body>
+</html>
+"#,
+        title = escape(title),
+    )
+}
+
+// OpenGraph and Twitter card tags, plus oEmbed discovery for embeddable polls
+fn meta_tags(poll: &Poll, page: &Page, links: &Links) -> String {
+    let Some(poll_id) = poll.id else {
+        return String::new();
+    };
+    let total: i32 = poll.options.iter().map(|o| o.votes).sum();
+    let options: Vec<&str> = poll.options.iter().map(|o| o.text.as_str()).collect();
+    let description: String = format!(
+        "Poll by {} · {} vote{} · {}",
+        poll.author.as_deref().unwrap_or("Unknown"),
+        total,
+        if total == 1 { "" } else { "s" },
+        options.join(", "),
+    )
+    .chars()
+    .take(200)
+    .collect();
+    let page_url = format!("{}{}", links.backend, page.path());
+    let image_url = format!("{}{}", links.backend, page.image_path(poll_id));
+
+    let mut tags = String::new();
+    for (property, content) in [
+        ("og:type", "website"),
+        ("og:site_name", &links.site_name),
+        ("og:title", &poll.title),
+        ("og:description", &description),
+        ("og:url", &page_url),
+        ("og:image", &image_url),
+        ("og:image:width", &THUMBNAIL_WIDTH.to_string()),
+        ("og:image:height", &THUMBNAIL_HEIGHT.to_string()),
+    ] {
+        tags.push_str(&format!("<meta property=\"{}\" content=\"{}\">\n", property, escape(content)));
+    }
+    tags.push_str("<meta name=\"twitter:card\" content=\"summary_large_image\">\n");
+    if poll.embeddable {
+        let oembed_url = format!(
+            "{}/oembed?{}",
+            links.backend,
+            form_urlencoded::Serializer::new(String::new()).append_pair("url", &page_url).finish(),
+        );
+        tags.push_str(&format!(
+            "<link rel=\"alternate\" type=\"application/json+oembed\" href=\"{}\" title=\"{}\">\n",
+            escape(&oembed_url),
+            escape(&poll.title),
+        ));
+    }
+    tags
+}
+
+// The poll with a vote button per option while the session may still vote,
+// and the results otherwise
+async fn render_poll(
+    session: &Session,
+    poll: &Poll,
+    viewer: &Viewer,
+    page: &Page<'_>,
+    links: &Links,
+    error: Option<&str>,
+) -> Result<String, ApiError> {
+    let poll_id = poll.id.ok_or(ApiError::NotFound("poll"))?;
+    let voted = session.get::<bool>(&voted_key(&poll_id)).await?.unwrap_or(false);
+    let can_vote = viewer.user_id.is_some() && !poll.is_closed && !voted && poll.accepts_votes_from(viewer);
+    let total: i32 = poll.options.iter().map(|o| o.votes).sum();
+
+    let mut main = format!("<main>\n<h1>{}</h1>\n", escape(&poll.title));
+    main.push_str(&format!(
+        "<p class=\"meta\">by {} · {} vote{}{}</p>\n",
+        escape(poll.author.as_deref().unwrap_or("Unknown")),
+        total,
+        if total == 1 { "" } else { "s" },
+        if poll.is_closed { " · closed" } else { "" },
+    ));
+    if let Some(error) = error {
+        main.push_str(&format!("<p class=\"error\">{}</p>\n", escape(error)));
+    }
+
+    if can_vote {
+        main.push_str(&format!(
+            "<form method=\"post\" action=\"{}\">\n<input type=\"hidden\" name=\"csrf\" value=\"{}\">\n",
+            escape(&page.vote_path()),
+            escape(&csrf_token(session).await?),
+        ));
+        for option in &poll.options {
+            main.push_str(&format!(
+                "<button type=\"submit\" name=\"option_id\" value=\"{}\">{}</button>\n",
+                option.id,
+                escape(&option.text),
+            ));
+        }
+        main.push_str("</form>\n");
+    } else {
+        main.push_str("<ul>\n");
+        for (i, option) in poll.options.iter().enumerate() {
+            let percentage = match total {
+                0 => 0.0,
+                total => option.votes as f64 * 100.0 / total as f64,
+            };
+            main.push_str(&format!(
+                "<li><div class=\"row\"><span>{}</span><span class=\"count\">{} ({:.0}%)</span></div>\
+                 <div class=\"bar\"><div style=\"width: {:.1}%; background: {}\"></div></div></li>\n",
+                escape(&option.text),
+                option.votes,
+                percentage,
+                percentage,
+                DEFAULT_PALETTE[i % DEFAULT_PALETTE.len()],
+            ));
+        }
+        main.push_str("</ul>\n");
+        if viewer.user_id.is_none() && !poll.is_closed {
+            main.push_str(&format!(
+                "<p class=\"note\"><a href=\"{}/login\" target=\"_blank\" rel=\"noopener\">Sign in</a> to vote.</p>\n",
+                escape(&links.frontend),
+            ));
+        }
+    }
+    main.push_str(&format!(
+        "<p class=\"note\"><a href=\"{}/polls/{}\" target=\"_blank\" rel=\"noopener\">Open in {}</a></p>\n</main>",
+        escape(&links.frontend),
+        poll_id,
+        escape(&links.site_name),
+    ));
+
+    Ok(page_shell(&poll.title, &meta_tags(poll, page, links), page.theme(), &main))
+}
+
+pub async fn embed_page(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    Path(poll_id): Path<String>,
+    Query(params): Query<EmbedParams>,
+) -> Result<Response, ApiError> {
+    let Ok(poll_id) = parse_id(&poll_id, "poll ID") else {
+        return Ok(not_found_page());
+    };
+    let opened = open_embedded(&app_state, &session, reader_id(user), poll_id, params.token.as_deref()).await?;
+    let Some((poll, viewer)) = opened else {
+        return Ok(not_found_page());
+    };
+    let page = Page::Embed { poll_id, params: &params };
+    let body = render_poll(&session, &poll, &viewer, &page, &Links::new(), None).await?;
+    Ok(html_response(StatusCode::OK, body, &page.frame_ancestors(&poll)))
+}
+
+pub async fn share_page(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    Path(token): Path<String>,
+) -> Result<Response, ApiError> {
+    let Some((poll, viewer)) = open_shared(&app_state, &session, reader_id(user), &token).await? else {
+        return Ok(not_found_page());
+    };
+    let page = Page::Share { token: &token };
+    let body = render_poll(&session, &poll, &viewer, &page, &Links::new(), None).await?;
+    Ok(html_response(StatusCode::OK, body, &page.frame_ancestors(&poll)))
+}
+
+// Votes from the page's form, then shows the page again: after a redirect
+// on success, or right away with the reason the vote was refused
+async fn vote_from_page(
+    app_state: &AppState,
+    user: Option<AuthUser>,
+    session: &Session,
+    (poll, viewer): (Poll, Viewer),
+    page: Page<'_>,
+    form: VoteForm,
+) -> Result<Response, ApiError> {
+    let poll_id = poll.id.ok_or(ApiError::NotFound("poll"))?;
+    let frame_ancestors = page.frame_ancestors(&poll);
+    if session.get::<String>(CSRF_KEY).await?.as_deref() != Some(form.csrf.as_str()) {
+        warn!("Rejected vote on poll {} with a missing or stale CSRF token", poll_id);
+        return Err(ApiError::Forbidden);
+    }
+    let result = match &user {
+        Some(user) => cast_vote(app_state, user, session, poll_id, form.option_id, &app_state.broadcast_tx).await,
+        None => Err(ApiError::Forbidden),
+    };
+    match result {
+        Ok(()) => Ok(Redirect::to(&page.path()).into_response()),
+        Err(e) => {
+            let (_, message, _) = e.client_parts()?;
+            let poll = app_state.polls.get(poll_id).await?.unwrap_or(poll);
+            let body = render_poll(session, &poll, &viewer, &page, &Links::new(), Some(&message)).await?;
+            Ok(html_response(StatusCode::OK, body, &frame_ancestors))
+        }
+    }
+}
+
+pub async fn embed_vote(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    Path(poll_id): Path<String>,
+    Query(params): Query<EmbedParams>,
+    Form(form): Form<VoteForm>,
+) -> Result<Response, ApiError> {
+    let Ok(poll_id) = parse_id(&poll_id, "poll ID") else {
+        return Ok(not_found_page());
+    };
+    let user_id = user.as_ref().map(|user| user.id);
+    let opened = open_embedded(&app_state, &session, user_id, poll_id, params.token.as_deref()).await?;
+    let Some(opened) = opened else {
+        return Ok(not_found_page());
+    };
+    let page = Page::Embed { poll_id, params: &params };
+    vote_from_page(&app_state, user, &session, opened, page, form).await
+}
+
+pub async fn share_vote(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    Path(token): Path<String>,
+    Form(form): Form<VoteForm>,
+) -> Result<Response, ApiError> {
+    let user_id = user.as_ref().map(|user| user.id);
+    let Some(opened) = open_shared(&app_state, &session, user_id, &token).await? else {
+        return Ok(not_found_page());
+    };
+    vote_from_page(&app_state, user, &session, opened, Page::Share { token: &token }, form).await
+}
+
+// The share page's preview image, readable by anyone holding the link
+pub async fn share_chart(
+    Extension(app_state): Extension<AppState>,
+    user: Option<AuthUser>,
+    session: Session,
+    headers: HeaderMap,
+    Path(token): Path<String>,
+    Query(params): Query<ChartParams>,
+) -> Result<Response, ApiError> {
+    let (poll, _) = open_shared(&app_state, &session, reader_id(user), &token)
+        .await?
+        .ok_or(ApiError::NotFound("poll"))?;
+    chart_response(&poll, &params, &headers, ImageFormat::Png).await
+}
+
+// The poll a frontend, widget or share link URL points at, as an anonymous
+// visitor holding that URL would see it. Only URLs on this site's own
+// frontend or backend origin are resolved.
+async fn resolve_url(
+    app_state: &AppState,
+    links: &Links,
+    url: &str,
+) -> Result<Option<(Poll, Option<String>)>, ApiError> {
+    let Ok(url) = url::Url::parse(url) else {
+        return Ok(None);
+    };
+    let own_origin = [&links.frontend, &links.backend]
+        .into_iter()
+        .filter_map(|base| url::Url::parse(base).ok())
+        .any(|base| base.origin() == url.origin());
+    if !own_origin {
+        return Ok(None);
+    }
+    let segments: Vec<&str> = url.path_segments().map(|s| s.filter(|s| !s.is_empty()).collect()).unwrap_or_default();
+    let (poll, token) = match segments.as_slice() {
+        ["p", token] => match app_state.polls.get_by_share_token(token).await? {
+            Some(poll) => (poll, Some(token.to_string())),
+            None => return Ok(None),
+        },
+        ["embed", id] | ["polls", id] | ["polls", id, "results"] => {
+            let Ok(poll_id) = ObjectId::parse_str(id) else {
+                return Ok(None);
+            };
+            let token = url
+                .query_pairs()
+                .find(|(key, _)| key == "token")
+                .map(|(_, value)| value.into_owned());
+            match app_state.polls.get(poll_id).await? {
+                Some(poll) => (poll, token),
+                None => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+    let has_share_link = token.is_some() && token.as_deref() == poll.share_token.as_deref();
+    let viewer = Viewer { has_share_link, ..Viewer::default() };
+    Ok((poll.embeddable && poll.is_visible_to(&viewer)).then(|| (poll, token.filter(|_| has_share_link))))
+}
+
+pub async fn oembed(
+    Extension(app_state): Extension<AppState>,
+    Query(params): Query<OEmbedParams>,
+) -> Result<Response, ApiError> {
+    if params.format.as_deref().is_some_and(|format| format != "json") {
+        return Ok((StatusCode::NOT_IMPLEMENTED, "Only the json format is supported").into_response());
+    }
+    let links = Links::new();
+    let (poll, token) = resolve_url(&app_state, &links, &params.url).await?.ok_or(ApiError::NotFound("poll"))?;
+    let poll_id = poll.id.ok_or(ApiError::NotFound("poll"))?;
+
+    let width = DEFAULT_EMBED_WIDTH.min(params.maxwidth.unwrap_or(u32::MAX));
+    let height = (150 + 52 * poll.options.len() as u32)
+        .min(MAX_EMBED_HEIGHT)
+        .min(params.maxheight.unwrap_or(u32::MAX));
+    let embed_params = EmbedParams { token, ..EmbedParams::default() };
+    let page = Page::Embed { poll_id, params: &embed_params };
+    let html = format!(
+        "<iframe src=\"{}{}\" width=\"{}\" height=\"{}\" style=\"border: 0\" loading=\"lazy\" title=\"{}\"></iframe>",
+        escape(&links.backend),
+        escape(&page.path()),
+        width,
+        height,
+        escape(&poll.title),
+    );
+
+    // Thumbnails shrink to fit the consumer's bounds, down to the chart minimum
+    let thumbnail_width = THUMBNAIL_WIDTH.min(params.maxwidth.unwrap_or(u32::MAX));
+    let thumbnail_height = THUMBNAIL_HEIGHT.min(params.maxheight.unwrap_or(u32::MAX));
+    let thumbnail = (thumbnail_width >= 200 && thumbnail_height >= 200).then(|| {
+        format!(
+            "{}{}?width={}&height={}",
+            links.backend,
+            page.image_path(poll_id),
+            thumbnail_width,
+            thumbnail_height,
+        )
+    });
+
+    info!("Serving oEmbed for poll {}", poll_id);
+    Ok(Json(OEmbedResponse {
+        version: "1.0",
+        kind: "rich",
+        provider_name: links.site_name.clone(),
+        provider_url: links.frontend.clone(),
+        title: poll.title.clone(),
+        author_name: poll.author.clone().unwrap_or_else(|| "Unknown".to_string()),
+        html,
+        width,
+        height,
+        thumbnail_width: thumbnail.as_ref().map(|_| thumbnail_width),
+        thumbnail_height: thumbnail.as_ref().map(|_| thumbnail_height),
+        thumbnail_url: thumbnail,
+    })
+    .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::poll;
+
+    #[test]
+    fn widget_is_framed_only_by_listed_origins() {
+        let params = EmbedParams { token: None, theme: ChartTheme::Light };
+        let embed = Page::Embed { poll_id: ObjectId::new(), params: &params };
+        let mut poll = poll(Uuid::new_v4());
+        assert_eq!(embed.frame_ancestors(&poll), "'self'");
+        poll.embed_origins = vec!["https://wiki.example.com".to_string(), "http://localhost:8080".to_string()];
+        assert_eq!(embed.frame_ancestors(&poll), "https://wiki.example.com http://localhost:8080");
+        // The share page is never framed
+        assert_eq!(Page::Share { token: "token" }.frame_ancestors(&poll), "'none'");
+    }
+
+    #[test]
+    fn pages_carry_their_frame_ancestors() {
+        let response = html_response(StatusCode::OK, String::new(), "https://wiki.example.com");
+        let policy = response.headers()[header::CONTENT_SECURITY_POLICY].to_str().unwrap();
+        assert!(policy.ends_with("frame-ancestors https://wiki.example.com"));
+        assert!(policy.starts_with("default-src 'none';"));
+    }
+
+    #[test]
+    fn widget_links_keep_the_token_and_theme() {
+        let poll_id = ObjectId::new();
+        let params = EmbedParams { token: Some("a b".to_string()), theme: ChartTheme::Dark };
+        let embed = Page::Embed { poll_id, params: &params };
+        assert_eq!(embed.path(), format!("/embed/{}?token=a+b&theme=dark", poll_id));
+        assert_eq!(embed.vote_path(), format!("/embed/{}/vote?token=a+b&theme=dark", poll_id));
+        assert_eq!(Page::Share { token: "abc" }.image_path(poll_id), "/p/abc/chart.png");
+
+        let params = EmbedParams { token: None, theme: ChartTheme::Light };
+        let embed = Page::Embed { poll_id, params: &params };
+        assert_eq!(embed.vote_path(), format!("/embed/{}/vote", poll_id));
+        assert_eq!(embed.image_path(poll_id), format!("/api/polls/{}/chart.png", poll_id));
+    }
+}
@@ -0,0 +1,355 @@
+// src/routes/export.rs
+//
+// Poll results as CSV, JSON or XLSX for managers of the poll: option totals
+// with percentages, then one row per ballot. Ballots name their voters, as
+// `/api/polls/:poll_id/voters` does for the same people.
+use std::io::{Cursor, Write};
+use axum::{
+    extract::{Extension, Path, Query},
+    http::header,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use rust_xlsxwriter::Workbook;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, ZipWriter};
+use crate::auth::tokens::AuthUser;
+use crate::error::ApiError;
+use crate::models::{Poll, PollPermission, Scope};
+use crate::routes::orgs::managed_org_ids;
+use crate::routes::polls::{authorize, parse_id, search_polls, ListParams};
+use crate::startup::AppState;
+use crate::store::PollScope;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Xlsx,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+// The bulk export takes the filters of `/api/polls/manage` as well
+#[derive(Deserialize)]
+pub struct BulkExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(flatten)]
+    pub list: ListParams,
+}
+
+#[derive(Serialize)]
+pub struct OptionTotal {
+    pub id: i32,
+    pub text: String,
+    pub votes: i32,
+    // Share of all votes, rounded to one decimal
+    pub percentage: f64,
+}
+
+#[derive(Serialize)]
+pub struct BallotRow {
+    pub username: String,
+    #[serde(rename = "optionId")]
+    pub option_id: i32,
+    pub option: String,
+    #[serde(rename = "votedAt")]
+    pub voted_at: String,
+}
+
+#[derive(Serialize)]
+pub struct PollExport {
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    #[serde(rename = "isClosed")]
+    pub is_closed: bool,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "totalVotes")]
+    pub total_votes: i32,
+    pub options: Vec<OptionTotal>,
+    pub ballots: Vec<BallotRow>,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/polls/:poll_id/export", get(export_poll))
+        .route("/api/polls/manage/export", get(export_user_polls))
+}
+
+fn internal(what: &str, e: impl std::fmt::Display) -> ApiError {
+    ApiError::Internal(format!("Failed to write {}: {}", what, e))
+}
+
+// Totals and ballots of one poll, with voter names looked up in one batch
+async fn collect(app_state: &AppState, poll: Poll) -> Result<PollExport, ApiError> {
+    let poll_id = poll.id.ok_or(ApiError::NotFound("poll"))?;
+    let votes = app_state.votes.list_for_poll(poll_id).await?;
+    let voter_ids: Vec<String> = votes.iter().map(|v| v.user_id.clone()).collect();
+    let voters = app_state.users.get_many(&voter_ids).await?;
+
+    let total_votes: i32 = poll.options.iter().map(|o| o.votes).sum();
+    let option_text = |id: i32| {
+        poll.options.iter().find(|o| o.id == id).map(|o| o.text.clone()).unwrap_or_default()
+    };
+    let ballots = votes
+        .iter()
+        .map(|vote| BallotRow {
+            username: voters
+                .iter()
+                .find(|u| Uuid::parse_str(&vote.user_id).is_ok_and(|id| id == u.unique_id))
+                .map(|u| u.username.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            option_id: vote.option_id,
+            option: option_text(vote.option_id),
+            voted_at: vote.created_at.try_to_rfc3339_string().unwrap_or_default(),
+        })
+        .collect();
+    let options = poll
+        .options
+        .iter()
+        .map(|o| OptionTotal {
+            id: o.id,
+            text: o.text.clone(),
+            votes: o.votes,
+            percentage: match total_votes {
+                0 => 0.0,
+                total => (o.votes as f64 * 1000.0 / total as f64).round() / 10.0,
+            },
+        })
+        .collect();
+
+    Ok(PollExport {
+        id: poll_id.to_hex(),
+        title: poll.title,
+        author: poll.author.unwrap_or_else(|| "Unknown".to_string()),
+        is_closed: poll.is_closed,
+        created_at: poll.created_at.try_to_rfc3339_string().unwrap_or_default(),
+        total_votes,
+        options,
+        ballots,
+    })
+}
+
+// Spreadsheets run a cell starting with one of these as a formula
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+// Quotes a cell so a spreadsheet shows option texts and usernames as text
+fn csv_cell(cell: String) -> String {
+    if cell.starts_with(FORMULA_PREFIXES) {
+        format!("'{}", cell)
+    } else {
+        cell
+    }
+}
+
+// Appends the rows to `out` as CSV
+fn write_csv(out: Vec<u8>, rows: impl Iterator<Item = [String; 4]>) -> Result<Vec<u8>, ApiError> {
+    let mut writer = csv::Writer::from_writer(out);
+    for row in rows {
+        writer.write_record(row.map(csv_cell)).map_err(|e| internal("CSV", e))?;
+    }
+    writer.into_inner().map_err(|e| internal("CSV", e))
+}
+
+// Totals first, then a blank line and the ballots under their own header
+fn to_csv(export: &PollExport) -> Result<Vec<u8>, ApiError> {
+    let totals = [["option_id", "option", "votes", "percentage"].map(String::from)]
+        .into_iter()
+        .chain(export.options.iter().map(|o| {
+            [o.id.to_string(), o.text.clone(), o.votes.to_string(), format!("{:.1}", o.percentage)]
+        }));
+    let mut out = write_csv(Vec::new(), totals)?;
+    out.push(b'\n');
+    let ballots = [["voter", "option_id", "option", "voted_at"].map(String::from)]
+        .into_iter()
+        .chain(export.ballots.iter().map(|b| {
+            [b.username.clone(), b.option_id.to_string(), b.option.clone(), b.voted_at.clone()]
+        }));
+    write_csv(out, ballots)
+}
+
+// A "Results" sheet with the totals and a "Ballots" sheet
+fn to_xlsx(export: &PollExport) -> Result<Vec<u8>, ApiError> {
+    let xlsx = |e: rust_xlsxwriter::XlsxError| internal("XLSX", e);
+    let mut workbook = Workbook::new();
+
+    let results = workbook.add_worksheet().set_name("Results").map_err(xlsx)?;
+    results.write_string(0, 0, &export.title).map_err(xlsx)?;
+    for (col, header) in ["Option", "Votes", "Percentage"].iter().enumerate() {
+        results.write_string(2, col as u16, *header).map_err(xlsx)?;
+    }
+    for (i, option) in export.options.iter().enumerate() {
+        let row = 3 + i as u32;
+        results.write_string(row, 0, &option.text).map_err(xlsx)?;
+        results.write_number(row, 1, option.votes).map_err(xlsx)?;
+        results.write_number(row, 2, option.percentage).map_err(xlsx)?;
+    }
+
+    let ballots = workbook.add_worksheet().set_name("Ballots").map_err(xlsx)?;
+    for (col, header) in ["Voter", "Option", "Voted at"].iter().enumerate() {
+        ballots.write_string(0, col as u16, *header).map_err(xlsx)?;
+    }
+    for (i, ballot) in export.ballots.iter().enumerate() {
+        let row = 1 + i as u32;
+        ballots.write_string(row, 0, &ballot.username).map_err(xlsx)?;
+        ballots.write_string(row, 1, &ballot.option).map_err(xlsx)?;
+        ballots.write_string(row, 2, &ballot.voted_at).map_err(xlsx)?;
+    }
+
+    workbook.save_to_buffer().map_err(xlsx)
+}
+
+fn render(export: &PollExport, format: ExportFormat) -> Result<Vec<u8>, ApiError> {
+    match format {
+        ExportFormat::Csv => to_csv(export),
+        ExportFormat::Json => serde_json::to_vec_pretty(export).map_err(|e| internal("JSON", e)),
+        ExportFormat::Xlsx => to_xlsx(export),
+    }
+}
+
+// `poll-<id>.<ext>`; titles are left out of file names, which they could break
+fn file_name(export: &PollExport, format: ExportFormat) -> String {
+    format!("poll-{}.{}", export.id, format.extension())
+}
+
+pub async fn export_poll(
+    Extension(app_state): Extension<AppState>,
+    user: AuthUser,
+    Path(poll_id): Path<String>,
+    Query(params): Query<ExportParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    user.require(Scope::PollsRead)?;
+    let poll_id = parse_id(&poll_id, "poll ID")?;
+    let (poll, _) = authorize(&app_state, poll_id, user.id, PollPermission::Manage).await?;
+
+    let export = collect(&app_state, poll).await?;
+    info!("Exporting poll {} as {:?} for user {}", poll_id, params.format, user.id);
+    let body = render(&export, params.format)?;
+    let disposition = format!("attachment; filename=\"{}\"", file_name(&export, params.format));
+    Ok((
+        [
+            (header::CONTENT_TYPE, params.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    ))
+}
+
+// Every poll `/api/polls/manage` lists with the same filters, one file each
+pub async fn export_user_polls(
+    Extension(app_state): Extension<AppState>,
+    user: AuthUser,
+    Query(params): Query<BulkExportParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    user.require(Scope::PollsRead)?;
+    let org_ids = managed_org_ids(&app_state, user.id).await?;
+    let scope = PollScope::Managed { user_id: user.id, org_ids: &org_ids };
+
+    let mut list = ListParams { cursor: None, limit: Some("100".to_string()), ..params.list };
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut count = 0;
+    loop {
+        let page = search_polls(&app_state, scope, &list).await?;
+        for poll in page.polls {
+            let export = collect(&app_state, poll).await?;
+            archive
+                .start_file(file_name(&export, params.format), SimpleFileOptions::default())
+                .map_err(|e| internal("ZIP", e))?;
+            archive.write_all(&render(&export, params.format)?).map_err(|e| internal("ZIP", e))?;
+            count += 1;
+        }
+        match page.next_cursor {
+            Some(cursor) => list.cursor = Some(cursor.encode()),
+            None => break,
+        }
+    }
+    let body = archive.finish().map_err(|e| internal("ZIP", e))?.into_inner();
+    info!("Exported {} polls as {:?} for user {}", count, params.format, user.id);
+
+    let disposition = format!("attachment; filename=\"polls-{}.zip\"", params.format.extension());
+    Ok(([(header::CONTENT_TYPE, "application/zip".to_string()), (header::CONTENT_DISPOSITION, disposition)], body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(option_text: &str, username: &str) -> PollExport {
+        PollExport {
+            id: "650000000000000000000001".to_string(),
+            title: "Lunch".to_string(),
+            author: "alice".to_string(),
+            is_closed: false,
+            created_at: "2026-10-19T09:00:00Z".to_string(),
+            total_votes: 3,
+            options: vec![
+                OptionTotal { id: 1, text: option_text.to_string(), votes: 2, percentage: 66.7 },
+                OptionTotal { id: 2, text: "Sushi, rolls".to_string(), votes: 1, percentage: 33.3 },
+            ],
+            ballots: vec![BallotRow {
+                username: username.to_string(),
+                option_id: 1,
+                option: option_text.to_string(),
+                voted_at: "2026-10-19T10:00:00Z".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn formula_cells_are_escaped() {
+        for cell in ["=1+1", "+1", "-1", "@SUM(A1)", "\tx", "\rx"] {
+            assert_eq!(csv_cell(cell.to_string()), format!("'{}", cell));
+        }
+        assert_eq!(csv_cell("Pizza = good".to_string()), "Pizza = good");
+        assert_eq!(csv_cell(String::new()), "");
+    }
+
+    #[test]
+    fn csv_has_totals_then_ballots() {
+        let csv = String::from_utf8(to_csv(&export("=HYPERLINK(\"x\")", "bob")).unwrap()).unwrap();
+        assert_eq!(
+            csv,
+            "option_id,option,votes,percentage\n\
+             1,\"'=HYPERLINK(\"\"x\"\")\",2,66.7\n\
+             2,\"Sushi, rolls\",1,33.3\n\
+             \n\
+             voter,option_id,option,voted_at\n\
+             bob,1,\"'=HYPERLINK(\"\"x\"\")\",2026-10-19T10:00:00Z\n"
+        );
+    }
+
+    #[test]
+    fn xlsx_is_a_zip_archive() {
+        let xlsx = to_xlsx(&export("Pizza", "bob")).unwrap();
+        assert!(xlsx.starts_with(b"PK"));
+    }
+}
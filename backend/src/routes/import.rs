@@ -0,0 +1,249 @@
+// src/routes/import.rs
+//
+// Bulk creation of polls from CSV or JSON. Every row goes through the same
+// checks as `POST /api/polls`, and nothing is stored unless all rows pass and
+// are written together, so a corrected file can be sent again without
+// creating duplicates.
+use axum::{
+    body::Bytes,
+    extract::{Extension, Json, Query},
+    http::{header, HeaderMap},
+    routing::post,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use crate::auth::tokens::AuthUser;
+use crate::error::ApiError;
+use crate::models::{Poll, Recurrence, Scope, Visibility};
+use crate::routes::polls::{build_poll, CreatePollRequest, PollResponse};
+use crate::startup::AppState;
+
+const MAX_IMPORT_ROWS: usize = 500;
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+}
+
+// One CSV line. Lists are separated by `|` and the recurrence, if any, is
+// the same JSON object `POST /api/polls` takes.
+#[derive(Deserialize)]
+struct CsvRow {
+    title: String,
+    options: String,
+    #[serde(default)]
+    visibility: Option<Visibility>,
+    #[serde(default)]
+    invited: Option<String>,
+    #[serde(rename = "orgId", default)]
+    org_id: Option<String>,
+    #[serde(default)]
+    tags: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    recurrence: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RowError {
+    // 1-based position among the polls, not counting the CSV header
+    pub row: usize,
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    pub imported: usize,
+    // The created polls, or in a dry run the polls the valid rows would create
+    pub polls: Vec<PollResponse>,
+    pub errors: Vec<RowError>,
+}
+
+pub fn router() -> Router {
+    Router::new().route("/api/polls/import", post(import_polls))
+}
+
+fn split_list(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split('|')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+impl TryFrom<CsvRow> for CreatePollRequest {
+    type Error = String;
+
+    fn try_from(row: CsvRow) -> Result<Self, String> {
+        let recurrence = match row.recurrence.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(json) => Some(
+                serde_json::from_str::<Recurrence>(json)
+                    .map_err(|e| format!("Recurrence must be a JSON schedule: {}", e))?,
+            ),
+        };
+        Ok(CreatePollRequest {
+            title: row.title,
+            options: row.options.split('|').map(|option| option.trim().to_string()).collect(),
+            recurrence,
+            visibility: row.visibility.unwrap_or_default(),
+            invited: split_list(row.invited),
+            org_id: row.org_id.filter(|id| !id.trim().is_empty()),
+            tags: split_list(row.tags),
+            category: row.category,
+        })
+    }
+}
+
+// Rows of the body, each either a poll to validate or why it is unreadable
+fn parse_rows(headers: &HeaderMap, body: &[u8]) -> Result<Vec<Result<CreatePollRequest, String>>, ApiError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    match content_type.as_str() {
+        "application/json" => {
+            let rows: Vec<serde_json::Value> = serde_json::from_slice(body)
+                .map_err(|_| ApiError::validation("body", "Expected a JSON array of polls"))?;
+            Ok(rows
+                .into_iter()
+                .map(|row| serde_json::from_value::<CreatePollRequest>(row).map_err(|e| e.to_string()))
+                .collect())
+        }
+        "text/csv" => {
+            let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::Headers).from_reader(body);
+            Ok(reader
+                .deserialize::<CsvRow>()
+                .map(|row| row.map_err(|e| e.to_string()).and_then(CreatePollRequest::try_from))
+                .collect())
+        }
+        _ => Err(ApiError::validation("body", "Send the polls as text/csv or application/json")),
+    }
+}
+
+pub async fn import_polls(
+    Extension(app_state): Extension<AppState>,
+    user: AuthUser,
+    Query(params): Query<ImportParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ImportReport>, ApiError> {
+    user.require(Scope::PollsWrite)?;
+    let rows = parse_rows(&headers, &body)?;
+    if rows.is_empty() {
+        return Err(ApiError::validation("body", "Nothing to import"));
+    }
+    if rows.len() > MAX_IMPORT_ROWS {
+        return Err(ApiError::validation("body", "At most 500 polls can be imported at once"));
+    }
+
+    let mut polls: Vec<Poll> = Vec::new();
+    let mut errors = Vec::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        let request = match row {
+            Ok(request) => request,
+            Err(message) => {
+                errors.push(RowError { row: i + 1, code: "invalid_row", message, details: None });
+                continue;
+            }
+        };
+        match build_poll(&app_state, user.id, request).await {
+            Ok(poll) => polls.push(poll),
+            // Internal failures are not the row's fault and abort the import
+            Err(e) => {
+                let (code, message, details) = e.client_parts()?;
+                errors.push(RowError { row: i + 1, code, message, details });
+            }
+        }
+    }
+
+    let author = app_state
+        .users
+        .get(user.id)
+        .await?
+        .ok_or(ApiError::NotFound("user"))?
+        .username;
+    if params.dry_run || !errors.is_empty() {
+        info!("Import by user {} checked: {} valid, {} invalid", user.id, polls.len(), errors.len());
+        let polls = match params.dry_run {
+            true => polls
+                .into_iter()
+                .map(|poll| PollResponse::from_poll(Poll { author: Some(author.clone()), ..poll }))
+                .collect(),
+            false => Vec::new(),
+        };
+        return Ok(Json(ImportReport { dry_run: params.dry_run, imported: 0, polls, errors }));
+    }
+
+    // One batch, so a failed write leaves none of the rows behind
+    let poll_ids = app_state.polls.insert_many(&polls).await?;
+    let mut created = Vec::with_capacity(polls.len());
+    for (poll, poll_id) in polls.into_iter().zip(poll_ids) {
+        let poll = Poll { id: Some(poll_id), author: Some(author.clone()), ..poll };
+        let _ = app_state.broadcast_tx.send(poll.clone());
+        created.push(PollResponse::for_owner(poll));
+    }
+    info!("Imported {} polls for user {}", created.len(), user.id);
+    Ok(Json(ImportReport { dry_run: false, imported: created.len(), polls: created, errors }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Frequency;
+
+    fn content_type(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn csv_rows_split_their_lists() {
+        let body = "title, options ,visibility,invited,tags,category,recurrence\n\
+                    Lunch,Pizza | Sushi|,private,bob||carol,Food|Team,,\"{\"\"frequency\"\":\"\"daily\"\",\"\"hour\"\":9,\"\"minute\"\":0}\"\n";
+        let rows = parse_rows(&content_type("text/csv; charset=utf-8"), body.as_bytes()).unwrap();
+        let [Ok(request)] = rows.as_slice() else { panic!("expected one valid row") };
+        assert_eq!(request.title, "Lunch");
+        // Empty options are kept for the same check as `POST /api/polls`
+        assert_eq!(request.options, ["Pizza", "Sushi", ""]);
+        assert_eq!(request.visibility, Visibility::Private);
+        assert_eq!(request.invited, ["bob", "carol"]);
+        assert_eq!(request.tags, ["Food", "Team"]);
+        assert_eq!(request.recurrence.as_ref().map(|r| r.frequency), Some(Frequency::Daily));
+        assert_eq!(request.org_id, None);
+    }
+
+    #[test]
+    fn unreadable_rows_are_reported_one_by_one() {
+        let body = "title,options,recurrence\nLunch,Pizza|Sushi,\nDinner,Tacos|Curry,weekly\n";
+        let rows = parse_rows(&content_type("text/csv"), body.as_bytes()).unwrap();
+        assert!(rows[0].is_ok());
+        assert!(rows[1].as_ref().is_err_and(|e| e.starts_with("Recurrence must be a JSON schedule")));
+
+        let body = r#"[{ "title": "Lunch", "options": ["Pizza", "Sushi"] }, { "title": "No options" }]"#;
+        let rows = parse_rows(&content_type("application/json"), body.as_bytes()).unwrap();
+        assert!(rows[0].is_ok());
+        assert!(rows[1].as_ref().is_err_and(|e| e.contains("options")));
+    }
+
+    #[test]
+    fn bodies_that_are_not_a_list_of_polls_are_refused() {
+        let refused = |headers: HeaderMap, body: &str| {
+            matches!(parse_rows(&headers, body.as_bytes()), Err(ApiError::Validation { field: "body", .. }))
+        };
+        assert!(refused(content_type("application/json"), r#"{ "title": "Lunch" }"#));
+        assert!(refused(content_type("text/plain"), "title,options\n"));
+        assert!(refused(HeaderMap::new(), "[]"));
+    }
+}
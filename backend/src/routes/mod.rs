@@ -1 +1,8 @@
+pub mod account;
+pub mod admin;
+pub mod charts;
+pub mod embed;
+pub mod export;
+pub mod import;
+pub mod orgs;
 pub mod polls;
\ No newline at end of file
@@ -0,0 +1,263 @@
+use axum::{
+    extract::{Extension, Json, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use mongodb::bson::oid::ObjectId;
+use crate::auth::tokens::SessionUser;
+use crate::error::ApiError;
+use crate::startup::AppState;
+use crate::models::{OrgMember, OrgRole, Organization};
+use crate::routes::polls::{parse_id, PollResponse};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct CreateOrgRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct MemberRequest {
+    pub username: String,
+    pub role: OrgRole,
+}
+
+#[derive(Deserialize)]
+pub struct RemoveMemberRequest {
+    pub username: String,
+}
+
+#[derive(Serialize)]
+pub struct MemberResponse {
+    pub username: String,
+    pub role: OrgRole,
+}
+
+#[derive(Serialize)]
+pub struct OrgResponse {
+    pub id: String,
+    pub name: String,
+    pub role: OrgRole,
+    pub members: Vec<MemberResponse>,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/orgs", post(create_org).get(get_user_orgs))
+        .route("/api/orgs/:org_id", get(get_org))
+        .route("/api/orgs/:org_id/members", post(set_member))
+        .route("/api/orgs/:org_id/members/remove", post(remove_member))
+        .route("/api/orgs/:org_id/polls", get(get_org_polls))
+}
+
+// The user's role in an organization, or None if they are not a member
+pub async fn role_in_org(
+    app_state: &AppState,
+    org_id: &ObjectId,
+    user_id: Uuid,
+) -> Result<Option<OrgRole>, ApiError> {
+    let org = app_state.orgs.get(*org_id).await?;
+    Ok(org.and_then(|org| org.role_of(user_id)))
+}
+
+// IDs of the organizations whose polls the user may manage
+pub async fn managed_org_ids(app_state: &AppState, user_id: Uuid) -> Result<Vec<ObjectId>, ApiError> {
+    let orgs = app_state.orgs.list_for_member(&user_id.to_string()).await?;
+    Ok(orgs
+        .into_iter()
+        .filter(|org| org.role_of(user_id).is_some_and(OrgRole::can_manage_polls))
+        .filter_map(|org| org.id)
+        .collect())
+}
+
+async fn org_response(app_state: &AppState, org: Organization, role: OrgRole) -> Result<OrgResponse, ApiError> {
+    let member_ids: Vec<String> = org.members.iter().map(|m| m.user_id.clone()).collect();
+    let users = app_state.users.get_many(&member_ids).await?;
+    let members = org
+        .members
+        .iter()
+        .map(|member| MemberResponse {
+            username: users
+                .iter()
+                .find(|u| u.unique_id.to_string() == member.user_id)
+                .map(|u| u.username.clone())
+                .unwrap_or_else(|| "Unknown".to_string()),
+            role: member.role,
+        })
+        .collect();
+    Ok(OrgResponse {
+        id: org.id.map(|id| id.to_hex()).unwrap_or_default(),
+        name: org.name,
+        role,
+        members,
+    })
+}
+
+// Loads an organization the session user belongs to, with their role
+async fn load_membership(
+    app_state: &AppState,
+    org_id: &str,
+    user_unique_id: Uuid,
+) -> Result<(Organization, OrgRole), ApiError> {
+    let org_id = parse_id(org_id, "organization ID")?;
+    let org = app_state
+        .orgs
+        .get(org_id)
+        .await?
+        .ok_or_else(|| {
+            debug!("Organization {} not found", org_id);
+            ApiError::NotFound("organization")
+        })?;
+    // Non-members cannot tell the organization exists
+    let role = org.role_of(user_unique_id).ok_or_else(|| {
+        warn!("User {} is not a member of organization {}", user_unique_id, org_id);
+        ApiError::NotFound("organization")
+    })?;
+    Ok((org, role))
+}
+
+pub async fn create_org(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    Json(org_data): Json<CreateOrgRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let name = org_data.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::validation("name", "Name must not be empty"));
+    }
+
+    let mut org = Organization {
+        id: None,
+        name: name.to_string(),
+        members: vec![OrgMember {
+            user_id: user_unique_id.to_string(),
+            role: OrgRole::Owner,
+        }],
+        created_at: mongodb::bson::DateTime::now(),
+    };
+
+    match app_state.orgs.insert(&org).await {
+        Ok(org_id) => {
+            info!("Organization created by user {}: {}", user_unique_id, org_id);
+            org.id = Some(org_id);
+            Ok(Json(org_response(&app_state, org, OrgRole::Owner).await?))
+        }
+        Err(e) => {
+            error!("Failed to insert organization: {:?}", e);
+            Err(e)
+        }
+    }
+}
+
+pub async fn get_user_orgs(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+) -> Result<impl IntoResponse, ApiError> {
+    let orgs = app_state.orgs.list_for_member(&user_unique_id.to_string()).await?;
+    info!("Found {} organizations for user {}", orgs.len(), user_unique_id);
+
+    let mut response = Vec::with_capacity(orgs.len());
+    for org in orgs {
+        if let Some(role) = org.role_of(user_unique_id) {
+            response.push(org_response(&app_state, org, role).await?);
+        }
+    }
+    Ok(Json(response))
+}
+
+pub async fn get_org(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    Path(org_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (org, role) = load_membership(&app_state, &org_id, user_unique_id).await?;
+    Ok(Json(org_response(&app_state, org, role).await?))
+}
+
+// Adds a member or changes an existing member's role. Owners only.
+pub async fn set_member(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    Path(org_id): Path<String>,
+    Json(member): Json<MemberRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (mut org, role) = load_membership(&app_state, &org_id, user_unique_id).await?;
+    if role != OrgRole::Owner {
+        warn!("User {} is not an owner of organization {}", user_unique_id, org_id);
+        return Err(ApiError::Forbidden);
+    }
+
+    let user = app_state
+        .users
+        .find_by_username(&member.username)
+        .await?
+        .ok_or(ApiError::NotFound("user"))?;
+    let member_id = user.unique_id.to_string();
+
+    match org.members.iter_mut().find(|m| m.user_id == member_id) {
+        Some(existing) => existing.role = member.role,
+        None => org.members.push(OrgMember { user_id: member_id, role: member.role }),
+    }
+    if !org.members.iter().any(|m| m.role == OrgRole::Owner) {
+        debug!("Refusing to leave organization {} without an owner", org_id);
+        return Err(ApiError::conflict("sole_organization_owner", "The organization needs another owner first"));
+    }
+
+    if let Some(id) = org.id {
+        app_state.orgs.set_members(id, &org.members).await?;
+    }
+    info!("User {} set to {:?} in organization {} by {}", user.username, member.role, org_id, user_unique_id);
+    Ok(Json(org_response(&app_state, org, role).await?))
+}
+
+pub async fn remove_member(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    Path(org_id): Path<String>,
+    Json(request): Json<RemoveMemberRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (mut org, role) = load_membership(&app_state, &org_id, user_unique_id).await?;
+    let user = app_state
+        .users
+        .find_by_username(&request.username)
+        .await?
+        .ok_or(ApiError::NotFound("user"))?;
+
+    // Members may always leave; removing someone else takes an owner
+    if role != OrgRole::Owner && user.unique_id != user_unique_id {
+        warn!("User {} is not an owner of organization {}", user_unique_id, org_id);
+        return Err(ApiError::Forbidden);
+    }
+
+    let member_id = user.unique_id.to_string();
+    org.members.retain(|m| m.user_id != member_id);
+    if !org.members.iter().any(|m| m.role == OrgRole::Owner) {
+        debug!("Refusing to remove the last owner of organization {}", org_id);
+        return Err(ApiError::conflict("sole_organization_owner", "The organization needs another owner first"));
+    }
+
+    if let Some(id) = org.id {
+        app_state.orgs.set_members(id, &org.members).await?;
+    }
+    info!("User {} removed from organization {} by {}", user.username, org_id, user_unique_id);
+    Ok(StatusCode::OK)
+}
+
+pub async fn get_org_polls(
+    Extension(app_state): Extension<AppState>,
+    SessionUser { id: user_unique_id }: SessionUser,
+    Path(org_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let (org, _) = load_membership(&app_state, &org_id, user_unique_id).await?;
+    let polls = match org.id {
+        Some(id) => app_state.polls.list_by_org(id).await?,
+        None => Vec::new(),
+    };
+    info!("Found {} polls for organization {}", polls.len(), org_id);
+
+    let response: Vec<PollResponse> = polls.into_iter().map(PollResponse::from_poll).collect();
+    Ok(Json(response))
+}
@@ -0,0 +1,98 @@
+// src/scheduler/mod.rs
+use chrono::Utc;
+use mongodb::bson::DateTime;
+use crate::error::ApiError;
+use crate::models::{Poll, PollOption};
+use crate::startup::AppState;
+
+const TICK_SECONDS: u64 = 60;
+
+// Periodically rolls over recurring polls whose next occurrence is due.
+pub fn spawn(app_state: AppState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(TICK_SECONDS));
+        loop {
+            interval.tick().await;
+            if let Err(e) = run_due_recurrences(&app_state).await {
+                error!("Recurring poll scheduler failed: {:?}", e);
+            }
+        }
+    });
+    info!("Started recurring poll scheduler ({}s interval)", TICK_SECONDS);
+}
+
+async fn run_due_recurrences(app_state: &AppState) -> Result<(), ApiError> {
+    let due = app_state.polls.list_due(DateTime::now()).await?;
+
+    for poll in due {
+        if let Err(e) = roll_over(app_state, poll).await {
+            error!("Failed to roll over recurring poll: {:?}", e);
+        }
+    }
+    Ok(())
+}
+
+// Closes the current instance and opens the next one with the same options.
+async fn roll_over(app_state: &AppState, poll: Poll) -> Result<(), ApiError> {
+    let (Some(poll_id), Some(recurrence), Some(due_at)) = (poll.id, poll.recurrence.clone(), poll.next_occurrence_at) else {
+        return Ok(());
+    };
+
+    // Claim the rollover by moving the schedule (and share link) off this
+    // instance, so a second server running the scheduler cannot create a
+    // duplicate instance.
+    if !app_state.polls.claim_rollover(poll_id, due_at).await? {
+        info!("Recurring poll {} already rolled over elsewhere", poll_id);
+        return Ok(());
+    }
+
+    // Skip occurrences missed while the server was down
+    let next_occurrence_at = recurrence
+        .next_after(Utc::now())
+        .map(|t| DateTime::from_system_time(t.into()));
+    let next_poll = Poll {
+        id: None,
+        title: poll.title.clone(),
+        options: poll.options.iter().map(|opt| PollOption {
+            id: opt.id,
+            text: opt.text.clone(),
+            votes: 0,
+        }).collect(),
+        creator_id: poll.creator_id,
+        is_closed: false,
+        created_at: DateTime::now(),
+        author: None,
+        recurrence: Some(recurrence),
+        next_occurrence_at,
+        series_id: Some(poll.series_id.unwrap_or(poll_id)),
+        previous_instance_id: Some(poll_id),
+        visibility: poll.visibility,
+        share_token: poll.share_token.clone(),
+        invited_user_ids: poll.invited_user_ids.clone(),
+        org_id: poll.org_id,
+        collaborator_ids: poll.collaborator_ids.clone(),
+        tags: poll.tags.clone(),
+        category: poll.category.clone(),
+        embeddable: poll.embeddable,
+        embed_origins: poll.embed_origins.clone(),
+    };
+
+    // The claim already took the schedule off this instance, so hand it back
+    // for the next tick rather than losing the series
+    let next_id = match app_state.polls.insert(&next_poll).await {
+        Ok(next_id) => next_id,
+        Err(e) => {
+            app_state.polls.release_rollover(&poll).await?;
+            return Err(e);
+        }
+    };
+    info!("Recurring poll {} rolled over into {}", poll_id, next_id);
+
+    // Both reads resolve the author
+    for id in [poll_id, next_id] {
+        if let Ok(Some(updated_poll)) = app_state.polls.get(id).await {
+            let _ = app_state.broadcast_tx.send(updated_poll);
+        }
+    }
+    Ok(())
+}
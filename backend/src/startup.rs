@@ -1,20 +1,76 @@
 use std::sync::Arc;
 use webauthn_rs::prelude::*;
-use mongodb::{Client, Database};
 use serde::{Serialize, Deserialize};
 use dotenv::dotenv;
-use tracing::info;
 use uuid::Uuid;
 use tokio::sync::broadcast::{self, Sender};
+use crate::auth::policy::AuthenticatorPolicy;
 use crate::models::Poll;
+use crate::store::{
+    self, AdminLogStore, OrgStore, PollStore, SessionBackend, StorageKind, TokenStore, UserSessionStore, UserStore,
+    VoteStore,
+};
 use std::env;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct UserData {
     pub username: String,
+    // Case-insensitive lookup form of `username`, see `auth::username`
+    #[serde(default)]
+    pub username_key: String,
     #[serde(deserialize_with = "deserialize_uuid", serialize_with = "serialize_uuid")]
     pub unique_id: Uuid,
     pub passkeys: Vec<Passkey>,
+    #[serde(default)]
+    pub is_admin: bool,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default)]
+    pub passkey_meta: Vec<PasskeyMeta>,
+    // SHA-256 hex digests of the unused recovery codes
+    #[serde(default)]
+    pub recovery_codes: Vec<String>,
+}
+
+// Display details for a passkey, matched to `passkeys` by credential ID.
+// Accounts created before these were recorded have no entries.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PasskeyMeta {
+    pub cred_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aaguid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<mongodb::bson::DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<mongodb::bson::DateTime>,
+    // Attestation recorded at registration, see `auth::policy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_format: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_verified: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_eligible: Option<bool>,
+}
+
+impl PasskeyMeta {
+    // Placeholder for a passkey registered before metadata was kept
+    pub fn unknown(cred_id: String) -> Self {
+        Self {
+            cred_id,
+            nickname: None,
+            aaguid: None,
+            created_at: None,
+            last_used_at: None,
+            attestation_format: None,
+            attestation_type: None,
+            user_verified: None,
+            backup_eligible: None,
+        }
+    }
 }
 
 fn deserialize_uuid<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
@@ -35,8 +91,16 @@ where
 #[derive(Clone)]
 pub struct AppState {
     pub webauthn: Arc<Webauthn>,
-    pub db: Database,
+    pub users: Arc<dyn UserStore>,
+    pub polls: Arc<dyn PollStore>,
+    pub votes: Arc<dyn VoteStore>,
+    pub orgs: Arc<dyn OrgStore>,
+    pub user_sessions: Arc<dyn UserSessionStore>,
+    pub api_tokens: Arc<dyn TokenStore>,
+    pub admin_log: Arc<dyn AdminLogStore>,
     pub broadcast_tx: Arc<Sender<Poll>>,
+    pub auth_policy: Arc<AuthenticatorPolicy>,
+    pub session_store: SessionBackend,
 }
 
 impl AppState {
@@ -54,21 +118,25 @@ impl AppState {
         let builder = builder.rp_name(&rp_name);
         let webauthn = Arc::new(builder.build().expect("Failed to build WebAuthn"));
 
-        let mongo_uri = std::env::var("MONGODB_URI").expect("MONGODB_URI must be set in .env");
-        info!("Connecting to MongoDB ....");
-        let client = Client::with_uri_str(&mongo_uri)
-            .await
-            .expect("Failed to connect to MongoDB");
-        let db = client.database("polling-app");
-        info!("Using database: polling_db, collection: users");
+        let stores = store::open(StorageKind::from_env()).await;
 
         let (tx, _) = broadcast::channel::<Poll>(100);
         let broadcast_tx = Arc::new(tx);
 
-        Self { webauthn, db, broadcast_tx }
-    }
+        let auth_policy = Arc::new(AuthenticatorPolicy::load());
 
-    pub fn users_collection(&self) -> mongodb::Collection<UserData> {
-        self.db.collection("users")
+        Self {
+            webauthn,
+            users: stores.users,
+            polls: stores.polls,
+            votes: stores.votes,
+            orgs: stores.orgs,
+            user_sessions: stores.user_sessions,
+            api_tokens: stores.api_tokens,
+            admin_log: stores.admin_log,
+            broadcast_tx,
+            auth_policy,
+            session_store: stores.session_store,
+        }
     }
-}
\ No newline at end of file
+}
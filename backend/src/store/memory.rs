@@ -0,0 +1,948 @@
+// src/store/memory.rs
+//
+// Keeps everything in process memory, for tests and demos without a
+// database. The unique indexes of the MongoDB backend are enforced by hand.
+use std::sync::{Arc, Mutex, MutexGuard};
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use tower_sessions::MemoryStore;
+use uuid::Uuid;
+use webauthn_rs::prelude::{AuthenticationResult, Passkey};
+use crate::auth::username::username_key;
+use crate::error::ApiError;
+use crate::models::{AdminAction, ApiToken, OrgMember, Organization, Poll, PollOption, Recurrence, UserSession, Visibility, Vote};
+use crate::startup::{PasskeyMeta, UserData};
+use super::{
+    AdminLogStore, OrgStore, PollAccess, PollPage, PollQuery, PollScope, PollStore, SessionBackend, StoreResult, Stores, TokenStore,
+    UserSessionStore, UserStore, VoteStore,
+};
+
+pub fn open() -> Stores {
+    let db = Arc::new(MemoryDb::default());
+    Stores {
+        users: db.clone(),
+        polls: db.clone(),
+        votes: db.clone(),
+        orgs: db.clone(),
+        user_sessions: db.clone(),
+        api_tokens: db.clone(),
+        admin_log: db,
+        session_store: SessionBackend::Memory(MemoryStore::default()),
+    }
+}
+
+#[derive(Default)]
+struct Tables {
+    users: Vec<UserData>,
+    polls: Vec<Poll>,
+    votes: Vec<Vote>,
+    orgs: Vec<Organization>,
+    user_sessions: Vec<UserSession>,
+    api_tokens: Vec<ApiToken>,
+    admin_log: Vec<AdminAction>,
+}
+
+impl Tables {
+    fn user_mut(&mut self, id: Uuid) -> Option<&mut UserData> {
+        self.users.iter_mut().find(|u| u.unique_id == id)
+    }
+
+    fn poll_mut(&mut self, id: ObjectId) -> Option<&mut Poll> {
+        self.polls.iter_mut().find(|p| p.id == Some(id))
+    }
+
+    fn managed_mut(&mut self, id: ObjectId, access: PollAccess) -> Option<&mut Poll> {
+        self.poll_mut(id).filter(|p| access.allows(p))
+    }
+
+    // Copies of the matching polls with `author` resolved
+    fn find_polls(&self, filter: impl Fn(&Poll) -> bool) -> Vec<Poll> {
+        self.polls
+            .iter()
+            .filter(|p| filter(p))
+            .map(|p| Poll {
+                author: self.users.iter().find(|u| u.unique_id == p.creator_id).map(|u| u.username.clone()),
+                ..p.clone()
+            })
+            .collect()
+    }
+
+    fn username_taken(&self, key: &str, except: Option<Uuid>) -> bool {
+        self.users.iter().any(|u| u.username_key == key && Some(u.unique_id) != except)
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryDb(Mutex<Tables>);
+
+impl MemoryDb {
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        // A panic while holding the lock leaves the tables usable
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl UserStore for MemoryDb {
+    async fn get(&self, id: Uuid) -> StoreResult<Option<UserData>> {
+        Ok(self.tables().users.iter().find(|u| u.unique_id == id).cloned())
+    }
+
+    async fn find_by_username(&self, username: &str) -> StoreResult<Option<UserData>> {
+        let key = username_key(username);
+        Ok(self.tables().users.iter().find(|u| u.username_key == key).cloned())
+    }
+
+    async fn find_by_recovery_code(&self, username: &str, code_hash: &str) -> StoreResult<Option<UserData>> {
+        let key = username_key(username);
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .find(|u| u.username_key == key && u.recovery_codes.iter().any(|c| c == code_hash))
+            .cloned())
+    }
+
+    async fn get_many(&self, ids: &[String]) -> StoreResult<Vec<UserData>> {
+        Ok(self
+            .tables()
+            .users
+            .iter()
+            .filter(|u| ids.contains(&u.unique_id.to_string()))
+            .cloned()
+            .collect())
+    }
+
+    async fn list(&self) -> StoreResult<Vec<UserData>> {
+        let mut users = self.tables().users.clone();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        Ok(users)
+    }
+
+    async fn insert(&self, user: &UserData) -> StoreResult<()> {
+        let mut tables = self.tables();
+        if tables.username_taken(&user.username_key, None) {
+            return Err(ApiError::UsernameTaken);
+        }
+        tables.users.push(user.clone());
+        Ok(())
+    }
+
+    async fn rename(&self, id: Uuid, username: &str) -> StoreResult<()> {
+        let mut tables = self.tables();
+        let key = username_key(username);
+        if tables.username_taken(&key, Some(id)) {
+            return Err(ApiError::UsernameTaken);
+        }
+        if let Some(user) = tables.user_mut(id) {
+            user.username = username.to_string();
+            user.username_key = key;
+        }
+        Ok(())
+    }
+
+    async fn set_admin(&self, id: Uuid, is_admin: bool) -> StoreResult<()> {
+        if let Some(user) = self.tables().user_mut(id) {
+            user.is_admin = is_admin;
+        }
+        Ok(())
+    }
+
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> StoreResult<()> {
+        if let Some(user) = self.tables().user_mut(id) {
+            user.disabled = disabled;
+        }
+        Ok(())
+    }
+
+    async fn update_credential(&self, id: Uuid, result: &AuthenticationResult) -> StoreResult<bool> {
+        let mut tables = self.tables();
+        let Some(user) = tables.user_mut(id) else {
+            return Ok(false);
+        };
+        Ok(user.passkeys.iter_mut().any(|sk| sk.update_credential(result).is_some()))
+    }
+
+    async fn add_passkey(&self, id: Uuid, passkey: &Passkey, meta: &PasskeyMeta) -> StoreResult<()> {
+        if let Some(user) = self.tables().user_mut(id) {
+            user.passkeys.push(passkey.clone());
+            user.passkey_meta.push(meta.clone());
+        }
+        Ok(())
+    }
+
+    async fn add_passkey_with_recovery_code(
+        &self,
+        id: Uuid,
+        code_hash: &str,
+        passkey: &Passkey,
+        meta: &PasskeyMeta,
+    ) -> StoreResult<bool> {
+        let mut tables = self.tables();
+        let Some(user) = tables.user_mut(id) else {
+            return Ok(false);
+        };
+        let Some(index) = user.recovery_codes.iter().position(|c| c == code_hash) else {
+            return Ok(false);
+        };
+        user.recovery_codes.remove(index);
+        user.passkeys.push(passkey.clone());
+        user.passkey_meta.push(meta.clone());
+        Ok(true)
+    }
+
+    async fn replace_passkeys(
+        &self,
+        id: Uuid,
+        expected_count: usize,
+        passkeys: &[Passkey],
+        meta: &[PasskeyMeta],
+    ) -> StoreResult<bool> {
+        let mut tables = self.tables();
+        match tables.user_mut(id) {
+            Some(user) if user.passkeys.len() == expected_count => {
+                user.passkeys = passkeys.to_vec();
+                user.passkey_meta = meta.to_vec();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn save_passkey_meta(&self, id: Uuid, meta: &PasskeyMeta) -> StoreResult<()> {
+        if let Some(user) = self.tables().user_mut(id) {
+            match user.passkey_meta.iter_mut().find(|m| m.cred_id == meta.cred_id) {
+                Some(existing) => *existing = meta.clone(),
+                None => user.passkey_meta.push(meta.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    async fn touch_passkey(&self, id: Uuid, cred_id: &str) -> StoreResult<()> {
+        let now = DateTime::now();
+        if let Some(user) = self.tables().user_mut(id) {
+            match user.passkey_meta.iter_mut().find(|m| m.cred_id == cred_id) {
+                Some(meta) => meta.last_used_at = Some(now),
+                None => user.passkey_meta.push(PasskeyMeta {
+                    last_used_at: Some(now),
+                    ..PasskeyMeta::unknown(cred_id.to_string())
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_recovery_codes(&self, id: Uuid, code_hashes: &[String]) -> StoreResult<bool> {
+        match self.tables().user_mut(id) {
+            Some(user) => {
+                user.recovery_codes = code_hashes.to_vec();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, id: Uuid) -> StoreResult<()> {
+        self.tables().users.retain(|u| u.unique_id != id);
+        Ok(())
+    }
+}
+
+fn in_scope(poll: &Poll, scope: PollScope<'_>) -> bool {
+    match scope {
+        PollScope::Public => poll.visibility == Visibility::Public,
+        PollScope::Managed { user_id, org_ids } => {
+            poll.creator_id == user_id
+                || poll.is_collaborator(user_id)
+                || poll.org_id.is_some_and(|org_id| org_ids.contains(&org_id))
+        }
+    }
+}
+
+#[async_trait]
+impl PollStore for MemoryDb {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Poll>> {
+        Ok(self.tables().find_polls(|p| p.id == Some(id)).pop())
+    }
+
+    async fn get_by_share_token(&self, token: &str) -> StoreResult<Option<Poll>> {
+        Ok(self.tables().find_polls(|p| p.share_token.as_deref() == Some(token)).pop())
+    }
+
+    async fn get_many(&self, ids: &[ObjectId]) -> StoreResult<Vec<Poll>> {
+        Ok(self.tables().find_polls(|p| p.id.is_some_and(|id| ids.contains(&id))))
+    }
+
+    async fn insert(&self, poll: &Poll) -> StoreResult<ObjectId> {
+        let id = ObjectId::new();
+        let poll = Poll { id: Some(id), author: None, ..poll.clone() };
+        self.tables().polls.push(poll);
+        Ok(id)
+    }
+
+    async fn insert_many(&self, polls: &[Poll]) -> StoreResult<Vec<ObjectId>> {
+        let mut tables = self.tables();
+        let ids: Vec<ObjectId> = polls.iter().map(|_| ObjectId::new()).collect();
+        for (poll, id) in polls.iter().zip(&ids) {
+            tables.polls.push(Poll { id: Some(*id), author: None, ..poll.clone() });
+        }
+        Ok(ids)
+    }
+
+    async fn search(&self, scope: PollScope<'_>, query: &PollQuery) -> StoreResult<PollPage> {
+        let words: Vec<String> = query
+            .search
+            .as_deref()
+            .map(|text| text.split_whitespace().map(str::to_lowercase).collect())
+            .unwrap_or_default();
+        let mut polls = self.tables().find_polls(|p| {
+            // Any of the words, as with a MongoDB text search
+            let text_matches = words.is_empty()
+                || words.iter().any(|word| {
+                    p.title.to_lowercase().contains(word.as_str())
+                        || p.options.iter().any(|o| o.text.to_lowercase().contains(word.as_str()))
+                });
+            in_scope(p, scope)
+                && text_matches
+                && query.is_closed.is_none_or(|closed| p.is_closed == closed)
+                && query.creator_id.is_none_or(|id| p.creator_id == id)
+                && query.created_after.is_none_or(|at| p.created_at >= at)
+                && query.created_before.is_none_or(|at| p.created_at < at)
+                && query.tag.as_ref().is_none_or(|tag| p.tags.contains(tag))
+                && query.category.as_ref().is_none_or(|category| p.category.as_ref() == Some(category))
+        });
+        let total = polls.len() as u64;
+
+        let sort = query.sort;
+        polls.sort_by_key(|p| (sort.key(p), p.id.map(|id| id.bytes())));
+        if sort.descending() {
+            polls.reverse();
+        }
+        if let Some(cursor) = query.cursor {
+            polls.retain(|p| p.id.is_some_and(|id| cursor.admits(sort.key(p), id, sort)));
+        }
+        polls.truncate(query.limit + 1);
+        Ok(PollPage::new(polls, query, total))
+    }
+
+    async fn tag_counts(&self, scope: PollScope<'_>) -> StoreResult<Vec<(String, u64)>> {
+        let mut counts: Vec<(String, u64)> = Vec::new();
+        for poll in self.tables().polls.iter().filter(|p| in_scope(p, scope)) {
+            for tag in &poll.tags {
+                match counts.iter_mut().find(|(t, _)| t == tag) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((tag.clone(), 1)),
+                }
+            }
+        }
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+
+    async fn list_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>> {
+        Ok(self.tables().find_polls(|p| p.creator_id == user_id))
+    }
+
+    async fn list_by_org(&self, org_id: ObjectId) -> StoreResult<Vec<Poll>> {
+        Ok(self.tables().find_polls(|p| p.org_id == Some(org_id)))
+    }
+
+    async fn list_series(&self, series_id: ObjectId) -> StoreResult<Vec<Poll>> {
+        let mut polls = self.tables().find_polls(|p| p.id == Some(series_id) || p.series_id == Some(series_id));
+        polls.sort_by_key(|p| p.created_at);
+        Ok(polls)
+    }
+
+    async fn list_due(&self, now: DateTime) -> StoreResult<Vec<Poll>> {
+        Ok(self
+            .tables()
+            .find_polls(|p| p.recurrence.is_some() && p.next_occurrence_at.is_some_and(|at| at <= now)))
+    }
+
+    async fn close(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool> {
+        match self.tables().managed_mut(id, access) {
+            Some(poll) => {
+                poll.is_closed = true;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn reset_votes(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool> {
+        match self.tables().managed_mut(id, access) {
+            Some(poll) => {
+                poll.options.iter_mut().for_each(|opt| opt.votes = 0);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn update_content(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        title: &str,
+        options: &[PollOption],
+        tags: Option<&[String]>,
+        category: Option<Option<&str>>,
+    ) -> StoreResult<bool> {
+        match self.tables().managed_mut(id, access) {
+            Some(poll) => {
+                poll.title = title.to_string();
+                poll.options = options.to_vec();
+                if let Some(tags) = tags {
+                    poll.tags = tags.to_vec();
+                }
+                if let Some(category) = category {
+                    poll.category = category.map(str::to_string);
+                }
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_recurrence(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        schedule: Option<(&Recurrence, DateTime)>,
+    ) -> StoreResult<bool> {
+        match self.tables().managed_mut(id, access) {
+            Some(poll) if !poll.is_closed => {
+                poll.recurrence = schedule.map(|(recurrence, _)| recurrence.clone());
+                poll.next_occurrence_at = schedule.map(|(_, at)| at);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn set_visibility(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        visibility: Visibility,
+        invited_user_ids: &[String],
+        share_token: Option<&str>,
+    ) -> StoreResult<bool> {
+        match self.tables().managed_mut(id, access) {
+            Some(poll) => {
+                poll.visibility = visibility;
+                poll.invited_user_ids = invited_user_ids.to_vec();
+                poll.share_token = share_token.map(str::to_string);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_embedding(&self, id: ObjectId, access: PollAccess, embeddable: bool, origins: &[String]) -> StoreResult<bool> {
+        match self.tables().managed_mut(id, access) {
+            Some(poll) => {
+                poll.embeddable = embeddable;
+                poll.embed_origins = origins.to_vec();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn add_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()> {
+        if let Some(poll) = self.tables().managed_mut(id, access) {
+            if !poll.collaborator_ids.iter().any(|c| c == user_id) {
+                poll.collaborator_ids.push(user_id.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()> {
+        if let Some(poll) = self.tables().managed_mut(id, access) {
+            poll.collaborator_ids.retain(|c| c != user_id);
+        }
+        Ok(())
+    }
+
+    async fn increment_vote(&self, id: ObjectId, option_id: i32) -> StoreResult<bool> {
+        let mut tables = self.tables();
+        let option = tables
+            .poll_mut(id)
+            .filter(|poll| !poll.is_closed)
+            .and_then(|poll| poll.options.iter_mut().find(|opt| opt.id == option_id));
+        match option {
+            Some(option) => {
+                option.votes += 1;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn claim_rollover(&self, id: ObjectId, due_at: DateTime) -> StoreResult<bool> {
+        match self.tables().poll_mut(id) {
+            Some(poll) if poll.next_occurrence_at == Some(due_at) => {
+                poll.is_closed = true;
+                poll.recurrence = None;
+                poll.next_occurrence_at = None;
+                poll.share_token = None;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn release_rollover(&self, claimed: &Poll) -> StoreResult<()> {
+        let Some(id) = claimed.id else {
+            return Ok(());
+        };
+        if let Some(poll) = self.tables().poll_mut(id) {
+            poll.is_closed = claimed.is_closed;
+            poll.recurrence = claimed.recurrence.clone();
+            poll.next_occurrence_at = claimed.next_occurrence_at;
+            poll.share_token = claimed.share_token.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: ObjectId, access: PollAccess) -> StoreResult<Option<Poll>> {
+        let mut tables = self.tables();
+        let index = tables.polls.iter().position(|p| p.id == Some(id) && access.allows(p));
+        Ok(index.map(|index| tables.polls.remove(index)))
+    }
+
+    async fn delete_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>> {
+        let mut tables = self.tables();
+        let deleted = tables.find_polls(|p| p.creator_id == user_id);
+        tables.polls.retain(|p| p.creator_id != user_id);
+        Ok(deleted)
+    }
+
+    async fn reassign_creator(&self, from: Uuid, to: Uuid) -> StoreResult<u64> {
+        let mut count = 0;
+        for poll in self.tables().polls.iter_mut().filter(|p| p.creator_id == from) {
+            poll.creator_id = to;
+            poll.recurrence = None;
+            poll.next_occurrence_at = None;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn remove_user(&self, user_id: &str) -> StoreResult<()> {
+        for poll in self.tables().polls.iter_mut() {
+            poll.invited_user_ids.retain(|id| id != user_id);
+            poll.collaborator_ids.retain(|id| id != user_id);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl VoteStore for MemoryDb {
+    async fn insert(&self, vote: &Vote) -> StoreResult<bool> {
+        let mut tables = self.tables();
+        if tables.votes.iter().any(|v| v.poll_id == vote.poll_id && v.user_id == vote.user_id) {
+            return Ok(false);
+        }
+        let mut vote = vote.clone();
+        vote.id = Some(ObjectId::new());
+        tables.votes.push(vote);
+        Ok(true)
+    }
+
+    async fn delete(&self, poll_id: ObjectId, user_id: &str) -> StoreResult<()> {
+        self.tables().votes.retain(|v| !(v.poll_id == poll_id && v.user_id == user_id));
+        Ok(())
+    }
+
+    async fn delete_for_polls(&self, poll_ids: &[ObjectId]) -> StoreResult<()> {
+        self.tables().votes.retain(|v| !poll_ids.contains(&v.poll_id));
+        Ok(())
+    }
+
+    async fn delete_by_user(&self, user_id: &str) -> StoreResult<()> {
+        self.tables().votes.retain(|v| v.user_id != user_id);
+        Ok(())
+    }
+
+    async fn list_for_poll(&self, poll_id: ObjectId) -> StoreResult<Vec<Vote>> {
+        let mut votes: Vec<Vote> = self.tables().votes.iter().filter(|v| v.poll_id == poll_id).cloned().collect();
+        votes.sort_by_key(|v| v.created_at);
+        Ok(votes)
+    }
+
+    async fn list_by_user(&self, user_id: &str) -> StoreResult<Vec<Vote>> {
+        Ok(self.tables().votes.iter().filter(|v| v.user_id == user_id).cloned().collect())
+    }
+}
+
+#[async_trait]
+impl OrgStore for MemoryDb {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Organization>> {
+        Ok(self.tables().orgs.iter().find(|o| o.id == Some(id)).cloned())
+    }
+
+    async fn insert(&self, org: &Organization) -> StoreResult<ObjectId> {
+        let id = ObjectId::new();
+        let mut org = org.clone();
+        org.id = Some(id);
+        self.tables().orgs.push(org);
+        Ok(id)
+    }
+
+    async fn list_for_member(&self, user_id: &str) -> StoreResult<Vec<Organization>> {
+        Ok(self
+            .tables()
+            .orgs
+            .iter()
+            .filter(|o| o.members.iter().any(|m| m.user_id == user_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn set_members(&self, id: ObjectId, members: &[OrgMember]) -> StoreResult<()> {
+        if let Some(org) = self.tables().orgs.iter_mut().find(|o| o.id == Some(id)) {
+            org.members = members.to_vec();
+        }
+        Ok(())
+    }
+
+    async fn remove_member_everywhere(&self, user_id: &str) -> StoreResult<()> {
+        let mut tables = self.tables();
+        for org in tables.orgs.iter_mut() {
+            org.members.retain(|m| m.user_id != user_id);
+        }
+        tables.orgs.retain(|o| !o.members.is_empty());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserSessionStore for MemoryDb {
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>> {
+        let mut records: Vec<UserSession> = self
+            .tables()
+            .user_sessions
+            .iter()
+            .filter(|s| s.user_id == user_id)
+            .cloned()
+            .collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.last_seen_at));
+        Ok(records)
+    }
+
+    async fn touch(&self, session_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<bool> {
+        match self.tables().user_sessions.iter_mut().find(|s| s.session_id == session_id) {
+            Some(record) => {
+                record.last_seen_at = DateTime::now();
+                record.user_agent = user_agent;
+                record.ip = ip;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn record(&self, session_id: &str, user_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<()> {
+        let mut tables = self.tables();
+        let now = DateTime::now();
+        match tables.user_sessions.iter_mut().find(|s| s.session_id == session_id) {
+            Some(record) => {
+                record.last_seen_at = now;
+                record.user_agent = user_agent;
+                record.ip = ip;
+            }
+            None => tables.user_sessions.push(UserSession {
+                id: Some(ObjectId::new()),
+                session_id: session_id.to_string(),
+                user_id: user_id.to_string(),
+                created_at: now,
+                last_seen_at: now,
+                user_agent,
+                ip,
+            }),
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, session_id: &str) -> StoreResult<()> {
+        self.tables().user_sessions.retain(|s| s.session_id != session_id);
+        Ok(())
+    }
+
+    async fn remove_by_id(&self, id: ObjectId, user_id: &str) -> StoreResult<Option<UserSession>> {
+        let mut tables = self.tables();
+        let index = tables
+            .user_sessions
+            .iter()
+            .position(|s| s.id == Some(id) && s.user_id == user_id);
+        Ok(index.map(|index| tables.user_sessions.remove(index)))
+    }
+
+    async fn remove_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>> {
+        let mut tables = self.tables();
+        let (removed, kept) = std::mem::take(&mut tables.user_sessions)
+            .into_iter()
+            .partition(|s| s.user_id == user_id);
+        tables.user_sessions = kept;
+        Ok(removed)
+    }
+}
+
+#[async_trait]
+impl TokenStore for MemoryDb {
+    async fn insert(&self, token: &ApiToken) -> StoreResult<ObjectId> {
+        let mut tables = self.tables();
+        if tables.api_tokens.iter().any(|t| t.token_hash == token.token_hash) {
+            return Err(ApiError::Internal("Duplicate API token hash".to_string()));
+        }
+        let id = ObjectId::new();
+        let mut token = token.clone();
+        token.id = Some(id);
+        tables.api_tokens.push(token);
+        Ok(id)
+    }
+
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<ApiToken>> {
+        let mut tokens: Vec<ApiToken> = self
+            .tables()
+            .api_tokens
+            .iter()
+            .filter(|t| t.user_id == user_id)
+            .cloned()
+            .collect();
+        tokens.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(tokens)
+    }
+
+    async fn use_token(&self, token_hash: &str) -> StoreResult<Option<ApiToken>> {
+        let now = DateTime::now();
+        let mut tables = self.tables();
+        let token = tables
+            .api_tokens
+            .iter_mut()
+            .find(|t| t.token_hash == token_hash && t.expires_at > now);
+        // Like `find_one_and_update`, returns the token as it was before the use
+        Ok(token.map(|token| {
+            let before = token.clone();
+            token.last_used_at = Some(now);
+            before
+        }))
+    }
+
+    async fn delete(&self, id: ObjectId, user_id: &str) -> StoreResult<bool> {
+        let mut tables = self.tables();
+        let before = tables.api_tokens.len();
+        tables.api_tokens.retain(|t| !(t.id == Some(id) && t.user_id == user_id));
+        Ok(tables.api_tokens.len() < before)
+    }
+
+    async fn delete_for_user(&self, user_id: &str) -> StoreResult<()> {
+        self.tables().api_tokens.retain(|t| t.user_id != user_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AdminLogStore for MemoryDb {
+    async fn record(&self, action: &AdminAction) -> StoreResult<()> {
+        let mut action = action.clone();
+        action.id = Some(ObjectId::new());
+        self.tables().admin_log.push(action);
+        Ok(())
+    }
+
+    async fn recent(&self, limit: usize) -> StoreResult<Vec<AdminAction>> {
+        Ok(self.tables().admin_log.iter().rev().take(limit).cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::poll;
+    use crate::models::Frequency;
+
+    #[tokio::test]
+    async fn due_recurring_poll_is_claimed_once() {
+        let stores = open();
+        let due_at = DateTime::from_millis(1_000);
+        let schedule = Recurrence { frequency: Frequency::Daily, weekdays: Vec::new(), day_of_month: None, hour: 9, minute: 0 };
+        let recurring = Poll {
+            recurrence: Some(schedule),
+            next_occurrence_at: Some(due_at),
+            share_token: Some("token".to_string()),
+            ..poll(Uuid::new_v4())
+        };
+        let poll_id = stores.polls.insert(&recurring).await.unwrap();
+        stores.polls.insert(&poll(Uuid::new_v4())).await.unwrap();
+
+        let due = stores.polls.list_due(DateTime::now()).await.unwrap();
+        assert_eq!(due.iter().map(|p| p.id.unwrap()).collect::<Vec<_>>(), vec![poll_id]);
+        assert!(stores.polls.claim_rollover(poll_id, due_at).await.unwrap());
+        // A second scheduler finds nothing left to claim
+        assert!(!stores.polls.claim_rollover(poll_id, due_at).await.unwrap());
+        let claimed = stores.polls.get(poll_id).await.unwrap().unwrap();
+        assert!(claimed.is_closed && claimed.recurrence.is_none() && claimed.share_token.is_none());
+        assert!(stores.polls.list_due(DateTime::now()).await.unwrap().is_empty());
+
+        // A failed rollover hands the schedule back
+        stores.polls.release_rollover(&due[0]).await.unwrap();
+        assert_eq!(stores.polls.list_due(DateTime::now()).await.unwrap().len(), 1);
+    }
+
+    fn creator(user_id: Uuid) -> PollAccess {
+        PollAccess::User { user_id, collaborators: false, org_id: None }
+    }
+
+    #[tokio::test]
+    async fn public_listing_leaves_out_unlisted_and_private_polls() {
+        let stores = open();
+        let creator_id = Uuid::new_v4();
+        let public_id = stores.polls.insert(&poll(creator_id)).await.unwrap();
+        let unlisted_id = stores.polls.insert(&poll(creator_id)).await.unwrap();
+        let private_id = stores.polls.insert(&poll(creator_id)).await.unwrap();
+        let access = creator(creator_id);
+        assert!(stores.polls.set_visibility(unlisted_id, access, Visibility::Unlisted, &[], Some("token")).await.unwrap());
+        assert!(stores.polls.set_visibility(private_id, access, Visibility::Private, &[], None).await.unwrap());
+        // Only the creator changes visibility
+        assert!(!stores.polls.set_visibility(public_id, creator(Uuid::new_v4()), Visibility::Private, &[], None).await.unwrap());
+
+        let query = PollQuery { limit: 10, ..PollQuery::default() };
+        let public = stores.polls.search(PollScope::Public, &query).await.unwrap();
+        assert_eq!(public.polls.iter().map(|p| p.id.unwrap()).collect::<Vec<_>>(), vec![public_id]);
+        assert_eq!(public.total, 1);
+
+        let managed = PollScope::Managed { user_id: creator_id, org_ids: &[] };
+        assert_eq!(stores.polls.search(managed, &query).await.unwrap().total, 3);
+        let shared = stores.polls.get_by_share_token("token").await.unwrap().unwrap();
+        assert_eq!(shared.id, Some(unlisted_id));
+    }
+
+    #[tokio::test]
+    async fn updates_reach_only_the_creator_and_collaborators() {
+        let stores = open();
+        let creator_id = Uuid::new_v4();
+        let collaborator = Uuid::new_v4();
+        let poll_id = stores.polls.insert(&poll(creator_id)).await.unwrap();
+        stores.polls.add_collaborator(poll_id, creator(creator_id), &collaborator.to_string()).await.unwrap();
+
+        let options = [PollOption { id: 1, text: "Tacos".to_string(), votes: 0 }];
+        let as_collaborator = PollAccess::User { user_id: collaborator, collaborators: true, org_id: None };
+        assert!(stores.polls.update_content(poll_id, as_collaborator, "Dinner", &options, None, None).await.unwrap());
+        assert_eq!(stores.polls.get(poll_id).await.unwrap().unwrap().title, "Dinner");
+
+        // Owner-only actions do not extend to collaborators
+        assert!(stores.polls.delete(poll_id, creator(collaborator)).await.unwrap().is_none());
+        let stranger = PollAccess::User { user_id: Uuid::new_v4(), collaborators: true, org_id: None };
+        assert!(!stores.polls.reset_votes(poll_id, stranger).await.unwrap());
+
+        let org_id = ObjectId::new();
+        let as_org = PollAccess::User { user_id: Uuid::new_v4(), collaborators: false, org_id: Some(org_id) };
+        assert!(!stores.polls.close(poll_id, as_org).await.unwrap());
+
+        let deleted = stores.polls.delete(poll_id, creator(creator_id)).await.unwrap();
+        assert_eq!(deleted.and_then(|p| p.id), Some(poll_id));
+        assert!(stores.polls.get(poll_id).await.unwrap().is_none());
+    }
+
+    fn user(username: &str) -> UserData {
+        UserData {
+            username: username.to_string(),
+            username_key: username_key(username),
+            unique_id: Uuid::new_v4(),
+            passkeys: Vec::new(),
+            is_admin: false,
+            disabled: false,
+            passkey_meta: Vec::new(),
+            recovery_codes: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn usernames_are_unique_regardless_of_case() {
+        let stores = open();
+        let alice = user("alice");
+        let bob = user("bob");
+        stores.users.insert(&alice).await.unwrap();
+        stores.users.insert(&bob).await.unwrap();
+
+        assert!(matches!(stores.users.insert(&user("ALICE")).await, Err(ApiError::UsernameTaken)));
+        assert!(matches!(stores.users.rename(bob.unique_id, "Alice").await, Err(ApiError::UsernameTaken)));
+        // Changing only the case of one's own name is allowed
+        stores.users.rename(alice.unique_id, "Alice").await.unwrap();
+        let found = stores.users.find_by_username("aLiCe").await.unwrap().unwrap();
+        assert_eq!((found.unique_id, found.username.as_str()), (alice.unique_id, "Alice"));
+    }
+
+    fn vote(poll_id: ObjectId, user_id: Uuid, option_id: i32) -> Vote {
+        Vote { id: None, poll_id, user_id: user_id.to_string(), option_id, created_at: DateTime::now() }
+    }
+
+    async fn option_votes(stores: &Stores, poll_id: ObjectId) -> Vec<i32> {
+        let poll = stores.polls.get(poll_id).await.unwrap().unwrap();
+        poll.options.iter().map(|o| o.votes).collect()
+    }
+
+    #[tokio::test]
+    async fn vote_is_counted() {
+        let stores = open();
+        let poll_id = stores.polls.insert(&poll(Uuid::new_v4())).await.unwrap();
+        let voter = Uuid::new_v4();
+
+        assert!(stores.votes.insert(&vote(poll_id, voter, 2)).await.unwrap());
+        assert!(stores.polls.increment_vote(poll_id, 2).await.unwrap());
+        assert_eq!(option_votes(&stores, poll_id).await, vec![0, 1]);
+        assert_eq!(stores.votes.list_for_poll(poll_id).await.unwrap().len(), 1);
+        // No such option
+        assert!(!stores.polls.increment_vote(poll_id, 3).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn second_vote_by_the_same_user_is_refused() {
+        let stores = open();
+        let poll_id = stores.polls.insert(&poll(Uuid::new_v4())).await.unwrap();
+        let voter = Uuid::new_v4();
+
+        assert!(stores.votes.insert(&vote(poll_id, voter, 1)).await.unwrap());
+        assert!(!stores.votes.insert(&vote(poll_id, voter, 2)).await.unwrap());
+        assert!(stores.votes.insert(&vote(poll_id, Uuid::new_v4(), 2)).await.unwrap());
+        assert_eq!(stores.votes.list_for_poll(poll_id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn closed_poll_takes_no_votes() {
+        let stores = open();
+        let creator_id = Uuid::new_v4();
+        let poll_id = stores.polls.insert(&poll(creator_id)).await.unwrap();
+
+        assert!(!stores.polls.close(poll_id, creator(Uuid::new_v4())).await.unwrap());
+        assert!(stores.polls.close(poll_id, creator(creator_id)).await.unwrap());
+        assert!(stores.polls.get(poll_id).await.unwrap().unwrap().is_closed);
+        assert!(!stores.polls.increment_vote(poll_id, 1).await.unwrap());
+        assert_eq!(option_votes(&stores, poll_id).await, vec![0, 0]);
+    }
+
+    #[tokio::test]
+    async fn batch_insert_stores_every_poll() {
+        let stores = open();
+        let creator_id = Uuid::new_v4();
+        let ids = stores.polls.insert_many(&[poll(creator_id), poll(creator_id)]).await.unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(stores.polls.list_by_creator(creator_id).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn search_filters_and_pages() {
+        crate::store::tests::check_search(&open()).await;
+    }
+
+    #[tokio::test]
+    async fn edits_keep_tags_unless_given() {
+        crate::store::tests::check_content_update(&open()).await;
+    }
+}
@@ -0,0 +1,545 @@
+// src/store/mod.rs
+//
+// Storage behind the handlers. Each collection is reached through a trait so
+// the API can run on MongoDB, SQLite or PostgreSQL, or for tests and demos
+// entirely in memory.
+pub mod memory;
+pub mod mongo;
+pub mod sql;
+
+use std::cmp::Ordering;
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use mongodb::bson::{oid::ObjectId, DateTime};
+use tower_sessions::{
+    session::{Id, Record},
+    session_store, MemoryStore, SessionStore,
+};
+use tower_sessions_mongodb_store::MongoDBStore;
+use uuid::Uuid;
+use webauthn_rs::prelude::{AuthenticationResult, Passkey};
+use crate::error::ApiError;
+use crate::models::{AdminAction, ApiToken, OrgMember, Organization, Poll, PollOption, Recurrence, UserSession, Visibility, Vote};
+use crate::startup::{PasskeyMeta, UserData};
+
+pub type StoreResult<T> = Result<T, ApiError>;
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn get(&self, id: Uuid) -> StoreResult<Option<UserData>>;
+    // Case-insensitive, see `auth::username`
+    async fn find_by_username(&self, username: &str) -> StoreResult<Option<UserData>>;
+    async fn find_by_recovery_code(&self, username: &str, code_hash: &str) -> StoreResult<Option<UserData>>;
+    // Users whose `unique_id` is in `ids`
+    async fn get_many(&self, ids: &[String]) -> StoreResult<Vec<UserData>>;
+    // Every user, by username
+    async fn list(&self) -> StoreResult<Vec<UserData>>;
+    // Fails with `UsernameTaken` if the username is in use
+    async fn insert(&self, user: &UserData) -> StoreResult<()>;
+    // Fails with `UsernameTaken` if the username is in use
+    async fn rename(&self, id: Uuid, username: &str) -> StoreResult<()>;
+    async fn set_admin(&self, id: Uuid, is_admin: bool) -> StoreResult<()>;
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> StoreResult<()>;
+    // Applies an assertion's counter and backup state to the credential it
+    // used, leaving the others alone. False if the user no longer has that
+    // credential, e.g. because it was revoked during the ceremony.
+    async fn update_credential(&self, id: Uuid, result: &AuthenticationResult) -> StoreResult<bool>;
+    async fn add_passkey(&self, id: Uuid, passkey: &Passkey, meta: &PasskeyMeta) -> StoreResult<()>;
+    // Adds the passkey and uses up the recovery code in one step. False if
+    // the code was used up in the meantime.
+    async fn add_passkey_with_recovery_code(
+        &self,
+        id: Uuid,
+        code_hash: &str,
+        passkey: &Passkey,
+        meta: &PasskeyMeta,
+    ) -> StoreResult<bool>;
+    // Replaces the passkeys, unless their number is no longer `expected_count`
+    async fn replace_passkeys(
+        &self,
+        id: Uuid,
+        expected_count: usize,
+        passkeys: &[Passkey],
+        meta: &[PasskeyMeta],
+    ) -> StoreResult<bool>;
+    // Inserts or replaces the metadata entry with the same credential ID
+    async fn save_passkey_meta(&self, id: Uuid, meta: &PasskeyMeta) -> StoreResult<()>;
+    // Sets `last_used_at`, adding a placeholder entry if the passkey has none
+    async fn touch_passkey(&self, id: Uuid, cred_id: &str) -> StoreResult<()>;
+    // False if there is no such user
+    async fn set_recovery_codes(&self, id: Uuid, code_hashes: &[String]) -> StoreResult<bool>;
+    async fn delete(&self, id: Uuid) -> StoreResult<()>;
+}
+
+#[async_trait]
+pub trait PollStore: Send + Sync {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Poll>>;
+    async fn get_by_share_token(&self, token: &str) -> StoreResult<Option<Poll>>;
+    async fn get_many(&self, ids: &[ObjectId]) -> StoreResult<Vec<Poll>>;
+    async fn insert(&self, poll: &Poll) -> StoreResult<ObjectId>;
+    // Inserts all the polls or, on failure, none of them
+    async fn insert_many(&self, polls: &[Poll]) -> StoreResult<Vec<ObjectId>>;
+    // One page of the polls in `scope` matching `query`
+    async fn search(&self, scope: PollScope<'_>, query: &PollQuery) -> StoreResult<PollPage>;
+    // How many polls in `scope` carry each tag, most used first
+    async fn tag_counts(&self, scope: PollScope<'_>) -> StoreResult<Vec<(String, u64)>>;
+    async fn list_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>>;
+    async fn list_by_org(&self, org_id: ObjectId) -> StoreResult<Vec<Poll>>;
+    // Every instance of a recurring series, oldest first
+    async fn list_series(&self, series_id: ObjectId) -> StoreResult<Vec<Poll>>;
+    // Recurring polls whose next occurrence is at or before `now`
+    async fn list_due(&self, now: DateTime) -> StoreResult<Vec<Poll>>;
+    // The update methods return false if the poll does not exist or `access`
+    // no longer reaches it
+    async fn close(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool>;
+    async fn reset_votes(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool>;
+    async fn update_content(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        title: &str,
+        options: &[PollOption],
+        // Left as stored when None; `Some(None)` clears the category
+        tags: Option<&[String]>,
+        category: Option<Option<&str>>,
+    ) -> StoreResult<bool>;
+    // Sets or clears the schedule of an open poll; false if it is closed
+    async fn set_recurrence(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        schedule: Option<(&Recurrence, DateTime)>,
+    ) -> StoreResult<bool>;
+    async fn set_visibility(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        visibility: Visibility,
+        invited_user_ids: &[String],
+        share_token: Option<&str>,
+    ) -> StoreResult<bool>;
+    async fn set_embedding(&self, id: ObjectId, access: PollAccess, embeddable: bool, origins: &[String]) -> StoreResult<bool>;
+    // Both do nothing when `access` no longer reaches the poll
+    async fn add_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()>;
+    async fn remove_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()>;
+    // Counts one vote, if the poll is open and has the option
+    async fn increment_vote(&self, id: ObjectId, option_id: i32) -> StoreResult<bool>;
+    // Closes a due instance and moves its schedule and share link off it.
+    // False if another scheduler got there first.
+    async fn claim_rollover(&self, id: ObjectId, due_at: DateTime) -> StoreResult<bool>;
+    // Puts back what `claim_rollover` took from `poll`, when its next instance
+    // could not be created
+    async fn release_rollover(&self, poll: &Poll) -> StoreResult<()>;
+    // Returns the deleted poll
+    async fn delete(&self, id: ObjectId, access: PollAccess) -> StoreResult<Option<Poll>>;
+    async fn delete_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>>;
+    // Hands a user's polls to another user and stops their schedules
+    async fn reassign_creator(&self, from: Uuid, to: Uuid) -> StoreResult<u64>;
+    // Drops the user from every invitation and collaborator list
+    async fn remove_user(&self, user_id: &str) -> StoreResult<()>;
+}
+
+// Which polls a listing draws from
+#[derive(Clone, Copy, Debug)]
+pub enum PollScope<'a> {
+    // Polls that are neither unlisted nor private
+    Public,
+    // Polls the user created, collaborates on, or that belong to `org_ids`
+    Managed { user_id: Uuid, org_ids: &'a [ObjectId] },
+}
+
+// Whom a management update is made for. The route checks the permission
+// first and the store matches this in the same write, so a creator or
+// collaborator change in between cannot be slipped past. Organization roles
+// are only checked by the route.
+#[derive(Clone, Copy, Debug)]
+pub enum PollAccess {
+    // Admin actions
+    Any,
+    // The creator, a collaborator when `collaborators` is set, or anyone
+    // while the poll belongs to `org_id`, an organization they manage
+    User { user_id: Uuid, collaborators: bool, org_id: Option<ObjectId> },
+}
+
+impl PollAccess {
+    pub fn allows(self, poll: &Poll) -> bool {
+        match self {
+            PollAccess::Any => true,
+            PollAccess::User { user_id, collaborators, org_id } => {
+                poll.creator_id == user_id
+                    || (collaborators && poll.is_collaborator(user_id))
+                    || org_id.is_some_and(|org_id| poll.org_id == Some(org_id))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PollSort {
+    #[default]
+    Newest,
+    MostVotes,
+    // Open recurring polls by their next rollover; everything else last
+    ClosingSoon,
+}
+
+impl PollSort {
+    // The value a listing is ordered by, with poll IDs breaking ties
+    pub fn key(self, poll: &Poll) -> i64 {
+        match self {
+            PollSort::Newest => poll.created_at.timestamp_millis(),
+            PollSort::MostVotes => poll.options.iter().map(|o| o.votes as i64).sum(),
+            PollSort::ClosingSoon => poll.next_occurrence_at.map_or(i64::MAX, |at| at.timestamp_millis()),
+        }
+    }
+
+    pub fn descending(self) -> bool {
+        self != PollSort::ClosingSoon
+    }
+}
+
+// Position after the last poll of a page, handed to clients as an opaque string
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PollCursor {
+    pub key: i64,
+    pub id: ObjectId,
+}
+
+impl PollCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}.{}", self.key, self.id.to_hex()))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let (key, id) = raw.split_once('.')?;
+        Some(PollCursor { key: key.parse().ok()?, id: ObjectId::parse_str(id).ok()? })
+    }
+
+    // Whether a poll with this sort key and ID belongs after the cursor
+    pub fn admits(&self, key: i64, id: ObjectId, sort: PollSort) -> bool {
+        let order = (key, id.bytes()).cmp(&(self.key, self.id.bytes()));
+        order == if sort.descending() { Ordering::Less } else { Ordering::Greater }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PollQuery {
+    pub is_closed: Option<bool>,
+    pub creator_id: Option<Uuid>,
+    pub created_after: Option<DateTime>,
+    pub created_before: Option<DateTime>,
+    // Normalized, see `models::tags`
+    pub tag: Option<String>,
+    pub category: Option<String>,
+    // Words to look for in titles and option texts
+    pub search: Option<String>,
+    pub sort: PollSort,
+    pub cursor: Option<PollCursor>,
+    pub limit: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct PollPage {
+    pub polls: Vec<Poll>,
+    // Matches across all pages
+    pub total: u64,
+    pub next_cursor: Option<PollCursor>,
+}
+
+impl PollPage {
+    // Cuts `polls`, fetched with one extra to tell whether more follow, to `limit`
+    pub fn new(mut polls: Vec<Poll>, query: &PollQuery, total: u64) -> Self {
+        let next_cursor = if polls.len() > query.limit {
+            polls.truncate(query.limit);
+            polls.last().and_then(|p| Some(PollCursor { key: query.sort.key(p), id: p.id? }))
+        } else {
+            None
+        };
+        PollPage { polls, total, next_cursor }
+    }
+}
+
+#[async_trait]
+pub trait VoteStore: Send + Sync {
+    // False if the user already voted on the poll
+    async fn insert(&self, vote: &Vote) -> StoreResult<bool>;
+    async fn delete(&self, poll_id: ObjectId, user_id: &str) -> StoreResult<()>;
+    async fn delete_for_polls(&self, poll_ids: &[ObjectId]) -> StoreResult<()>;
+    async fn delete_by_user(&self, user_id: &str) -> StoreResult<()>;
+    // Oldest first
+    async fn list_for_poll(&self, poll_id: ObjectId) -> StoreResult<Vec<Vote>>;
+    async fn list_by_user(&self, user_id: &str) -> StoreResult<Vec<Vote>>;
+}
+
+#[async_trait]
+pub trait OrgStore: Send + Sync {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Organization>>;
+    async fn insert(&self, org: &Organization) -> StoreResult<ObjectId>;
+    async fn list_for_member(&self, user_id: &str) -> StoreResult<Vec<Organization>>;
+    async fn set_members(&self, id: ObjectId, members: &[OrgMember]) -> StoreResult<()>;
+    // Removes the user from every organization and deletes the ones left empty
+    async fn remove_member_everywhere(&self, user_id: &str) -> StoreResult<()>;
+}
+
+// The index of signed-in sessions, see `auth::sessions`
+#[async_trait]
+pub trait UserSessionStore: Send + Sync {
+    // Most recently active first
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>>;
+    // Refreshes an indexed session; false if it is not indexed (any more)
+    async fn touch(&self, session_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<bool>;
+    // Refreshes the session, indexing it first if needed
+    async fn record(&self, session_id: &str, user_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<()>;
+    async fn remove(&self, session_id: &str) -> StoreResult<()>;
+    // Removes the entry if it belongs to the user, and returns it
+    async fn remove_by_id(&self, id: ObjectId, user_id: &str) -> StoreResult<Option<UserSession>>;
+    async fn remove_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>>;
+}
+
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    async fn insert(&self, token: &ApiToken) -> StoreResult<ObjectId>;
+    // Newest first
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<ApiToken>>;
+    // Looks up an unexpired token and records the use
+    async fn use_token(&self, token_hash: &str) -> StoreResult<Option<ApiToken>>;
+    // False if the user has no such token
+    async fn delete(&self, id: ObjectId, user_id: &str) -> StoreResult<bool>;
+    async fn delete_for_user(&self, user_id: &str) -> StoreResult<()>;
+}
+
+#[async_trait]
+pub trait AdminLogStore: Send + Sync {
+    async fn record(&self, action: &AdminAction) -> StoreResult<()>;
+    // Newest first
+    async fn recent(&self, limit: usize) -> StoreResult<Vec<AdminAction>>;
+}
+
+// Session data for tower-sessions, kept next to the other collections
+#[derive(Clone, Debug)]
+pub enum SessionBackend {
+    MongoDB(MongoDBStore),
+    Memory(MemoryStore),
+    Sql(sql::SqlSessionStore),
+}
+
+#[async_trait]
+impl SessionStore for SessionBackend {
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        match self {
+            SessionBackend::MongoDB(store) => store.save(record).await,
+            SessionBackend::Memory(store) => store.save(record).await,
+            SessionBackend::Sql(store) => store.save(record).await,
+        }
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        match self {
+            SessionBackend::MongoDB(store) => store.load(session_id).await,
+            SessionBackend::Memory(store) => store.load(session_id).await,
+            SessionBackend::Sql(store) => store.load(session_id).await,
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        match self {
+            SessionBackend::MongoDB(store) => store.delete(session_id).await,
+            SessionBackend::Memory(store) => store.delete(session_id).await,
+            SessionBackend::Sql(store) => store.delete(session_id).await,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Stores {
+    pub users: Arc<dyn UserStore>,
+    pub polls: Arc<dyn PollStore>,
+    pub votes: Arc<dyn VoteStore>,
+    pub orgs: Arc<dyn OrgStore>,
+    pub user_sessions: Arc<dyn UserSessionStore>,
+    pub api_tokens: Arc<dyn TokenStore>,
+    pub admin_log: Arc<dyn AdminLogStore>,
+    pub session_store: SessionBackend,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageKind {
+    MongoDB,
+    Sqlite,
+    Postgres,
+    // Everything is lost on restart
+    Memory,
+}
+
+impl fmt::Display for StorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageKind::MongoDB => write!(f, "mongodb"),
+            StorageKind::Sqlite => write!(f, "sqlite"),
+            StorageKind::Postgres => write!(f, "postgres"),
+            StorageKind::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+impl StorageKind {
+    // STORAGE=mongodb (default), sqlite, postgres or memory
+    pub fn from_env() -> Self {
+        match env::var("STORAGE").as_deref() {
+            Err(_) | Ok("mongodb") => StorageKind::MongoDB,
+            Ok("sqlite") => StorageKind::Sqlite,
+            Ok("postgres") => StorageKind::Postgres,
+            Ok("memory") => StorageKind::Memory,
+            Ok(other) => panic!("Unknown STORAGE {:?}, expected mongodb, sqlite, postgres or memory", other),
+        }
+    }
+}
+
+pub async fn open(kind: StorageKind) -> Stores {
+    info!("Using {} storage", kind);
+    match kind {
+        StorageKind::MongoDB => {
+            let uri = env::var("MONGODB_URI").expect("MONGODB_URI must be set in .env");
+            mongo::open(&uri).await
+        }
+        StorageKind::Sqlite | StorageKind::Postgres => {
+            let url = env::var("DATABASE_URL").expect("DATABASE_URL must be set for SQL storage");
+            sql::open(kind, &url).await
+        }
+        StorageKind::Memory => memory::open(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::poll;
+
+    #[test]
+    fn organization_access_reaches_only_its_polls() {
+        let org_id = ObjectId::new();
+        let org_poll = Poll { org_id: Some(org_id), ..poll(Uuid::new_v4()) };
+        let member = PollAccess::User { user_id: Uuid::new_v4(), collaborators: false, org_id: Some(org_id) };
+        assert!(member.allows(&org_poll));
+        assert!(!member.allows(&poll(Uuid::new_v4())));
+        let outsider = PollAccess::User { user_id: Uuid::new_v4(), collaborators: false, org_id: Some(ObjectId::new()) };
+        assert!(!outsider.allows(&org_poll));
+    }
+
+    fn cursor(key: i64) -> PollCursor {
+        PollCursor { key, id: ObjectId::new() }
+    }
+
+    #[test]
+    fn cursor_survives_encoding() {
+        let cursor = cursor(-42);
+        assert_eq!(PollCursor::decode(&cursor.encode()), Some(cursor));
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        assert_eq!(PollCursor::decode("not base64!"), None);
+        assert_eq!(PollCursor::decode(&URL_SAFE_NO_PAD.encode("12")), None);
+        assert_eq!(PollCursor::decode(&URL_SAFE_NO_PAD.encode("x.650000000000000000000001")), None);
+        assert_eq!(PollCursor::decode(&URL_SAFE_NO_PAD.encode("12.nope")), None);
+    }
+
+    #[test]
+    fn cursor_admits_what_follows_in_sort_order() {
+        let at = cursor(100);
+        let later_id = ObjectId::new();
+        // Newest first: smaller keys follow, and equal keys by smaller ID
+        assert!(at.admits(99, ObjectId::new(), PollSort::Newest));
+        assert!(!at.admits(101, ObjectId::new(), PollSort::Newest));
+        assert!(!at.admits(100, later_id, PollSort::Newest));
+        assert!(!at.admits(100, at.id, PollSort::Newest));
+        // Closing soon is ascending
+        assert!(at.admits(101, ObjectId::new(), PollSort::ClosingSoon));
+        assert!(at.admits(100, later_id, PollSort::ClosingSoon));
+        assert!(!at.admits(99, ObjectId::new(), PollSort::ClosingSoon));
+    }
+
+    #[test]
+    fn page_with_an_extra_poll_has_a_cursor_at_its_last_poll() {
+        let polls: Vec<Poll> = (0..3).map(|_| poll(Uuid::new_v4())).collect();
+        let query = PollQuery { limit: 2, ..PollQuery::default() };
+        let page = PollPage::new(polls.clone(), &query, 7);
+
+        assert_eq!(page.polls.len(), 2);
+        assert_eq!(page.total, 7);
+        let last = &polls[1];
+        assert_eq!(page.next_cursor, Some(PollCursor { key: PollSort::Newest.key(last), id: last.id.unwrap() }));
+    }
+
+    #[test]
+    fn last_page_has_no_cursor() {
+        let polls: Vec<Poll> = (0..2).map(|_| poll(Uuid::new_v4())).collect();
+        let query = PollQuery { limit: 2, ..PollQuery::default() };
+        let page = PollPage::new(polls, &query, 2);
+
+        assert_eq!(page.polls.len(), 2);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    // Listing behaviour every backend must share
+    pub(super) async fn check_search(stores: &Stores) {
+        let creator_id = Uuid::new_v4();
+        let titles = ["Lunch", "Dinner", "Breakfast", "Dinner party", "Brunch"];
+        let mut ids = Vec::new();
+        for (i, title) in titles.iter().enumerate() {
+            let tags = if i % 2 == 0 { vec!["food".to_string()] } else { Vec::new() };
+            let created_at = DateTime::from_millis(1_000 * (i as i64 + 1));
+            let poll = Poll { title: title.to_string(), tags, created_at, ..poll(creator_id) };
+            ids.push(stores.polls.insert(&poll).await.unwrap());
+        }
+
+        // Newest first, two at a time, each poll once
+        let mut query = PollQuery { limit: 2, ..PollQuery::default() };
+        let mut seen = Vec::new();
+        loop {
+            let page = stores.polls.search(PollScope::Public, &query).await.unwrap();
+            assert_eq!(page.total, 5);
+            seen.extend(page.polls.iter().map(|p| p.id.unwrap()));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, ids.iter().rev().copied().collect::<Vec<_>>());
+
+        let titles_of = |page: PollPage| page.polls.into_iter().map(|p| p.title).collect::<Vec<_>>();
+        let query = PollQuery { limit: 10, search: Some("DINNER".to_string()), ..PollQuery::default() };
+        assert_eq!(titles_of(stores.polls.search(PollScope::Public, &query).await.unwrap()), ["Dinner party", "Dinner"]);
+        let query = PollQuery { limit: 10, tag: Some("food".to_string()), ..PollQuery::default() };
+        assert_eq!(titles_of(stores.polls.search(PollScope::Public, &query).await.unwrap()), ["Brunch", "Breakfast", "Lunch"]);
+        let query = PollQuery { limit: 10, created_after: Some(DateTime::from_millis(4_000)), ..PollQuery::default() };
+        assert_eq!(stores.polls.search(PollScope::Public, &query).await.unwrap().total, 2);
+    }
+
+    // Editing leaves tags and category alone unless they are given
+    pub(super) async fn check_content_update(stores: &Stores) {
+        let creator_id = Uuid::new_v4();
+        let tagged = Poll { tags: vec!["food".to_string()], category: Some("team".to_string()), ..poll(creator_id) };
+        let poll_id = stores.polls.insert(&tagged).await.unwrap();
+        let access = PollAccess::User { user_id: creator_id, collaborators: false, org_id: None };
+        let options = [PollOption { id: 1, text: "Tacos".to_string(), votes: 0 }];
+
+        assert!(stores.polls.update_content(poll_id, access, "Dinner", &options, None, None).await.unwrap());
+        let stored = stores.polls.get(poll_id).await.unwrap().unwrap();
+        assert_eq!((stored.title.as_str(), stored.options.len()), ("Dinner", 1));
+        assert_eq!((stored.tags, stored.category), (vec!["food".to_string()], Some("team".to_string())));
+
+        let tags = ["drinks".to_string()];
+        assert!(stores.polls.update_content(poll_id, access, "Drinks", &options, Some(&tags), Some(None)).await.unwrap());
+        let stored = stores.polls.get(poll_id).await.unwrap().unwrap();
+        assert_eq!((stored.tags, stored.category), (vec!["drinks".to_string()], None));
+
+        let stranger = PollAccess::User { user_id: Uuid::new_v4(), collaborators: true, org_id: None };
+        assert!(!stores.polls.update_content(poll_id, stranger, "Mine", &options, Some(&[]), None).await.unwrap());
+        assert_eq!(stores.polls.get(poll_id).await.unwrap().unwrap().tags, ["drinks"]);
+    }
+}
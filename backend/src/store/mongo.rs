@@ -0,0 +1,972 @@
+// src/store/mongo.rs
+use std::sync::Arc;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson, Document, DateTime},
+    options::IndexOptions,
+    results::InsertOneResult,
+    Client, Collection, Database, IndexModel,
+};
+use tower_sessions_mongodb_store::MongoDBStore;
+use uuid::Uuid;
+use webauthn_rs::prelude::{AuthenticationResult, Passkey};
+use crate::auth::username::username_key;
+use crate::error::{is_duplicate_key, ApiError};
+use crate::models::{AdminAction, ApiToken, OrgMember, Organization, Poll, PollOption, Recurrence, UserSession, Visibility, Vote};
+use crate::startup::{PasskeyMeta, UserData};
+use super::{
+    AdminLogStore, OrgStore, PollAccess, PollPage, PollQuery, PollScope, PollSort, PollStore, SessionBackend, StoreResult, Stores,
+    TokenStore, UserSessionStore, UserStore, VoteStore,
+};
+
+const DATABASE_NAME: &str = "polling-app";
+
+pub async fn open(uri: &str) -> Stores {
+    info!("Connecting to MongoDB ....");
+    let client = Client::with_uri_str(uri).await.expect("Failed to connect to MongoDB");
+    let db = client.database(DATABASE_NAME);
+    info!("Using database: {}", DATABASE_NAME);
+    ensure_indexes(&db).await;
+
+    // The session store is on its own driver version, hence the second client
+    let session_client = tower_sessions_mongodb_store::mongodb::Client::with_uri_str(uri)
+        .await
+        .expect("Failed to connect to MongoDB");
+    let session_store = MongoDBStore::new(session_client, DATABASE_NAME.to_string());
+
+    Stores {
+        users: Arc::new(MongoUserStore(db.collection("users"))),
+        polls: Arc::new(MongoPollStore(db.collection("polls"))),
+        votes: Arc::new(MongoVoteStore(db.collection("votes"))),
+        orgs: Arc::new(MongoOrgStore(db.collection("organizations"))),
+        user_sessions: Arc::new(MongoUserSessionStore(db.collection("user_sessions"))),
+        api_tokens: Arc::new(MongoTokenStore(db.collection("api_tokens"))),
+        admin_log: Arc::new(MongoAdminLogStore(db.collection("admin_log"))),
+        session_store: SessionBackend::MongoDB(session_store),
+    }
+}
+
+pub struct MongoUserStore(Collection<UserData>);
+
+#[async_trait]
+impl UserStore for MongoUserStore {
+    async fn get(&self, id: Uuid) -> StoreResult<Option<UserData>> {
+        Ok(self.0.find_one(doc! { "unique_id": id.to_string() }).await?)
+    }
+
+    async fn find_by_username(&self, username: &str) -> StoreResult<Option<UserData>> {
+        Ok(self.0.find_one(doc! { "username_key": username_key(username) }).await?)
+    }
+
+    async fn find_by_recovery_code(&self, username: &str, code_hash: &str) -> StoreResult<Option<UserData>> {
+        Ok(self
+            .0
+            .find_one(doc! { "username_key": username_key(username), "recovery_codes": code_hash })
+            .await?)
+    }
+
+    async fn get_many(&self, ids: &[String]) -> StoreResult<Vec<UserData>> {
+        let cursor = self.0.find(doc! { "unique_id": { "$in": ids } }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn list(&self) -> StoreResult<Vec<UserData>> {
+        let cursor = self.0.find(doc! {}).sort(doc! { "username": 1 }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn insert(&self, user: &UserData) -> StoreResult<()> {
+        // The unique username index catches a concurrent registration
+        match self.0.insert_one(user).await {
+            Ok(_) => Ok(()),
+            Err(e) if is_duplicate_key(&e) => Err(ApiError::UsernameTaken),
+            Err(e) => Err(ApiError::from(e)),
+        }
+    }
+
+    async fn rename(&self, id: Uuid, username: &str) -> StoreResult<()> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string() },
+                doc! { "$set": { "username": username, "username_key": username_key(username) } },
+            )
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_duplicate_key(&e) => Err(ApiError::UsernameTaken),
+            Err(e) => Err(ApiError::from(e)),
+        }
+    }
+
+    async fn set_admin(&self, id: Uuid, is_admin: bool) -> StoreResult<()> {
+        self.0
+            .update_one(doc! { "unique_id": id.to_string() }, doc! { "$set": { "is_admin": is_admin } })
+            .await?;
+        Ok(())
+    }
+
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> StoreResult<()> {
+        self.0
+            .update_one(doc! { "unique_id": id.to_string() }, doc! { "$set": { "disabled": disabled } })
+            .await?;
+        Ok(())
+    }
+
+    async fn update_credential(&self, id: Uuid, result: &AuthenticationResult) -> StoreResult<bool> {
+        let Some(user) = self.get(id).await? else {
+            return Ok(false);
+        };
+        let Some(mut passkey) = user.passkeys.into_iter().find(|sk| sk.cred_id() == result.cred_id()) else {
+            return Ok(false);
+        };
+        passkey.update_credential(result);
+        // Only the used credential's element is replaced, and only while it
+        // is still there
+        let updated = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string(), "passkeys.cred.cred_id": to_bson(result.cred_id())? },
+                doc! { "$set": { "passkeys.$": to_bson(&passkey)? } },
+            )
+            .await?;
+        Ok(updated.matched_count > 0)
+    }
+
+    async fn add_passkey(&self, id: Uuid, passkey: &Passkey, meta: &PasskeyMeta) -> StoreResult<()> {
+        self.0
+            .update_one(
+                doc! { "unique_id": id.to_string() },
+                doc! { "$push": { "passkeys": to_bson(passkey)?, "passkey_meta": to_bson(meta)? } },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn add_passkey_with_recovery_code(
+        &self,
+        id: Uuid,
+        code_hash: &str,
+        passkey: &Passkey,
+        meta: &PasskeyMeta,
+    ) -> StoreResult<bool> {
+        // Matching on the code makes a concurrent recovery with the same code fail
+        let result = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string(), "recovery_codes": code_hash },
+                doc! {
+                    "$pull": { "recovery_codes": code_hash },
+                    "$push": { "passkeys": to_bson(passkey)?, "passkey_meta": to_bson(meta)? },
+                },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn replace_passkeys(
+        &self,
+        id: Uuid,
+        expected_count: usize,
+        passkeys: &[Passkey],
+        meta: &[PasskeyMeta],
+    ) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string(), "passkeys": { "$size": expected_count as i64 } },
+                doc! { "$set": { "passkeys": to_bson(passkeys)?, "passkey_meta": to_bson(meta)? } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn save_passkey_meta(&self, id: Uuid, meta: &PasskeyMeta) -> StoreResult<()> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string(), "passkey_meta.cred_id": &meta.cred_id },
+                doc! { "$set": { "passkey_meta.$": to_bson(meta)? } },
+            )
+            .await?;
+        if result.matched_count == 0 {
+            self.0
+                .update_one(
+                    doc! { "unique_id": id.to_string() },
+                    doc! { "$push": { "passkey_meta": to_bson(meta)? } },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn touch_passkey(&self, id: Uuid, cred_id: &str) -> StoreResult<()> {
+        let now = DateTime::now();
+        let result = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string(), "passkey_meta.cred_id": cred_id },
+                doc! { "$set": { "passkey_meta.$.last_used_at": now } },
+            )
+            .await?;
+        if result.matched_count == 0 {
+            let meta = PasskeyMeta {
+                last_used_at: Some(now),
+                ..PasskeyMeta::unknown(cred_id.to_string())
+            };
+            self.0
+                .update_one(
+                    doc! { "unique_id": id.to_string() },
+                    doc! { "$push": { "passkey_meta": to_bson(&meta)? } },
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn set_recovery_codes(&self, id: Uuid, code_hashes: &[String]) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "unique_id": id.to_string() },
+                doc! { "$set": { "recovery_codes": code_hashes } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> StoreResult<()> {
+        self.0.delete_one(doc! { "unique_id": id.to_string() }).await?;
+        Ok(())
+    }
+}
+
+fn inserted_object_id(result: &InsertOneResult) -> StoreResult<ObjectId> {
+    result
+        .inserted_id
+        .as_object_id()
+        .ok_or_else(|| ApiError::Internal(format!("Inserted ID {} is not an ObjectId", result.inserted_id)))
+}
+
+pub struct MongoPollStore(Collection<Poll>);
+
+fn scope_filter(scope: PollScope<'_>) -> Document {
+    match scope {
+        PollScope::Public => doc! { "visibility": { "$nin": ["unlisted", "private"] } },
+        PollScope::Managed { user_id, org_ids } => doc! { "$or": [
+            { "creator_id": user_id.to_string() },
+            { "collaborator_ids": user_id.to_string() },
+            { "org_id": { "$in": org_ids } },
+        ] },
+    }
+}
+
+// Matches poll `id` if `access` reaches it
+fn managed_filter(id: ObjectId, access: PollAccess) -> Document {
+    let mut filter = doc! { "_id": id };
+    if let PollAccess::User { user_id, collaborators, org_id } = access {
+        let mut any = vec![doc! { "creator_id": user_id.to_string() }];
+        if collaborators {
+            any.push(doc! { "collaborator_ids": user_id.to_string() });
+        }
+        if let Some(org_id) = org_id {
+            any.push(doc! { "org_id": org_id });
+        }
+        filter.insert("$or", any);
+    }
+    filter
+}
+
+impl MongoPollStore {
+    // Runs `stages` and joins `author` in from `users`, in one round trip
+    async fn aggregate(&self, mut pipeline: Vec<Document>) -> StoreResult<Vec<Poll>> {
+        pipeline.extend([
+            doc! { "$lookup": {
+                "from": "users",
+                "localField": "creator_id",
+                "foreignField": "unique_id",
+                "pipeline": [{ "$project": { "_id": 0, "username": 1 } }],
+                "as": "creator",
+            } },
+            doc! { "$set": { "author": { "$first": "$creator.username" } } },
+            doc! { "$unset": ["creator", "sort_key"] },
+        ]);
+        let cursor = self.0.aggregate(pipeline).with_type::<Poll>().await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn find_sorted(&self, filter: Document, sort: Document) -> StoreResult<Vec<Poll>> {
+        let mut pipeline = vec![doc! { "$match": filter }];
+        if !sort.is_empty() {
+            pipeline.push(doc! { "$sort": sort });
+        }
+        self.aggregate(pipeline).await
+    }
+
+    async fn find(&self, filter: Document) -> StoreResult<Vec<Poll>> {
+        self.find_sorted(filter, Document::new()).await
+    }
+
+    async fn find_one(&self, filter: Document) -> StoreResult<Option<Poll>> {
+        Ok(self.find(filter).await?.pop())
+    }
+}
+
+#[async_trait]
+impl PollStore for MongoPollStore {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Poll>> {
+        self.find_one(doc! { "_id": id }).await
+    }
+
+    async fn get_by_share_token(&self, token: &str) -> StoreResult<Option<Poll>> {
+        self.find_one(doc! { "share_token": token }).await
+    }
+
+    async fn get_many(&self, ids: &[ObjectId]) -> StoreResult<Vec<Poll>> {
+        self.find(doc! { "_id": { "$in": ids } }).await
+    }
+
+    async fn insert(&self, poll: &Poll) -> StoreResult<ObjectId> {
+        let result = self.0.insert_one(poll).await?;
+        inserted_object_id(&result)
+    }
+
+    // A standalone server has no transactions, so a failed batch removes
+    // whatever part of it was written
+    async fn insert_many(&self, polls: &[Poll]) -> StoreResult<Vec<ObjectId>> {
+        let ids: Vec<ObjectId> = polls.iter().map(|_| ObjectId::new()).collect();
+        let polls: Vec<Poll> = polls.iter().zip(&ids).map(|(poll, id)| Poll { id: Some(*id), ..poll.clone() }).collect();
+        if let Err(e) = self.0.insert_many(&polls).await {
+            self.0.delete_many(doc! { "_id": { "$in": &ids } }).await?;
+            return Err(e.into());
+        }
+        Ok(ids)
+    }
+
+    async fn search(&self, scope: PollScope<'_>, query: &PollQuery) -> StoreResult<PollPage> {
+        let mut filter = scope_filter(scope);
+        if let Some(search) = &query.search {
+            filter.insert("$text", doc! { "$search": search });
+        }
+        if let Some(is_closed) = query.is_closed {
+            filter.insert("is_closed", is_closed);
+        }
+        if let Some(creator_id) = query.creator_id {
+            filter.insert("creator_id", creator_id.to_string());
+        }
+        if let Some(tag) = &query.tag {
+            filter.insert("tags", tag);
+        }
+        if let Some(category) = &query.category {
+            filter.insert("category", category);
+        }
+        let mut created_at = Document::new();
+        if let Some(after) = query.created_after {
+            created_at.insert("$gte", after);
+        }
+        if let Some(before) = query.created_before {
+            created_at.insert("$lt", before);
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+        let total = self.0.count_documents(filter.clone()).await?;
+
+        let sort_key = match query.sort {
+            PollSort::Newest => doc! { "$toLong": "$created_at" },
+            PollSort::MostVotes => doc! { "$toLong": { "$sum": "$options.votes" } },
+            PollSort::ClosingSoon => doc! { "$ifNull": [{ "$toLong": "$next_occurrence_at" }, i64::MAX] },
+        };
+        let (direction, past) = if query.sort.descending() { (-1, "$lt") } else { (1, "$gt") };
+        let mut pipeline = vec![doc! { "$match": filter }, doc! { "$set": { "sort_key": sort_key } }];
+        if let Some(cursor) = query.cursor {
+            pipeline.push(doc! { "$match": { "$or": [
+                { "sort_key": { past: cursor.key } },
+                { "sort_key": cursor.key, "_id": { past: cursor.id } },
+            ] } });
+        }
+        pipeline.push(doc! { "$sort": { "sort_key": direction, "_id": direction } });
+        pipeline.push(doc! { "$limit": query.limit as i64 + 1 });
+        let polls = self.aggregate(pipeline).await?;
+        Ok(PollPage::new(polls, query, total))
+    }
+
+    async fn tag_counts(&self, scope: PollScope<'_>) -> StoreResult<Vec<(String, u64)>> {
+        let pipeline = vec![
+            doc! { "$match": scope_filter(scope) },
+            doc! { "$unwind": "$tags" },
+            doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+            doc! { "$set": { "count": { "$toLong": "$count" } } },
+            doc! { "$sort": { "count": -1, "_id": 1 } },
+        ];
+        let counts: Vec<Document> = self.0.aggregate(pipeline).await?.try_collect().await?;
+        Ok(counts
+            .iter()
+            .filter_map(|c| Some((c.get_str("_id").ok()?.to_string(), c.get_i64("count").ok()? as u64)))
+            .collect())
+    }
+
+    async fn list_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>> {
+        self.find(doc! { "creator_id": user_id.to_string() }).await
+    }
+
+    async fn list_by_org(&self, org_id: ObjectId) -> StoreResult<Vec<Poll>> {
+        self.find(doc! { "org_id": org_id }).await
+    }
+
+    async fn list_series(&self, series_id: ObjectId) -> StoreResult<Vec<Poll>> {
+        self.find_sorted(
+            doc! { "$or": [{ "_id": series_id }, { "series_id": series_id }] },
+            doc! { "created_at": 1 },
+        )
+        .await
+    }
+
+    async fn list_due(&self, now: DateTime) -> StoreResult<Vec<Poll>> {
+        self.find(doc! {
+            "recurrence": { "$exists": true },
+            "next_occurrence_at": { "$lte": now },
+        })
+        .await
+    }
+
+    async fn close(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool> {
+        let result = self.0.update_one(managed_filter(id, access), doc! { "$set": { "is_closed": true } }).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn reset_votes(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(managed_filter(id, access), doc! { "$set": { "options.$[].votes": 0 } })
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn update_content(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        title: &str,
+        options: &[PollOption],
+        tags: Option<&[String]>,
+        category: Option<Option<&str>>,
+    ) -> StoreResult<bool> {
+        let mut set = doc! { "title": title, "options": to_bson(options)? };
+        if let Some(tags) = tags {
+            set.insert("tags", tags);
+        }
+        let mut update = doc! {};
+        match category {
+            Some(Some(category)) => {
+                set.insert("category", category);
+            }
+            Some(None) => {
+                update.insert("$unset", doc! { "category": "" });
+            }
+            None => {}
+        }
+        update.insert("$set", set);
+        let result = self.0.update_one(managed_filter(id, access), update).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn set_recurrence(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        schedule: Option<(&Recurrence, DateTime)>,
+    ) -> StoreResult<bool> {
+        // Only an open poll carries the schedule; closed instances are history
+        let update = match schedule {
+            Some((recurrence, next_occurrence_at)) => doc! { "$set": {
+                "recurrence": to_bson(recurrence)?,
+                "next_occurrence_at": next_occurrence_at,
+            } },
+            None => doc! { "$unset": { "recurrence": "", "next_occurrence_at": "" } },
+        };
+        let mut filter = managed_filter(id, access);
+        filter.insert("is_closed", false);
+        let result = self.0.update_one(filter, update).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn set_visibility(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        visibility: Visibility,
+        invited_user_ids: &[String],
+        share_token: Option<&str>,
+    ) -> StoreResult<bool> {
+        // Public polls drop the field entirely so the unique sparse index ignores them
+        let mut update = doc! { "$set": {
+            "visibility": to_bson(&visibility)?,
+            "invited_user_ids": invited_user_ids,
+        } };
+        match share_token {
+            Some(token) => update.get_document_mut("$set").unwrap().insert("share_token", token),
+            None => update.insert("$unset", doc! { "share_token": "" }),
+        };
+        let result = self.0.update_one(managed_filter(id, access), update).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn set_embedding(&self, id: ObjectId, access: PollAccess, embeddable: bool, origins: &[String]) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(
+                managed_filter(id, access),
+                doc! { "$set": { "embeddable": embeddable, "embed_origins": origins } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn add_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()> {
+        self.0
+            .update_one(managed_filter(id, access), doc! { "$addToSet": { "collaborator_ids": user_id } })
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()> {
+        self.0
+            .update_one(managed_filter(id, access), doc! { "$pull": { "collaborator_ids": user_id } })
+            .await?;
+        Ok(())
+    }
+
+    async fn increment_vote(&self, id: ObjectId, option_id: i32) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "_id": id, "is_closed": false, "options.id": option_id },
+                doc! { "$inc": { "options.$.votes": 1 } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn claim_rollover(&self, id: ObjectId, due_at: DateTime) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "_id": id, "next_occurrence_at": due_at },
+                doc! {
+                    "$set": { "is_closed": true },
+                    "$unset": { "recurrence": "", "next_occurrence_at": "", "share_token": "" },
+                },
+            )
+            .await?;
+        Ok(result.modified_count > 0)
+    }
+
+    async fn release_rollover(&self, poll: &Poll) -> StoreResult<()> {
+        let Some(id) = poll.id else {
+            return Ok(());
+        };
+        let mut set = doc! { "is_closed": poll.is_closed };
+        if let Some(recurrence) = &poll.recurrence {
+            set.insert("recurrence", to_bson(recurrence)?);
+        }
+        if let Some(next_occurrence_at) = poll.next_occurrence_at {
+            set.insert("next_occurrence_at", next_occurrence_at);
+        }
+        // Left unset when absent, for the unique sparse index
+        if let Some(share_token) = &poll.share_token {
+            set.insert("share_token", share_token);
+        }
+        self.0.update_one(doc! { "_id": id }, doc! { "$set": set }).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: ObjectId, access: PollAccess) -> StoreResult<Option<Poll>> {
+        Ok(self.0.find_one_and_delete(managed_filter(id, access)).await?)
+    }
+
+    async fn delete_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>> {
+        // Delete exactly the listed polls so a poll created meanwhile is not removed unreported
+        let polls = self.list_by_creator(user_id).await?;
+        let ids: Vec<ObjectId> = polls.iter().filter_map(|p| p.id).collect();
+        if !ids.is_empty() {
+            self.0.delete_many(doc! { "_id": { "$in": ids } }).await?;
+        }
+        Ok(polls)
+    }
+
+    async fn reassign_creator(&self, from: Uuid, to: Uuid) -> StoreResult<u64> {
+        let result = self
+            .0
+            .update_many(
+                doc! { "creator_id": from.to_string() },
+                doc! {
+                    "$set": { "creator_id": to.to_string() },
+                    "$unset": { "recurrence": "", "next_occurrence_at": "" },
+                },
+            )
+            .await?;
+        Ok(result.modified_count)
+    }
+
+    async fn remove_user(&self, user_id: &str) -> StoreResult<()> {
+        self.0
+            .update_many(
+                doc! { "$or": [{ "invited_user_ids": user_id }, { "collaborator_ids": user_id }] },
+                doc! { "$pull": { "invited_user_ids": user_id, "collaborator_ids": user_id } },
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct MongoVoteStore(Collection<Vote>);
+
+#[async_trait]
+impl VoteStore for MongoVoteStore {
+    async fn insert(&self, vote: &Vote) -> StoreResult<bool> {
+        // The unique (poll_id, user_id) index turns a second ballot into a duplicate key error
+        match self.0.insert_one(vote).await {
+            Ok(_) => Ok(true),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(ApiError::from(e)),
+        }
+    }
+
+    async fn delete(&self, poll_id: ObjectId, user_id: &str) -> StoreResult<()> {
+        self.0.delete_one(doc! { "poll_id": poll_id, "user_id": user_id }).await?;
+        Ok(())
+    }
+
+    async fn delete_for_polls(&self, poll_ids: &[ObjectId]) -> StoreResult<()> {
+        self.0.delete_many(doc! { "poll_id": { "$in": poll_ids } }).await?;
+        Ok(())
+    }
+
+    async fn delete_by_user(&self, user_id: &str) -> StoreResult<()> {
+        self.0.delete_many(doc! { "user_id": user_id }).await?;
+        Ok(())
+    }
+
+    async fn list_for_poll(&self, poll_id: ObjectId) -> StoreResult<Vec<Vote>> {
+        let cursor = self.0.find(doc! { "poll_id": poll_id }).sort(doc! { "created_at": 1 }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn list_by_user(&self, user_id: &str) -> StoreResult<Vec<Vote>> {
+        let cursor = self.0.find(doc! { "user_id": user_id }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+}
+
+pub struct MongoOrgStore(Collection<Organization>);
+
+#[async_trait]
+impl OrgStore for MongoOrgStore {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Organization>> {
+        Ok(self.0.find_one(doc! { "_id": id }).await?)
+    }
+
+    async fn insert(&self, org: &Organization) -> StoreResult<ObjectId> {
+        let result = self.0.insert_one(org).await?;
+        inserted_object_id(&result)
+    }
+
+    async fn list_for_member(&self, user_id: &str) -> StoreResult<Vec<Organization>> {
+        let cursor = self.0.find(doc! { "members.user_id": user_id }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn set_members(&self, id: ObjectId, members: &[OrgMember]) -> StoreResult<()> {
+        self.0
+            .update_one(doc! { "_id": id }, doc! { "$set": { "members": to_bson(members)? } })
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_member_everywhere(&self, user_id: &str) -> StoreResult<()> {
+        self.0
+            .update_many(
+                doc! { "members.user_id": user_id },
+                doc! { "$pull": { "members": { "user_id": user_id } } },
+            )
+            .await?;
+        self.0.delete_many(doc! { "members": { "$size": 0 } }).await?;
+        Ok(())
+    }
+}
+
+pub struct MongoUserSessionStore(Collection<UserSession>);
+
+#[async_trait]
+impl UserSessionStore for MongoUserSessionStore {
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>> {
+        let cursor = self.0.find(doc! { "user_id": user_id }).sort(doc! { "last_seen_at": -1 }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn touch(&self, session_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<bool> {
+        let result = self
+            .0
+            .update_one(
+                doc! { "session_id": session_id },
+                doc! { "$set": { "last_seen_at": DateTime::now(), "user_agent": user_agent, "ip": ip } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    async fn record(&self, session_id: &str, user_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<()> {
+        self.0
+            .update_one(
+                doc! { "session_id": session_id },
+                doc! {
+                    "$set": { "last_seen_at": DateTime::now(), "user_agent": user_agent, "ip": ip },
+                    "$setOnInsert": { "user_id": user_id, "created_at": DateTime::now() },
+                },
+            )
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, session_id: &str) -> StoreResult<()> {
+        self.0.delete_one(doc! { "session_id": session_id }).await?;
+        Ok(())
+    }
+
+    async fn remove_by_id(&self, id: ObjectId, user_id: &str) -> StoreResult<Option<UserSession>> {
+        Ok(self.0.find_one_and_delete(doc! { "_id": id, "user_id": user_id }).await?)
+    }
+
+    async fn remove_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>> {
+        let cursor = self.0.find(doc! { "user_id": user_id }).await?;
+        let records: Vec<UserSession> = cursor.try_collect().await?;
+        self.0.delete_many(doc! { "user_id": user_id }).await?;
+        Ok(records)
+    }
+}
+
+pub struct MongoTokenStore(Collection<ApiToken>);
+
+#[async_trait]
+impl TokenStore for MongoTokenStore {
+    async fn insert(&self, token: &ApiToken) -> StoreResult<ObjectId> {
+        let result = self.0.insert_one(token).await?;
+        inserted_object_id(&result)
+    }
+
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<ApiToken>> {
+        let cursor = self.0.find(doc! { "user_id": user_id }).sort(doc! { "created_at": -1 }).await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    async fn use_token(&self, token_hash: &str) -> StoreResult<Option<ApiToken>> {
+        Ok(self
+            .0
+            .find_one_and_update(
+                doc! { "token_hash": token_hash, "expires_at": { "$gt": DateTime::now() } },
+                doc! { "$set": { "last_used_at": DateTime::now() } },
+            )
+            .await?)
+    }
+
+    async fn delete(&self, id: ObjectId, user_id: &str) -> StoreResult<bool> {
+        let result = self.0.delete_one(doc! { "_id": id, "user_id": user_id }).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    async fn delete_for_user(&self, user_id: &str) -> StoreResult<()> {
+        self.0.delete_many(doc! { "user_id": user_id }).await?;
+        Ok(())
+    }
+}
+
+pub struct MongoAdminLogStore(Collection<AdminAction>);
+
+#[async_trait]
+impl AdminLogStore for MongoAdminLogStore {
+    async fn record(&self, action: &AdminAction) -> StoreResult<()> {
+        self.0.insert_one(action).await?;
+        Ok(())
+    }
+
+    async fn recent(&self, limit: usize) -> StoreResult<Vec<AdminAction>> {
+        let cursor = self
+            .0
+            .find(doc! {})
+            .sort(doc! { "created_at": -1 })
+            .limit(limit as i64)
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+}
+
+// Polls used to store their creator as a binary UUID while `users.unique_id`
+// is a string, which kept the two from being joined. `Poll` still reads the
+// binary form, so each one is loaded and written back as a string.
+async fn migrate_creator_ids(polls: &Collection<Poll>) {
+    let legacy: Result<Vec<Poll>, _> = match polls.find(doc! { "creator_id": { "$type": "binData" } }).await {
+        Ok(cursor) => cursor.try_collect().await,
+        Err(e) => Err(e),
+    };
+    let legacy = match legacy {
+        Ok(legacy) => legacy,
+        Err(e) => {
+            error!("Failed to read polls with a binary creator_id: {:?}", e);
+            return;
+        }
+    };
+    let mut migrated = 0;
+    for poll in legacy {
+        let Some(id) = poll.id else { continue };
+        match polls
+            .update_one(doc! { "_id": id }, doc! { "$set": { "creator_id": poll.creator_id.to_string() } })
+            .await
+        {
+            Ok(_) => migrated += 1,
+            Err(e) => error!("Failed to migrate creator_id of poll {}: {:?}", id, e),
+        }
+    }
+    if migrated > 0 {
+        info!("Stored creator_id as a string for {} polls", migrated);
+    }
+}
+
+async fn backfill_username_keys(db: &Database) {
+    // Read as plain documents: these lack a field `UserData` requires
+    let users = db.collection::<Document>("users");
+    let cursor = users
+        .find(doc! { "username_key": { "$exists": false } })
+        .projection(doc! { "unique_id": 1, "username": 1 })
+        .await
+        .expect("Failed to read users without username_key");
+    let missing: Vec<Document> = cursor.try_collect().await.expect("Failed to read users without username_key");
+    for user in &missing {
+        let (Ok(unique_id), Ok(username)) = (user.get_str("unique_id"), user.get_str("username")) else {
+            error!("Skipping user document without unique_id or username: {:?}", user.get("_id"));
+            continue;
+        };
+        users
+            .update_one(
+                doc! { "unique_id": unique_id },
+                doc! { "$set": { "username_key": username_key(username) } },
+            )
+            .await
+            .expect("Failed to backfill users.username_key");
+    }
+    if !missing.is_empty() {
+        info!("Backfilled username_key for {} users", missing.len());
+    }
+}
+
+async fn ensure_indexes(db: &Database) {
+    // Accounts created before usernames were case-insensitive get their key
+    // here. The key is computed like at registration, so lookups find them.
+    backfill_username_keys(db).await;
+    // If two old accounts differ only in case the index cannot be built; the
+    // server refuses to start until one of them is renamed
+    let users = db.collection::<UserData>("users");
+    let username_index = IndexModel::builder()
+        .keys(doc! { "username_key": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    users
+        .create_index(username_index)
+        .await
+        .expect("Failed to create unique index users.username_key; are two usernames equal but for case?");
+    info!("Ensured index users.username_key");
+    // Poll reads join authors on it
+    let unique_id_index = IndexModel::builder()
+        .keys(doc! { "unique_id": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    match users.create_index(unique_id_index).await {
+        Ok(_) => info!("Ensured index users.unique_id"),
+        Err(e) => error!("Failed to create index users.unique_id: {:?}", e),
+    }
+
+    // Authors are resolved from `creator_id`; names copied into older poll
+    // documents would go stale on rename
+    let polls = db.collection::<Poll>("polls");
+    match polls
+        .update_many(doc! { "author": { "$exists": true } }, doc! { "$unset": { "author": "" } })
+        .await
+    {
+        Ok(result) if result.modified_count > 0 => info!("Dropped stored author from {} polls", result.modified_count),
+        Ok(_) => {}
+        Err(e) => error!("Failed to drop polls.author: {:?}", e),
+    }
+    migrate_creator_ids(&polls).await;
+    match polls.create_index(IndexModel::builder().keys(doc! { "creator_id": 1 }).build()).await {
+        Ok(_) => info!("Ensured index polls.creator_id"),
+        Err(e) => error!("Failed to create index polls.creator_id: {:?}", e),
+    }
+
+    // Listings filter on both
+    let label_indexes = [
+        IndexModel::builder().keys(doc! { "tags": 1 }).build(),
+        IndexModel::builder().keys(doc! { "category": 1 }).build(),
+    ];
+    match polls.create_indexes(label_indexes).await {
+        Ok(_) => info!("Ensured indexes polls.tags and polls.category"),
+        Err(e) => error!("Failed to create indexes polls.tags and polls.category: {:?}", e),
+    }
+
+    // Backs the `q` search of poll listings
+    let text_index = IndexModel::builder()
+        .keys(doc! { "title": "text", "options.text": "text" })
+        .build();
+    match polls.create_index(text_index).await {
+        Ok(_) => info!("Ensured text index on polls"),
+        Err(e) => error!("Failed to create text index on polls: {:?}", e),
+    }
+
+    let share_token_index = IndexModel::builder()
+        .keys(doc! { "share_token": 1 })
+        .options(IndexOptions::builder().unique(true).sparse(true).build())
+        .build();
+    match polls.create_index(share_token_index).await {
+        Ok(_) => info!("Ensured index polls.share_token"),
+        Err(e) => error!("Failed to create index polls.share_token: {:?}", e),
+    }
+
+    let session_index = IndexModel::builder()
+        .keys(doc! { "session_id": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    let session_user_index = IndexModel::builder().keys(doc! { "user_id": 1 }).build();
+    match db
+        .collection::<UserSession>("user_sessions")
+        .create_indexes([session_index, session_user_index])
+        .await
+    {
+        Ok(_) => info!("Ensured indexes on user_sessions"),
+        Err(e) => error!("Failed to create indexes on user_sessions: {:?}", e),
+    }
+
+    let token_index = IndexModel::builder()
+        .keys(doc! { "token_hash": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    let token_user_index = IndexModel::builder().keys(doc! { "user_id": 1 }).build();
+    match db
+        .collection::<ApiToken>("api_tokens")
+        .create_indexes([token_index, token_user_index])
+        .await
+    {
+        Ok(_) => info!("Ensured indexes on api_tokens"),
+        Err(e) => error!("Failed to create indexes on api_tokens: {:?}", e),
+    }
+
+    // One ballot per user per poll
+    let vote_index = IndexModel::builder()
+        .keys(doc! { "poll_id": 1, "user_id": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    match db.collection::<Vote>("votes").create_index(vote_index).await {
+        Ok(_) => info!("Ensured index votes.poll_id_user_id"),
+        Err(e) => error!("Failed to create index votes.poll_id_user_id: {:?}", e),
+    }
+}
@@ -0,0 +1,1666 @@
+// src/store/sql.rs
+//
+// SQLite and PostgreSQL storage through sqlx's `Any` driver, so one set of
+// queries serves both. Schema changes go in `migrations/<database>`.
+use std::collections::HashMap;
+use std::sync::Arc;
+use async_trait::async_trait;
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{
+    any::{AnyPoolOptions, AnyRow},
+    error::BoxDynError,
+    migrate::Migrator,
+    AnyPool, Row,
+};
+use tower_sessions::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+use uuid::Uuid;
+use webauthn_rs::prelude::{AuthenticationResult, Passkey};
+use crate::auth::username::username_key;
+use crate::error::ApiError;
+use crate::models::{
+    AdminAction, ApiToken, OrgMember, OrgRole, Organization, Poll, PollOption, Recurrence, UserSession, Visibility, Vote,
+};
+use crate::startup::{PasskeyMeta, UserData};
+use super::{
+    AdminLogStore, OrgStore, PollAccess, PollPage, PollQuery, PollScope, PollSort, PollStore, SessionBackend, StorageKind,
+    StoreResult, Stores, TokenStore, UserSessionStore, UserStore, VoteStore,
+};
+
+static SQLITE_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/sqlite");
+static POSTGRES_MIGRATIONS: Migrator = sqlx::migrate!("./migrations/postgres");
+
+pub async fn open(kind: StorageKind, url: &str) -> Stores {
+    sqlx::any::install_default_drivers();
+    let pool = AnyPoolOptions::new()
+        .connect(url)
+        .await
+        .expect("Failed to connect to DATABASE_URL");
+    let migrator = match kind {
+        StorageKind::Postgres => &POSTGRES_MIGRATIONS,
+        _ => &SQLITE_MIGRATIONS,
+    };
+    migrator.run(&pool).await.expect("Failed to run database migrations");
+    info!("Database schema is up to date");
+
+    Stores {
+        users: Arc::new(SqlUserStore(pool.clone())),
+        polls: Arc::new(SqlPollStore(pool.clone())),
+        votes: Arc::new(SqlVoteStore(pool.clone())),
+        orgs: Arc::new(SqlOrgStore(pool.clone())),
+        user_sessions: Arc::new(SqlUserSessionStore(pool.clone())),
+        api_tokens: Arc::new(SqlTokenStore(pool.clone())),
+        admin_log: Arc::new(SqlAdminLogStore(pool.clone())),
+        session_store: SessionBackend::Sql(SqlSessionStore(pool)),
+    }
+}
+
+fn decode_error(e: impl Into<BoxDynError>) -> ApiError {
+    ApiError::from(sqlx::Error::Decode(e.into()))
+}
+
+fn to_json<T: Serialize + ?Sized>(value: &T) -> StoreResult<String> {
+    serde_json::to_string(value).map_err(|e| ApiError::from(sqlx::Error::Encode(e.into())))
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> StoreResult<T> {
+    serde_json::from_str(json).map_err(decode_error)
+}
+
+fn object_id(hex: &str) -> StoreResult<ObjectId> {
+    ObjectId::parse_str(hex).map_err(decode_error)
+}
+
+fn optional_object_id(hex: Option<String>) -> StoreResult<Option<ObjectId>> {
+    hex.as_deref().map(object_id).transpose()
+}
+
+fn uuid(s: &str) -> StoreResult<Uuid> {
+    Uuid::parse_str(s).map_err(decode_error)
+}
+
+// PostgreSQL returns booleans, SQLite the integers they are stored as
+fn flag(row: &AnyRow, column: &str) -> StoreResult<bool> {
+    match row.try_get::<bool, _>(column) {
+        Ok(value) => Ok(value),
+        Err(_) => Ok(row.try_get::<i64, _>(column)? != 0),
+    }
+}
+
+fn timestamp(row: &AnyRow, column: &str) -> StoreResult<DateTime> {
+    Ok(DateTime::from_millis(row.try_get(column)?))
+}
+
+fn optional_timestamp(row: &AnyRow, column: &str) -> StoreResult<Option<DateTime>> {
+    Ok(row.try_get::<Option<i64>, _>(column)?.map(DateTime::from_millis))
+}
+
+// `$first, $first + 1, ...` for an `IN (...)` list of `count` values
+fn placeholders(first: usize, count: usize) -> String {
+    (first..first + count).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", ")
+}
+
+// Makes `%`, `_` and `\` match themselves in a `LIKE ... ESCAPE '\'` pattern
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+fn visibility_name(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Public => "public",
+        Visibility::Unlisted => "unlisted",
+        Visibility::Private => "private",
+    }
+}
+
+fn parse_visibility(name: &str) -> StoreResult<Visibility> {
+    match name {
+        "public" => Ok(Visibility::Public),
+        "unlisted" => Ok(Visibility::Unlisted),
+        "private" => Ok(Visibility::Private),
+        other => Err(decode_error(format!("unknown visibility {:?}", other))),
+    }
+}
+
+fn role_name(role: OrgRole) -> &'static str {
+    match role {
+        OrgRole::Owner => "owner",
+        OrgRole::Editor => "editor",
+        OrgRole::Viewer => "viewer",
+    }
+}
+
+fn parse_role(name: &str) -> StoreResult<OrgRole> {
+    match name {
+        "owner" => Ok(OrgRole::Owner),
+        "editor" => Ok(OrgRole::Editor),
+        "viewer" => Ok(OrgRole::Viewer),
+        other => Err(decode_error(format!("unknown organization role {:?}", other))),
+    }
+}
+
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(e) if e.is_unique_violation())
+}
+
+const USER_COLUMNS: &str =
+    "unique_id, username, username_key, is_admin, disabled, passkeys, passkey_meta, recovery_codes, version";
+
+fn user_from_row(row: &AnyRow) -> StoreResult<UserData> {
+    Ok(UserData {
+        username: row.try_get("username")?,
+        username_key: row.try_get("username_key")?,
+        unique_id: uuid(&row.try_get::<String, _>("unique_id")?)?,
+        passkeys: from_json(&row.try_get::<String, _>("passkeys")?)?,
+        is_admin: flag(row, "is_admin")?,
+        disabled: flag(row, "disabled")?,
+        passkey_meta: from_json(&row.try_get::<String, _>("passkey_meta")?)?,
+        recovery_codes: from_json(&row.try_get::<String, _>("recovery_codes")?)?,
+    })
+}
+
+pub struct SqlUserStore(AnyPool);
+
+impl SqlUserStore {
+    async fn find_one(&self, column: &str, value: &str) -> StoreResult<Option<(UserData, i64)>> {
+        let row = sqlx::query(&format!("SELECT {} FROM users WHERE {} = $1", USER_COLUMNS, column))
+            .bind(value)
+            .fetch_optional(&self.0)
+            .await?;
+        row.map(|row| Ok((user_from_row(&row)?, row.try_get("version")?))).transpose()
+    }
+
+    // Applies `change` to the user's passkeys and recovery codes, retrying if
+    // another update got in between. False if there is no such user or
+    // `change` returned false.
+    async fn modify(&self, id: Uuid, mut change: impl FnMut(&mut UserData) -> bool + Send) -> StoreResult<bool> {
+        loop {
+            let Some((mut user, version)) = self.find_one("unique_id", &id.to_string()).await? else {
+                return Ok(false);
+            };
+            if !change(&mut user) {
+                return Ok(false);
+            }
+            let result = sqlx::query(
+                "UPDATE users SET passkeys = $1, passkey_meta = $2, recovery_codes = $3, version = version + 1 \
+                 WHERE unique_id = $4 AND version = $5",
+            )
+            .bind(to_json(&user.passkeys)?)
+            .bind(to_json(&user.passkey_meta)?)
+            .bind(to_json(&user.recovery_codes)?)
+            .bind(id.to_string())
+            .bind(version)
+            .execute(&self.0)
+            .await?;
+            if result.rows_affected() > 0 {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl UserStore for SqlUserStore {
+    async fn get(&self, id: Uuid) -> StoreResult<Option<UserData>> {
+        Ok(self.find_one("unique_id", &id.to_string()).await?.map(|(user, _)| user))
+    }
+
+    async fn find_by_username(&self, username: &str) -> StoreResult<Option<UserData>> {
+        Ok(self.find_one("username_key", &username_key(username)).await?.map(|(user, _)| user))
+    }
+
+    async fn find_by_recovery_code(&self, username: &str, code_hash: &str) -> StoreResult<Option<UserData>> {
+        Ok(self
+            .find_by_username(username)
+            .await?
+            .filter(|user| user.recovery_codes.iter().any(|c| c == code_hash)))
+    }
+
+    async fn get_many(&self, ids: &[String]) -> StoreResult<Vec<UserData>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!("SELECT {} FROM users WHERE unique_id IN ({})", USER_COLUMNS, placeholders(1, ids.len()));
+        let mut query = sqlx::query(&sql);
+        for id in ids {
+            query = query.bind(id.as_str());
+        }
+        query.fetch_all(&self.0).await?.iter().map(user_from_row).collect()
+    }
+
+    async fn list(&self) -> StoreResult<Vec<UserData>> {
+        sqlx::query(&format!("SELECT {} FROM users ORDER BY username", USER_COLUMNS))
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(user_from_row)
+            .collect()
+    }
+
+    async fn insert(&self, user: &UserData) -> StoreResult<()> {
+        // The unique username_key column catches a concurrent registration
+        let result = sqlx::query(
+            "INSERT INTO users (unique_id, username, username_key, is_admin, disabled, passkeys, passkey_meta, recovery_codes) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(user.unique_id.to_string())
+        .bind(user.username.as_str())
+        .bind(user.username_key.as_str())
+        .bind(user.is_admin)
+        .bind(user.disabled)
+        .bind(to_json(&user.passkeys)?)
+        .bind(to_json(&user.passkey_meta)?)
+        .bind(to_json(&user.recovery_codes)?)
+        .execute(&self.0)
+        .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_unique_violation(&e) => Err(ApiError::UsernameTaken),
+            Err(e) => Err(ApiError::from(e)),
+        }
+    }
+
+    async fn rename(&self, id: Uuid, username: &str) -> StoreResult<()> {
+        let result = sqlx::query("UPDATE users SET username = $1, username_key = $2 WHERE unique_id = $3")
+            .bind(username)
+            .bind(username_key(username))
+            .bind(id.to_string())
+            .execute(&self.0)
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_unique_violation(&e) => Err(ApiError::UsernameTaken),
+            Err(e) => Err(ApiError::from(e)),
+        }
+    }
+
+    async fn set_admin(&self, id: Uuid, is_admin: bool) -> StoreResult<()> {
+        sqlx::query("UPDATE users SET is_admin = $1 WHERE unique_id = $2")
+            .bind(is_admin)
+            .bind(id.to_string())
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_disabled(&self, id: Uuid, disabled: bool) -> StoreResult<()> {
+        sqlx::query("UPDATE users SET disabled = $1 WHERE unique_id = $2")
+            .bind(disabled)
+            .bind(id.to_string())
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn update_credential(&self, id: Uuid, result: &AuthenticationResult) -> StoreResult<bool> {
+        self.modify(id, |user| user.passkeys.iter_mut().any(|sk| sk.update_credential(result).is_some()))
+            .await
+    }
+
+    async fn add_passkey(&self, id: Uuid, passkey: &Passkey, meta: &PasskeyMeta) -> StoreResult<()> {
+        self.modify(id, |user| {
+            user.passkeys.push(passkey.clone());
+            user.passkey_meta.push(meta.clone());
+            true
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn add_passkey_with_recovery_code(
+        &self,
+        id: Uuid,
+        code_hash: &str,
+        passkey: &Passkey,
+        meta: &PasskeyMeta,
+    ) -> StoreResult<bool> {
+        // A concurrent recovery with the same code finds it gone and fails
+        self.modify(id, |user| {
+            let Some(index) = user.recovery_codes.iter().position(|c| c == code_hash) else {
+                return false;
+            };
+            user.recovery_codes.remove(index);
+            user.passkeys.push(passkey.clone());
+            user.passkey_meta.push(meta.clone());
+            true
+        })
+        .await
+    }
+
+    async fn replace_passkeys(
+        &self,
+        id: Uuid,
+        expected_count: usize,
+        passkeys: &[Passkey],
+        meta: &[PasskeyMeta],
+    ) -> StoreResult<bool> {
+        self.modify(id, |user| {
+            if user.passkeys.len() != expected_count {
+                return false;
+            }
+            user.passkeys = passkeys.to_vec();
+            user.passkey_meta = meta.to_vec();
+            true
+        })
+        .await
+    }
+
+    async fn save_passkey_meta(&self, id: Uuid, meta: &PasskeyMeta) -> StoreResult<()> {
+        self.modify(id, |user| {
+            match user.passkey_meta.iter_mut().find(|m| m.cred_id == meta.cred_id) {
+                Some(existing) => *existing = meta.clone(),
+                None => user.passkey_meta.push(meta.clone()),
+            }
+            true
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn touch_passkey(&self, id: Uuid, cred_id: &str) -> StoreResult<()> {
+        let now = DateTime::now();
+        self.modify(id, |user| {
+            match user.passkey_meta.iter_mut().find(|m| m.cred_id == cred_id) {
+                Some(meta) => meta.last_used_at = Some(now),
+                None => user.passkey_meta.push(PasskeyMeta {
+                    last_used_at: Some(now),
+                    ..PasskeyMeta::unknown(cred_id.to_string())
+                }),
+            }
+            true
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn set_recovery_codes(&self, id: Uuid, code_hashes: &[String]) -> StoreResult<bool> {
+        let result = sqlx::query("UPDATE users SET recovery_codes = $1, version = version + 1 WHERE unique_id = $2")
+            .bind(to_json(code_hashes)?)
+            .bind(id.to_string())
+            .execute(&self.0)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, id: Uuid) -> StoreResult<()> {
+        sqlx::query("DELETE FROM users WHERE unique_id = $1")
+            .bind(id.to_string())
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+}
+
+const POLL_COLUMNS: &str = "id, title, creator_id, is_closed, created_at, recurrence, next_occurrence_at, \
+                            series_id, previous_instance_id, visibility, share_token, org_id, category, \
+                            embeddable, embed_origins, \
+                            (SELECT username FROM users WHERE users.unique_id = polls.creator_id) AS author";
+
+fn poll_from_row(row: &AnyRow) -> StoreResult<Poll> {
+    let recurrence: Option<String> = row.try_get("recurrence")?;
+    Ok(Poll {
+        id: Some(object_id(&row.try_get::<String, _>("id")?)?),
+        title: row.try_get("title")?,
+        options: Vec::new(),
+        creator_id: uuid(&row.try_get::<String, _>("creator_id")?)?,
+        is_closed: flag(row, "is_closed")?,
+        created_at: timestamp(row, "created_at")?,
+        author: row.try_get("author")?,
+        recurrence: recurrence.as_deref().map(from_json::<Recurrence>).transpose()?,
+        next_occurrence_at: optional_timestamp(row, "next_occurrence_at")?,
+        series_id: optional_object_id(row.try_get("series_id")?)?,
+        previous_instance_id: optional_object_id(row.try_get("previous_instance_id")?)?,
+        visibility: parse_visibility(&row.try_get::<String, _>("visibility")?)?,
+        share_token: row.try_get("share_token")?,
+        invited_user_ids: Vec::new(),
+        org_id: optional_object_id(row.try_get("org_id")?)?,
+        collaborator_ids: Vec::new(),
+        tags: Vec::new(),
+        category: row.try_get("category")?,
+        embeddable: flag(row, "embeddable")?,
+        embed_origins: from_json(&row.try_get::<String, _>("embed_origins")?)?,
+    })
+}
+
+pub struct SqlPollStore(AnyPool);
+
+impl SqlPollStore {
+    // Runs `SELECT ... FROM polls WHERE <filter>` with `args` bound in order,
+    // then loads the options, invitations and collaborators of the polls found
+    async fn find(&self, filter: &str, args: &[&str]) -> StoreResult<Vec<Poll>> {
+        let sql = format!("SELECT {} FROM polls WHERE {} ORDER BY created_at, id", POLL_COLUMNS, filter);
+        let mut query = sqlx::query(&sql);
+        for arg in args {
+            query = query.bind(*arg);
+        }
+        let mut polls = query
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(poll_from_row)
+            .collect::<StoreResult<Vec<_>>>()?;
+        if polls.is_empty() {
+            return Ok(polls);
+        }
+
+        let ids: Vec<String> = polls.iter().filter_map(|p| p.id).map(|id| id.to_hex()).collect();
+        let index: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+        let list = placeholders(1, ids.len());
+
+        let sql = format!(
+            "SELECT poll_id, option_id, text, votes FROM poll_options WHERE poll_id IN ({}) ORDER BY position",
+            list
+        );
+        let mut query = sqlx::query(&sql);
+        for id in &ids {
+            query = query.bind(id.as_str());
+        }
+        for row in query.fetch_all(&self.0).await? {
+            let poll_id: String = row.try_get("poll_id")?;
+            if let Some(&i) = index.get(poll_id.as_str()) {
+                polls[i].options.push(PollOption {
+                    id: row.try_get::<i64, _>("option_id")? as i32,
+                    text: row.try_get("text")?,
+                    votes: row.try_get::<i64, _>("votes")? as i32,
+                });
+            }
+        }
+
+        for table in ["poll_invites", "poll_collaborators"] {
+            let sql = format!(
+                "SELECT poll_id, user_id FROM {} WHERE poll_id IN ({}) ORDER BY position",
+                table, list
+            );
+            let mut query = sqlx::query(&sql);
+            for id in &ids {
+                query = query.bind(id.as_str());
+            }
+            for row in query.fetch_all(&self.0).await? {
+                let poll_id: String = row.try_get("poll_id")?;
+                if let Some(&i) = index.get(poll_id.as_str()) {
+                    let user_id = row.try_get("user_id")?;
+                    match table {
+                        "poll_invites" => polls[i].invited_user_ids.push(user_id),
+                        _ => polls[i].collaborator_ids.push(user_id),
+                    }
+                }
+            }
+        }
+
+        let sql = format!("SELECT poll_id, tag FROM poll_tags WHERE poll_id IN ({}) ORDER BY position", list);
+        let mut query = sqlx::query(&sql);
+        for id in &ids {
+            query = query.bind(id.as_str());
+        }
+        for row in query.fetch_all(&self.0).await? {
+            let poll_id: String = row.try_get("poll_id")?;
+            if let Some(&i) = index.get(poll_id.as_str()) {
+                polls[i].tags.push(row.try_get("tag")?);
+            }
+        }
+        Ok(polls)
+    }
+
+    async fn find_one(&self, filter: &str, args: &[&str]) -> StoreResult<Option<Poll>> {
+        Ok(self.find(filter, args).await?.pop())
+    }
+}
+
+async fn insert_options(
+    tx: &mut sqlx::Transaction<'_, sqlx::Any>,
+    poll_id: &str,
+    options: &[PollOption],
+) -> StoreResult<()> {
+    for (position, option) in options.iter().enumerate() {
+        sqlx::query("INSERT INTO poll_options (poll_id, option_id, position, text, votes) VALUES ($1, $2, $3, $4, $5)")
+            .bind(poll_id)
+            .bind(option.id as i64)
+            .bind(position as i64)
+            .bind(option.text.as_str())
+            .bind(option.votes as i64)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn insert_poll_users(
+    tx: &mut sqlx::Transaction<'_, sqlx::Any>,
+    table: &str,
+    poll_id: &str,
+    user_ids: &[String],
+) -> StoreResult<()> {
+    let sql = format!("INSERT INTO {} (poll_id, user_id, position) VALUES ($1, $2, $3)", table);
+    for (position, user_id) in user_ids.iter().enumerate() {
+        sqlx::query(&sql)
+            .bind(poll_id)
+            .bind(user_id.as_str())
+            .bind(position as i64)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn insert_tags(tx: &mut sqlx::Transaction<'_, sqlx::Any>, poll_id: &str, tags: &[String]) -> StoreResult<()> {
+    for (position, tag) in tags.iter().enumerate() {
+        sqlx::query("INSERT INTO poll_tags (poll_id, tag, position) VALUES ($1, $2, $3)")
+            .bind(poll_id)
+            .bind(tag.as_str())
+            .bind(position as i64)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+// Condition selecting the polls in `scope`, with its arguments appended to `args`
+fn scope_condition(scope: PollScope<'_>, args: &mut Vec<String>) -> String {
+    match scope {
+        PollScope::Public => "visibility = 'public'".to_string(),
+        PollScope::Managed { user_id, org_ids } => {
+            args.push(user_id.to_string());
+            let n = args.len();
+            let mut condition =
+                format!("creator_id = ${n} OR id IN (SELECT poll_id FROM poll_collaborators WHERE user_id = ${n})");
+            if !org_ids.is_empty() {
+                condition.push_str(&format!(" OR org_id IN ({})", placeholders(n + 1, org_ids.len())));
+                args.extend(org_ids.iter().map(|id| id.to_hex()));
+            }
+            format!("({})", condition)
+        }
+    }
+}
+
+// Condition matching the polls `access` reaches, with its arguments bound
+// from `$first` on
+fn access_condition(access: PollAccess, first: usize, args: &mut Vec<String>) -> String {
+    match access {
+        PollAccess::Any => "TRUE".to_string(),
+        PollAccess::User { user_id, collaborators, org_id } => {
+            args.push(user_id.to_string());
+            let mut condition = format!("polls.creator_id = ${first}");
+            if collaborators {
+                condition.push_str(&format!(
+                    " OR polls.id IN (SELECT poll_id FROM poll_collaborators WHERE user_id = ${first})"
+                ));
+            }
+            if let Some(org_id) = org_id {
+                args.push(org_id.to_hex());
+                condition.push_str(&format!(" OR polls.org_id = ${}", first + 1));
+            }
+            format!("({})", condition)
+        }
+    }
+}
+
+// Takes the poll's row lock for the rest of the transaction, so vote counts
+// never change under a concurrent close. False if there is no such poll or
+// `access` does not reach it.
+async fn lock_poll(
+    tx: &mut sqlx::Transaction<'_, sqlx::Any>,
+    id: &str,
+    open_only: bool,
+    access: PollAccess,
+) -> StoreResult<bool> {
+    let mut args = Vec::new();
+    let mut sql = format!(
+        "UPDATE polls SET is_closed = is_closed WHERE id = $1 AND {}",
+        access_condition(access, 2, &mut args)
+    );
+    if open_only {
+        sql.push_str(" AND is_closed = FALSE");
+    }
+    let mut query = sqlx::query(&sql).bind(id);
+    for arg in &args {
+        query = query.bind(arg.as_str());
+    }
+    let result = query.execute(&mut **tx).await?;
+    Ok(result.rows_affected() > 0)
+}
+
+// Writes a poll with its options, tags and user lists inside `tx`
+async fn insert_poll(tx: &mut sqlx::Transaction<'_, sqlx::Any>, poll: &Poll) -> StoreResult<ObjectId> {
+    let id = poll.id.unwrap_or_default();
+    let hex = id.to_hex();
+    sqlx::query(
+        "INSERT INTO polls (id, title, creator_id, is_closed, created_at, recurrence, next_occurrence_at, \
+         series_id, previous_instance_id, visibility, share_token, org_id, category, embeddable, \
+         embed_origins) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+    )
+    .bind(hex.as_str())
+    .bind(poll.title.as_str())
+    .bind(poll.creator_id.to_string())
+    .bind(poll.is_closed)
+    .bind(poll.created_at.timestamp_millis())
+    .bind(poll.recurrence.as_ref().map(to_json).transpose()?)
+    .bind(poll.next_occurrence_at.map(|t| t.timestamp_millis()))
+    .bind(poll.series_id.map(|id| id.to_hex()))
+    .bind(poll.previous_instance_id.map(|id| id.to_hex()))
+    .bind(visibility_name(poll.visibility))
+    .bind(poll.share_token.as_deref())
+    .bind(poll.org_id.map(|id| id.to_hex()))
+    .bind(poll.category.as_deref())
+    .bind(poll.embeddable)
+    .bind(to_json(&poll.embed_origins)?)
+    .execute(&mut **tx)
+    .await?;
+    insert_options(tx, &hex, &poll.options).await?;
+    insert_tags(tx, &hex, &poll.tags).await?;
+    insert_poll_users(tx, "poll_invites", &hex, &poll.invited_user_ids).await?;
+    insert_poll_users(tx, "poll_collaborators", &hex, &poll.collaborator_ids).await?;
+    Ok(id)
+}
+
+#[async_trait]
+impl PollStore for SqlPollStore {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Poll>> {
+        self.find_one("id = $1", &[&id.to_hex()]).await
+    }
+
+    async fn get_by_share_token(&self, token: &str) -> StoreResult<Option<Poll>> {
+        self.find_one("share_token = $1", &[token]).await
+    }
+
+    async fn get_many(&self, ids: &[ObjectId]) -> StoreResult<Vec<Poll>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let hex: Vec<String> = ids.iter().map(|id| id.to_hex()).collect();
+        let args: Vec<&str> = hex.iter().map(String::as_str).collect();
+        self.find(&format!("id IN ({})", placeholders(1, args.len())), &args).await
+    }
+
+    async fn insert(&self, poll: &Poll) -> StoreResult<ObjectId> {
+        let mut tx = self.0.begin().await?;
+        let id = insert_poll(&mut tx, poll).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn insert_many(&self, polls: &[Poll]) -> StoreResult<Vec<ObjectId>> {
+        let mut tx = self.0.begin().await?;
+        let mut ids = Vec::with_capacity(polls.len());
+        for poll in polls {
+            // Fresh IDs, as `insert_poll` keeps one the poll already has
+            ids.push(insert_poll(&mut tx, &Poll { id: None, ..poll.clone() }).await?);
+        }
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    async fn search(&self, scope: PollScope<'_>, query: &PollQuery) -> StoreResult<PollPage> {
+        // Text is bound in order; numbers are inlined, as `find` binds text only
+        let mut args: Vec<String> = Vec::new();
+        let mut conditions = vec![scope_condition(scope, &mut args)];
+        if let Some(search) = &query.search {
+            // Any of the words, as with a MongoDB text search
+            let mut matches = Vec::new();
+            for word in search.split_whitespace() {
+                args.push(format!("%{}%", escape_like(&word.to_lowercase())));
+                matches.push(format!(
+                    "LOWER(title) LIKE ${n} ESCAPE '\\' \
+                     OR id IN (SELECT poll_id FROM poll_options WHERE LOWER(text) LIKE ${n} ESCAPE '\\')",
+                    n = args.len()
+                ));
+            }
+            if !matches.is_empty() {
+                conditions.push(format!("({})", matches.join(" OR ")));
+            }
+        }
+        if let Some(is_closed) = query.is_closed {
+            conditions.push(format!("is_closed = {}", if is_closed { "TRUE" } else { "FALSE" }));
+        }
+        if let Some(creator_id) = query.creator_id {
+            args.push(creator_id.to_string());
+            conditions.push(format!("creator_id = ${}", args.len()));
+        }
+        if let Some(tag) = &query.tag {
+            args.push(tag.clone());
+            conditions.push(format!("id IN (SELECT poll_id FROM poll_tags WHERE tag = ${})", args.len()));
+        }
+        if let Some(category) = &query.category {
+            args.push(category.clone());
+            conditions.push(format!("category = ${}", args.len()));
+        }
+        if let Some(after) = query.created_after {
+            conditions.push(format!("created_at >= {}", after.timestamp_millis()));
+        }
+        if let Some(before) = query.created_before {
+            conditions.push(format!("created_at < {}", before.timestamp_millis()));
+        }
+        let filter = conditions.join(" AND ");
+
+        let sql = format!("SELECT COUNT(*) AS total FROM polls WHERE {}", filter);
+        let mut count = sqlx::query(&sql);
+        for arg in &args {
+            count = count.bind(arg.as_str());
+        }
+        let total: i64 = count.fetch_one(&self.0).await?.try_get("total")?;
+
+        let sort_key = match query.sort {
+            PollSort::Newest => "created_at",
+            PollSort::MostVotes => "(SELECT COALESCE(SUM(votes), 0) FROM poll_options WHERE poll_id = polls.id)",
+            PollSort::ClosingSoon => "COALESCE(next_occurrence_at, 9223372036854775807)",
+        };
+        let (direction, past) = if query.sort.descending() { ("DESC", "<") } else { ("ASC", ">") };
+        let mut page_filter = filter;
+        if let Some(cursor) = query.cursor {
+            args.push(cursor.id.to_hex());
+            page_filter.push_str(&format!(
+                " AND ({key} {past} {value} OR ({key} = {value} AND id {past} ${n}))",
+                key = sort_key,
+                past = past,
+                value = cursor.key,
+                n = args.len()
+            ));
+        }
+        let sql = format!(
+            "SELECT id FROM polls WHERE {} ORDER BY {} {dir}, id {dir} LIMIT {}",
+            page_filter,
+            sort_key,
+            query.limit + 1,
+            dir = direction
+        );
+        let mut page = sqlx::query(&sql);
+        for arg in &args {
+            page = page.bind(arg.as_str());
+        }
+        let ids = page
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(|row| row.try_get::<String, _>("id"))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut polls = Vec::with_capacity(ids.len());
+        if !ids.is_empty() {
+            let args: Vec<&str> = ids.iter().map(String::as_str).collect();
+            let mut found = self.find(&format!("id IN ({})", placeholders(1, args.len())), &args).await?;
+            for id in &ids {
+                if let Some(i) = found.iter().position(|p| p.id.is_some_and(|pid| pid.to_hex() == *id)) {
+                    polls.push(found.swap_remove(i));
+                }
+            }
+        }
+        Ok(PollPage::new(polls, query, total as u64))
+    }
+
+    async fn tag_counts(&self, scope: PollScope<'_>) -> StoreResult<Vec<(String, u64)>> {
+        let mut args = Vec::new();
+        let sql = format!(
+            "SELECT tag, COUNT(*) AS uses FROM poll_tags WHERE poll_id IN (SELECT id FROM polls WHERE {}) \
+             GROUP BY tag ORDER BY uses DESC, tag",
+            scope_condition(scope, &mut args)
+        );
+        let mut query = sqlx::query(&sql);
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        query
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(|row| Ok((row.try_get("tag")?, row.try_get::<i64, _>("uses")? as u64)))
+            .collect()
+    }
+
+    async fn list_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>> {
+        self.find("creator_id = $1", &[&user_id.to_string()]).await
+    }
+
+    async fn list_by_org(&self, org_id: ObjectId) -> StoreResult<Vec<Poll>> {
+        self.find("org_id = $1", &[&org_id.to_hex()]).await
+    }
+
+    async fn list_series(&self, series_id: ObjectId) -> StoreResult<Vec<Poll>> {
+        self.find("id = $1 OR series_id = $1", &[&series_id.to_hex()]).await
+    }
+
+    async fn list_due(&self, now: DateTime) -> StoreResult<Vec<Poll>> {
+        // Inlined, since `find` binds text only
+        let filter = format!(
+            "recurrence IS NOT NULL AND next_occurrence_at <= {}",
+            now.timestamp_millis()
+        );
+        self.find(&filter, &[]).await
+    }
+
+    async fn close(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool> {
+        let mut args = Vec::new();
+        let sql = format!(
+            "UPDATE polls SET is_closed = TRUE WHERE id = $1 AND {}",
+            access_condition(access, 2, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(id.to_hex());
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        let result = query.execute(&self.0).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn reset_votes(&self, id: ObjectId, access: PollAccess) -> StoreResult<bool> {
+        let hex = id.to_hex();
+        let mut tx = self.0.begin().await?;
+        if !lock_poll(&mut tx, &hex, false, access).await? {
+            return Ok(false);
+        }
+        sqlx::query("UPDATE poll_options SET votes = 0 WHERE poll_id = $1")
+            .bind(hex.as_str())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn update_content(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        title: &str,
+        options: &[PollOption],
+        tags: Option<&[String]>,
+        category: Option<Option<&str>>,
+    ) -> StoreResult<bool> {
+        let hex = id.to_hex();
+        let mut args = Vec::new();
+        let (assignments, next) = match category {
+            Some(_) => ("title = $1, category = $2", 3),
+            None => ("title = $1", 2),
+        };
+        let sql = format!(
+            "UPDATE polls SET {} WHERE id = ${} AND {}",
+            assignments,
+            next,
+            access_condition(access, next + 1, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(title);
+        if let Some(category) = category {
+            query = query.bind(category);
+        }
+        query = query.bind(hex.as_str());
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        let mut tx = self.0.begin().await?;
+        let result = query.execute(&mut *tx).await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM poll_options WHERE poll_id = $1")
+            .bind(hex.as_str())
+            .execute(&mut *tx)
+            .await?;
+        insert_options(&mut tx, &hex, options).await?;
+        if let Some(tags) = tags {
+            sqlx::query("DELETE FROM poll_tags WHERE poll_id = $1")
+                .bind(hex.as_str())
+                .execute(&mut *tx)
+                .await?;
+            insert_tags(&mut tx, &hex, tags).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn set_recurrence(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        schedule: Option<(&Recurrence, DateTime)>,
+    ) -> StoreResult<bool> {
+        // Only an open poll carries the schedule; closed instances are history
+        let (recurrence, next_occurrence_at) = match schedule {
+            Some((recurrence, next)) => (Some(to_json(recurrence)?), Some(next.timestamp_millis())),
+            None => (None, None),
+        };
+        let mut args = Vec::new();
+        let sql = format!(
+            "UPDATE polls SET recurrence = $1, next_occurrence_at = $2 WHERE id = $3 AND is_closed = FALSE AND {}",
+            access_condition(access, 4, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(recurrence).bind(next_occurrence_at).bind(id.to_hex());
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        let result = query.execute(&self.0).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_visibility(
+        &self,
+        id: ObjectId,
+        access: PollAccess,
+        visibility: Visibility,
+        invited_user_ids: &[String],
+        share_token: Option<&str>,
+    ) -> StoreResult<bool> {
+        let hex = id.to_hex();
+        let mut args = Vec::new();
+        let sql = format!(
+            "UPDATE polls SET visibility = $1, share_token = $2 WHERE id = $3 AND {}",
+            access_condition(access, 4, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(visibility_name(visibility)).bind(share_token).bind(hex.as_str());
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        let mut tx = self.0.begin().await?;
+        let result = query.execute(&mut *tx).await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM poll_invites WHERE poll_id = $1")
+            .bind(hex.as_str())
+            .execute(&mut *tx)
+            .await?;
+        insert_poll_users(&mut tx, "poll_invites", &hex, invited_user_ids).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn set_embedding(&self, id: ObjectId, access: PollAccess, embeddable: bool, origins: &[String]) -> StoreResult<bool> {
+        let mut args = Vec::new();
+        let sql = format!(
+            "UPDATE polls SET embeddable = $1, embed_origins = $2 WHERE id = $3 AND {}",
+            access_condition(access, 4, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(embeddable).bind(to_json(&origins)?).bind(id.to_hex());
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        let result = query.execute(&self.0).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn add_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()> {
+        // The WHERE clause keeps SQLite from reading ON CONFLICT as a join
+        let mut args = Vec::new();
+        let sql = format!(
+            "INSERT INTO poll_collaborators (poll_id, user_id, position) \
+             SELECT id, $2, (SELECT COALESCE(MAX(position), -1) + 1 FROM poll_collaborators WHERE poll_id = $1) \
+             FROM polls WHERE id = $1 AND {} \
+             ON CONFLICT (poll_id, user_id) DO NOTHING",
+            access_condition(access, 3, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(id.to_hex()).bind(user_id);
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        query.execute(&self.0).await?;
+        Ok(())
+    }
+
+    async fn remove_collaborator(&self, id: ObjectId, access: PollAccess, user_id: &str) -> StoreResult<()> {
+        let mut args = Vec::new();
+        let sql = format!(
+            "DELETE FROM poll_collaborators WHERE poll_id = $1 AND user_id = $2 \
+             AND EXISTS (SELECT 1 FROM polls WHERE polls.id = $1 AND {})",
+            access_condition(access, 3, &mut args)
+        );
+        let mut query = sqlx::query(&sql).bind(id.to_hex()).bind(user_id);
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        query.execute(&self.0).await?;
+        Ok(())
+    }
+
+    async fn increment_vote(&self, id: ObjectId, option_id: i32) -> StoreResult<bool> {
+        // Counts under the poll's row lock, so a vote racing a close is either
+        // counted before it or refused
+        let hex = id.to_hex();
+        let mut tx = self.0.begin().await?;
+        if !lock_poll(&mut tx, &hex, true, PollAccess::Any).await? {
+            return Ok(false);
+        }
+        let result = sqlx::query("UPDATE poll_options SET votes = votes + 1 WHERE poll_id = $1 AND option_id = $2")
+            .bind(hex.as_str())
+            .bind(option_id as i64)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn claim_rollover(&self, id: ObjectId, due_at: DateTime) -> StoreResult<bool> {
+        let result = sqlx::query(
+            "UPDATE polls SET is_closed = TRUE, recurrence = NULL, next_occurrence_at = NULL, share_token = NULL \
+             WHERE id = $1 AND next_occurrence_at = $2",
+        )
+        .bind(id.to_hex())
+        .bind(due_at.timestamp_millis())
+        .execute(&self.0)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn release_rollover(&self, poll: &Poll) -> StoreResult<()> {
+        let Some(id) = poll.id else {
+            return Ok(());
+        };
+        sqlx::query(
+            "UPDATE polls SET is_closed = $1, recurrence = $2, next_occurrence_at = $3, share_token = $4 WHERE id = $5",
+        )
+        .bind(poll.is_closed)
+        .bind(poll.recurrence.as_ref().map(to_json).transpose()?)
+        .bind(poll.next_occurrence_at.map(|at| at.timestamp_millis()))
+        .bind(poll.share_token.as_deref())
+        .bind(id.to_hex())
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: ObjectId, access: PollAccess) -> StoreResult<Option<Poll>> {
+        let Some(poll) = self.get(id).await? else {
+            return Ok(None);
+        };
+        let mut args = Vec::new();
+        let sql = format!("DELETE FROM polls WHERE id = $1 AND {}", access_condition(access, 2, &mut args));
+        let mut query = sqlx::query(&sql).bind(id.to_hex());
+        for arg in &args {
+            query = query.bind(arg.as_str());
+        }
+        let result = query.execute(&self.0).await?;
+        Ok((result.rows_affected() > 0).then_some(poll))
+    }
+
+    async fn delete_by_creator(&self, user_id: Uuid) -> StoreResult<Vec<Poll>> {
+        // Delete exactly the listed polls so a poll created meanwhile is not removed unreported
+        let polls = self.list_by_creator(user_id).await?;
+        let ids: Vec<String> = polls.iter().filter_map(|p| p.id).map(|id| id.to_hex()).collect();
+        if !ids.is_empty() {
+            let sql = format!("DELETE FROM polls WHERE id IN ({})", placeholders(1, ids.len()));
+            let mut query = sqlx::query(&sql);
+            for id in &ids {
+                query = query.bind(id.as_str());
+            }
+            query.execute(&self.0).await?;
+        }
+        Ok(polls)
+    }
+
+    async fn reassign_creator(&self, from: Uuid, to: Uuid) -> StoreResult<u64> {
+        let result = sqlx::query(
+            "UPDATE polls SET creator_id = $1, recurrence = NULL, next_occurrence_at = NULL WHERE creator_id = $2",
+        )
+        .bind(to.to_string())
+        .bind(from.to_string())
+        .execute(&self.0)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn remove_user(&self, user_id: &str) -> StoreResult<()> {
+        for table in ["poll_invites", "poll_collaborators"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE user_id = $1", table))
+                .bind(user_id)
+                .execute(&self.0)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+fn vote_from_row(row: &AnyRow) -> StoreResult<Vote> {
+    Ok(Vote {
+        id: Some(object_id(&row.try_get::<String, _>("id")?)?),
+        poll_id: object_id(&row.try_get::<String, _>("poll_id")?)?,
+        user_id: row.try_get("user_id")?,
+        option_id: row.try_get::<i64, _>("option_id")? as i32,
+        created_at: timestamp(row, "created_at")?,
+    })
+}
+
+pub struct SqlVoteStore(AnyPool);
+
+#[async_trait]
+impl VoteStore for SqlVoteStore {
+    async fn insert(&self, vote: &Vote) -> StoreResult<bool> {
+        // The unique (poll_id, user_id) constraint drops a second ballot
+        let result = sqlx::query(
+            "INSERT INTO votes (id, poll_id, user_id, option_id, created_at) VALUES ($1, $2, $3, $4, $5) \
+             ON CONFLICT (poll_id, user_id) DO NOTHING",
+        )
+        .bind(vote.id.unwrap_or_default().to_hex())
+        .bind(vote.poll_id.to_hex())
+        .bind(vote.user_id.as_str())
+        .bind(vote.option_id as i64)
+        .bind(vote.created_at.timestamp_millis())
+        .execute(&self.0)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, poll_id: ObjectId, user_id: &str) -> StoreResult<()> {
+        sqlx::query("DELETE FROM votes WHERE poll_id = $1 AND user_id = $2")
+            .bind(poll_id.to_hex())
+            .bind(user_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_for_polls(&self, poll_ids: &[ObjectId]) -> StoreResult<()> {
+        if poll_ids.is_empty() {
+            return Ok(());
+        }
+        let sql = format!("DELETE FROM votes WHERE poll_id IN ({})", placeholders(1, poll_ids.len()));
+        let mut query = sqlx::query(&sql);
+        for id in poll_ids {
+            query = query.bind(id.to_hex());
+        }
+        query.execute(&self.0).await?;
+        Ok(())
+    }
+
+    async fn delete_by_user(&self, user_id: &str) -> StoreResult<()> {
+        sqlx::query("DELETE FROM votes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_for_poll(&self, poll_id: ObjectId) -> StoreResult<Vec<Vote>> {
+        sqlx::query("SELECT * FROM votes WHERE poll_id = $1 ORDER BY created_at, id")
+            .bind(poll_id.to_hex())
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(vote_from_row)
+            .collect()
+    }
+
+    async fn list_by_user(&self, user_id: &str) -> StoreResult<Vec<Vote>> {
+        sqlx::query("SELECT * FROM votes WHERE user_id = $1 ORDER BY created_at, id")
+            .bind(user_id)
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(vote_from_row)
+            .collect()
+    }
+}
+
+pub struct SqlOrgStore(AnyPool);
+
+impl SqlOrgStore {
+    async fn find(&self, filter: &str, arg: &str) -> StoreResult<Vec<Organization>> {
+        let rows = sqlx::query(&format!("SELECT * FROM organizations WHERE {} ORDER BY created_at, id", filter))
+            .bind(arg)
+            .fetch_all(&self.0)
+            .await?;
+        let mut orgs = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.try_get("id")?;
+            let members = sqlx::query("SELECT user_id, role FROM org_members WHERE org_id = $1 ORDER BY position")
+                .bind(id.as_str())
+                .fetch_all(&self.0)
+                .await?
+                .iter()
+                .map(|m| {
+                    Ok(OrgMember {
+                        user_id: m.try_get("user_id")?,
+                        role: parse_role(&m.try_get::<String, _>("role")?)?,
+                    })
+                })
+                .collect::<StoreResult<Vec<_>>>()?;
+            orgs.push(Organization {
+                id: Some(object_id(&id)?),
+                name: row.try_get("name")?,
+                members,
+                created_at: timestamp(&row, "created_at")?,
+            });
+        }
+        Ok(orgs)
+    }
+}
+
+async fn insert_members(
+    tx: &mut sqlx::Transaction<'_, sqlx::Any>,
+    org_id: &str,
+    members: &[OrgMember],
+) -> StoreResult<()> {
+    for (position, member) in members.iter().enumerate() {
+        sqlx::query("INSERT INTO org_members (org_id, user_id, role, position) VALUES ($1, $2, $3, $4)")
+            .bind(org_id)
+            .bind(member.user_id.as_str())
+            .bind(role_name(member.role))
+            .bind(position as i64)
+            .execute(&mut **tx)
+            .await?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl OrgStore for SqlOrgStore {
+    async fn get(&self, id: ObjectId) -> StoreResult<Option<Organization>> {
+        Ok(self.find("id = $1", &id.to_hex()).await?.pop())
+    }
+
+    async fn insert(&self, org: &Organization) -> StoreResult<ObjectId> {
+        let id = org.id.unwrap_or_default();
+        let hex = id.to_hex();
+        let mut tx = self.0.begin().await?;
+        sqlx::query("INSERT INTO organizations (id, name, created_at) VALUES ($1, $2, $3)")
+            .bind(hex.as_str())
+            .bind(org.name.as_str())
+            .bind(org.created_at.timestamp_millis())
+            .execute(&mut *tx)
+            .await?;
+        insert_members(&mut tx, &hex, &org.members).await?;
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    async fn list_for_member(&self, user_id: &str) -> StoreResult<Vec<Organization>> {
+        self.find("id IN (SELECT org_id FROM org_members WHERE user_id = $1)", user_id).await
+    }
+
+    async fn set_members(&self, id: ObjectId, members: &[OrgMember]) -> StoreResult<()> {
+        let hex = id.to_hex();
+        let mut tx = self.0.begin().await?;
+        sqlx::query("DELETE FROM org_members WHERE org_id = $1")
+            .bind(hex.as_str())
+            .execute(&mut *tx)
+            .await?;
+        insert_members(&mut tx, &hex, members).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn remove_member_everywhere(&self, user_id: &str) -> StoreResult<()> {
+        sqlx::query("DELETE FROM org_members WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.0)
+            .await?;
+        sqlx::query("DELETE FROM organizations WHERE id NOT IN (SELECT org_id FROM org_members)")
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+}
+
+fn user_session_from_row(row: &AnyRow) -> StoreResult<UserSession> {
+    Ok(UserSession {
+        id: Some(object_id(&row.try_get::<String, _>("id")?)?),
+        session_id: row.try_get("session_id")?,
+        user_id: row.try_get("user_id")?,
+        created_at: timestamp(row, "created_at")?,
+        last_seen_at: timestamp(row, "last_seen_at")?,
+        user_agent: row.try_get("user_agent")?,
+        ip: row.try_get("ip")?,
+    })
+}
+
+pub struct SqlUserSessionStore(AnyPool);
+
+#[async_trait]
+impl UserSessionStore for SqlUserSessionStore {
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>> {
+        sqlx::query("SELECT * FROM user_sessions WHERE user_id = $1 ORDER BY last_seen_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(user_session_from_row)
+            .collect()
+    }
+
+    async fn touch(&self, session_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<bool> {
+        let result = sqlx::query("UPDATE user_sessions SET last_seen_at = $1, user_agent = $2, ip = $3 WHERE session_id = $4")
+            .bind(DateTime::now().timestamp_millis())
+            .bind(user_agent)
+            .bind(ip)
+            .bind(session_id)
+            .execute(&self.0)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record(&self, session_id: &str, user_id: &str, user_agent: Option<String>, ip: Option<String>) -> StoreResult<()> {
+        let now = DateTime::now().timestamp_millis();
+        sqlx::query(
+            "INSERT INTO user_sessions (id, session_id, user_id, created_at, last_seen_at, user_agent, ip) \
+             VALUES ($1, $2, $3, $4, $4, $5, $6) \
+             ON CONFLICT (session_id) DO UPDATE SET \
+             last_seen_at = excluded.last_seen_at, user_agent = excluded.user_agent, ip = excluded.ip",
+        )
+        .bind(ObjectId::new().to_hex())
+        .bind(session_id)
+        .bind(user_id)
+        .bind(now)
+        .bind(user_agent)
+        .bind(ip)
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, session_id: &str) -> StoreResult<()> {
+        sqlx::query("DELETE FROM user_sessions WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_by_id(&self, id: ObjectId, user_id: &str) -> StoreResult<Option<UserSession>> {
+        let row = sqlx::query("SELECT * FROM user_sessions WHERE id = $1 AND user_id = $2")
+            .bind(id.to_hex())
+            .bind(user_id)
+            .fetch_optional(&self.0)
+            .await?;
+        let Some(record) = row.as_ref().map(user_session_from_row).transpose()? else {
+            return Ok(None);
+        };
+        let result = sqlx::query("DELETE FROM user_sessions WHERE id = $1")
+            .bind(id.to_hex())
+            .execute(&self.0)
+            .await?;
+        Ok((result.rows_affected() > 0).then_some(record))
+    }
+
+    async fn remove_for_user(&self, user_id: &str) -> StoreResult<Vec<UserSession>> {
+        let records = self.list_for_user(user_id).await?;
+        sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.0)
+            .await?;
+        Ok(records)
+    }
+}
+
+fn token_from_row(row: &AnyRow) -> StoreResult<ApiToken> {
+    Ok(ApiToken {
+        id: Some(object_id(&row.try_get::<String, _>("id")?)?),
+        user_id: row.try_get("user_id")?,
+        name: row.try_get("name")?,
+        token_hash: row.try_get("token_hash")?,
+        scopes: from_json(&row.try_get::<String, _>("scopes")?)?,
+        created_at: timestamp(row, "created_at")?,
+        expires_at: timestamp(row, "expires_at")?,
+        last_used_at: optional_timestamp(row, "last_used_at")?,
+    })
+}
+
+pub struct SqlTokenStore(AnyPool);
+
+#[async_trait]
+impl TokenStore for SqlTokenStore {
+    async fn insert(&self, token: &ApiToken) -> StoreResult<ObjectId> {
+        let id = token.id.unwrap_or_default();
+        sqlx::query(
+            "INSERT INTO api_tokens (id, user_id, name, token_hash, scopes, created_at, expires_at, last_used_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(id.to_hex())
+        .bind(token.user_id.as_str())
+        .bind(token.name.as_str())
+        .bind(token.token_hash.as_str())
+        .bind(to_json(&token.scopes)?)
+        .bind(token.created_at.timestamp_millis())
+        .bind(token.expires_at.timestamp_millis())
+        .bind(token.last_used_at.map(|t| t.timestamp_millis()))
+        .execute(&self.0)
+        .await?;
+        Ok(id)
+    }
+
+    async fn list_for_user(&self, user_id: &str) -> StoreResult<Vec<ApiToken>> {
+        sqlx::query("SELECT * FROM api_tokens WHERE user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(token_from_row)
+            .collect()
+    }
+
+    async fn use_token(&self, token_hash: &str) -> StoreResult<Option<ApiToken>> {
+        let now = DateTime::now().timestamp_millis();
+        let row = sqlx::query("SELECT * FROM api_tokens WHERE token_hash = $1 AND expires_at > $2")
+            .bind(token_hash)
+            .bind(now)
+            .fetch_optional(&self.0)
+            .await?;
+        let Some(token) = row.as_ref().map(token_from_row).transpose()? else {
+            return Ok(None);
+        };
+        sqlx::query("UPDATE api_tokens SET last_used_at = $1 WHERE token_hash = $2")
+            .bind(now)
+            .bind(token_hash)
+            .execute(&self.0)
+            .await?;
+        Ok(Some(token))
+    }
+
+    async fn delete(&self, id: ObjectId, user_id: &str) -> StoreResult<bool> {
+        let result = sqlx::query("DELETE FROM api_tokens WHERE id = $1 AND user_id = $2")
+            .bind(id.to_hex())
+            .bind(user_id)
+            .execute(&self.0)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_for_user(&self, user_id: &str) -> StoreResult<()> {
+        sqlx::query("DELETE FROM api_tokens WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.0)
+            .await?;
+        Ok(())
+    }
+}
+
+pub struct SqlAdminLogStore(AnyPool);
+
+#[async_trait]
+impl AdminLogStore for SqlAdminLogStore {
+    async fn record(&self, action: &AdminAction) -> StoreResult<()> {
+        sqlx::query(
+            "INSERT INTO admin_log (id, admin_id, admin_username, action, target, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(action.id.unwrap_or_default().to_hex())
+        .bind(action.admin_id.as_str())
+        .bind(action.admin_username.as_str())
+        .bind(action.action.as_str())
+        .bind(action.target.as_str())
+        .bind(action.created_at.timestamp_millis())
+        .execute(&self.0)
+        .await?;
+        Ok(())
+    }
+
+    async fn recent(&self, limit: usize) -> StoreResult<Vec<AdminAction>> {
+        sqlx::query("SELECT * FROM admin_log ORDER BY created_at DESC LIMIT $1")
+            .bind(limit as i64)
+            .fetch_all(&self.0)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(AdminAction {
+                    id: Some(object_id(&row.try_get::<String, _>("id")?)?),
+                    admin_id: row.try_get("admin_id")?,
+                    admin_username: row.try_get("admin_username")?,
+                    action: row.try_get("action")?,
+                    target: row.try_get("target")?,
+                    created_at: timestamp(row, "created_at")?,
+                })
+            })
+            .collect()
+    }
+}
+
+// tower-sessions records in the `sessions` table
+#[derive(Clone, Debug)]
+pub struct SqlSessionStore(AnyPool);
+
+fn backend_error(e: sqlx::Error) -> session_store::Error {
+    session_store::Error::Backend(e.to_string())
+}
+
+#[async_trait]
+impl SessionStore for SqlSessionStore {
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data = serde_json::to_string(record).map_err(|e| session_store::Error::Encode(e.to_string()))?;
+        sqlx::query(
+            "INSERT INTO sessions (id, data, expiry_date) VALUES ($1, $2, $3) \
+             ON CONFLICT (id) DO UPDATE SET data = excluded.data, expiry_date = excluded.expiry_date",
+        )
+        .bind(record.id.to_string())
+        .bind(data)
+        .bind(record.expiry_date.unix_timestamp())
+        .execute(&self.0)
+        .await
+        .map_err(backend_error)?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        let now = chrono::Utc::now().timestamp();
+        let row = sqlx::query("SELECT data FROM sessions WHERE id = $1 AND expiry_date > $2")
+            .bind(session_id.to_string())
+            .bind(now)
+            .fetch_optional(&self.0)
+            .await
+            .map_err(backend_error)?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let data: String = row.try_get("data").map_err(backend_error)?;
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|e| session_store::Error::Decode(e.to_string()))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(session_id.to_string())
+            .execute(&self.0)
+            .await
+            .map_err(backend_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::tests::poll;
+
+    // A fresh in-memory database per test, shared by the pool's connections
+    async fn sqlite() -> Stores {
+        let url = format!("sqlite:file:test-{}?mode=memory&cache=shared", ObjectId::new());
+        open(StorageKind::Sqlite, &url).await
+    }
+
+    fn vote(poll_id: ObjectId, user_id: Uuid, option_id: i32) -> Vote {
+        Vote { id: None, poll_id, user_id: user_id.to_string(), option_id, created_at: DateTime::now() }
+    }
+
+    fn creator(user_id: Uuid) -> PollAccess {
+        PollAccess::User { user_id, collaborators: false, org_id: None }
+    }
+
+    #[tokio::test]
+    async fn polls_round_trip_with_options_and_members() {
+        let stores = sqlite().await;
+        let creator_id = Uuid::new_v4();
+        let invited = Uuid::new_v4().to_string();
+        let private = Poll {
+            visibility: Visibility::Private,
+            invited_user_ids: vec![invited.clone()],
+            tags: vec!["food".to_string()],
+            category: Some("team".to_string()),
+            ..poll(creator_id)
+        };
+        let poll_id = stores.polls.insert(&private).await.unwrap();
+
+        let stored = stores.polls.get(poll_id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "Lunch");
+        assert_eq!(stored.options.iter().map(|o| o.text.as_str()).collect::<Vec<_>>(), ["Pizza", "Sushi"]);
+        assert_eq!(stored.visibility, Visibility::Private);
+        assert_eq!(stored.invited_user_ids, [invited]);
+        assert_eq!((stored.tags, stored.category), (vec!["food".to_string()], Some("team".to_string())));
+        assert_eq!(stored.creator_id, creator_id);
+    }
+
+    #[tokio::test]
+    async fn one_vote_per_user_and_none_on_closed_polls() {
+        let stores = sqlite().await;
+        let creator_id = Uuid::new_v4();
+        let poll_id = stores.polls.insert(&poll(creator_id)).await.unwrap();
+        let voter = Uuid::new_v4();
+
+        assert!(stores.votes.insert(&vote(poll_id, voter, 1)).await.unwrap());
+        assert!(!stores.votes.insert(&vote(poll_id, voter, 2)).await.unwrap());
+        assert!(stores.polls.increment_vote(poll_id, 1).await.unwrap());
+        assert!(!stores.polls.increment_vote(poll_id, 3).await.unwrap());
+
+        assert!(stores.polls.close(poll_id, creator(creator_id)).await.unwrap());
+        assert!(!stores.polls.increment_vote(poll_id, 2).await.unwrap());
+        let stored = stores.polls.get(poll_id).await.unwrap().unwrap();
+        assert_eq!(stored.options.iter().map(|o| o.votes).collect::<Vec<_>>(), [1, 0]);
+    }
+
+    #[tokio::test]
+    async fn writes_need_access_to_the_poll() {
+        let stores = sqlite().await;
+        let creator_id = Uuid::new_v4();
+        let collaborator = Uuid::new_v4();
+        let org_id = ObjectId::new();
+        let poll_id = stores.polls.insert(&Poll { org_id: Some(org_id), ..poll(creator_id) }).await.unwrap();
+        stores.polls.add_collaborator(poll_id, creator(creator_id), &collaborator.to_string()).await.unwrap();
+
+        let as_collaborator = PollAccess::User { user_id: collaborator, collaborators: true, org_id: None };
+        let stranger = PollAccess::User { user_id: Uuid::new_v4(), collaborators: true, org_id: Some(ObjectId::new()) };
+        let as_org = PollAccess::User { user_id: Uuid::new_v4(), collaborators: false, org_id: Some(org_id) };
+        assert!(!stores.polls.reset_votes(poll_id, stranger).await.unwrap());
+        assert!(!stores.polls.close(poll_id, creator(collaborator)).await.unwrap());
+        assert!(stores.polls.reset_votes(poll_id, as_collaborator).await.unwrap());
+        assert!(stores.polls.close(poll_id, as_org).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn usernames_are_unique_regardless_of_case() {
+        let stores = sqlite().await;
+        let user = |username: &str| UserData {
+            username: username.to_string(),
+            username_key: username_key(username),
+            unique_id: Uuid::new_v4(),
+            passkeys: Vec::new(),
+            is_admin: false,
+            disabled: false,
+            passkey_meta: Vec::new(),
+            recovery_codes: Vec::new(),
+        };
+        let alice = user("alice");
+        stores.users.insert(&alice).await.unwrap();
+        assert!(matches!(stores.users.insert(&user("Alice")).await, Err(ApiError::UsernameTaken)));
+        let found = stores.users.find_by_username("ALICE").await.unwrap().unwrap();
+        assert_eq!(found.unique_id, alice.unique_id);
+    }
+
+    #[tokio::test]
+    async fn search_filters_and_pages() {
+        crate::store::tests::check_search(&sqlite().await).await;
+    }
+
+    #[tokio::test]
+    async fn edits_keep_tags_unless_given() {
+        crate::store::tests::check_content_update(&sqlite().await).await;
+    }
+}
@@ -4,33 +4,52 @@ use axum::{
     response::IntoResponse,
 };
 use futures_util::{SinkExt, StreamExt};
+use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use crate::startup::{AppState, UserData};
-use crate::models::Poll;
+use tower_sessions::Session;
+use crate::startup::AppState;
+use crate::models::Visibility;
+use crate::routes::polls::load_viewer;
 use axum::extract::ws::{Message, WebSocket};
-use mongodb::bson::{doc, oid::ObjectId};
-use serde_json;
+use mongodb::bson::oid::ObjectId;
+use uuid::Uuid;
 
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     Extension(app_state): Extension<AppState>,
+    session: Session,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, app_state))
+    ws.on_upgrade(move |socket| handle_socket(socket, app_state, session))
 }
 
-async fn handle_socket(socket: WebSocket, app_state: AppState) {
+// The session's user, unless the account was disabled or deleted since the
+// socket was opened
+async fn current_user_id(app_state: &AppState, session: &Session) -> Option<Uuid> {
+    let user_id: Uuid = session.get("user_id").await.ok().flatten()?;
+    match app_state.users.get(user_id).await {
+        Ok(Some(user)) if !user.disabled => Some(user_id),
+        _ => None,
+    }
+}
+
+async fn handle_socket(socket: WebSocket, app_state: AppState, session: Session) {
     info!("New WebSocket connection established");
     let ( ws_sender, mut ws_receiver) = socket.split();
     let ws_sender = Arc::new(Mutex::new(ws_sender));
     let tx = Arc::clone(&app_state.broadcast_tx);
     let mut rx = tx.subscribe();
     let is_closed = Arc::new(Mutex::new(false));
+    // Non-public polls this client joined after passing the visibility check
+    let joined = Arc::new(Mutex::new(HashSet::new()));
 
     let ws_sender_clone = Arc::clone(&ws_sender);
     let is_closed_clone = Arc::clone(&is_closed);
     let app_state_clone = app_state.clone();
+    let joined_clone = Arc::clone(&joined);
+    let session_clone = session.clone();
     tokio::spawn(async move {
+        let session = session_clone;
         while let Some(msg_result) = ws_receiver.next().await {
             match msg_result {
                 Ok(Message::Text(text)) => {
@@ -38,27 +57,26 @@ async fn handle_socket(socket: WebSocket, app_state: AppState) {
                     if text.starts_with("join_poll:") {
                         let poll_id = text.strip_prefix("join_poll:").unwrap();
                         if let Ok(poll_id) = ObjectId::parse_str(poll_id) {
-                            let collection = app_state_clone.db.collection::<Poll>("polls");
-                            let users_collection = app_state_clone.db.collection::<UserData>("users");
-                            match collection.find_one(doc! { "_id": poll_id }).await {
+                            match app_state_clone.polls.get(poll_id).await {
                                 Ok(Some(mut poll)) => {
                                     if poll.id.is_none() {
                                         poll.id = Some(poll_id);
                                     }
-                                    // Fetch author for initial poll
-                                    let creator = users_collection
-                                        .find_one(doc! { "unique_id": poll.creator_id.to_string() })
-                                        .await
-                                        .unwrap_or(None);
-                                    poll.author = creator.map(|u| u.username);
-                                    let poll_json = serde_json::to_string(&poll).unwrap();
+                                    let user_id = current_user_id(&app_state_clone, &session).await;
+                                    let viewer = load_viewer(&app_state_clone, &session, user_id, &poll).await.unwrap_or_default();
+                                    if !poll.is_visible_to(&viewer) {
+                                        info!("Poll {} not visible to WebSocket client {:?}", poll_id, viewer.user_id);
+                                        continue;
+                                    }
+                                    joined_clone.lock().await.insert(poll_id);
+                                    let poll_json = serde_json::to_string(&poll.redacted()).unwrap();
                                     let mut sender = ws_sender_clone.lock().await;
                                     if sender.send(Message::Text(poll_json)).await.is_err() {
                                         error!("Failed to send poll update: {}", poll_id);
                                         *is_closed_clone.lock().await = true;
                                         return;
                                     }
-                                    info!("Sent initial poll {} to client with author: {:?}", poll_id, poll.author);
+                                    info!("Sent initial poll {} to client", poll_id);
                                 }
                                 Ok(None) => info!("Poll {} not found", poll_id),
                                 Err(e) => {
@@ -122,10 +140,25 @@ async fn handle_socket(socket: WebSocket, app_state: AppState) {
             info!("Stopping broadcast loop due to closed connection");
             break;
         }
-        if poll.id.is_none() {
-            error!("Poll missing ID before broadcast, skipping: {:?}", poll);
+        let Some(poll_id) = poll.id else {
+            error!("Poll missing ID before broadcast, skipping");
             continue;
+        };
+        // Checked again on every update: the poll may have turned private,
+        // the invite may be gone or the account disabled since joining
+        if poll.visibility != Visibility::Public {
+            if !joined.lock().await.contains(&poll_id) {
+                continue;
+            }
+            let user_id = current_user_id(&app_state, &session).await;
+            let viewer = load_viewer(&app_state, &session, user_id, &poll).await.unwrap_or_default();
+            if !poll.is_visible_to(&viewer) {
+                info!("Poll {} no longer visible to WebSocket client {:?}", poll_id, user_id);
+                joined.lock().await.remove(&poll_id);
+                continue;
+            }
         }
+        let poll = poll.redacted();
         let poll_json = match serde_json::to_string(&poll) {
             Ok(json) => json,
             Err(e) => {
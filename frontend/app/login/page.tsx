@@ -1,8 +1,8 @@
 // app/login/page.tsx
 'use client';
 
-import { useState } from 'react';
-import { startAuth } from '@/lib/auth';
+import { useEffect, useState } from 'react';
+import { startAuth, startConditionalAuth } from '@/lib/auth';
 import { useAppStore } from '@/lib/store';
 import { useRouter } from 'next/navigation';
 import axios from 'axios';
@@ -14,16 +14,33 @@ const LoginPage = () => {
   const { setUser } = useAppStore();
   const router = useRouter();
 
+  const completeLogin = async () => {
+    const response = await axios.get(`${process.env.NEXT_PUBLIC_BACKEND_URL}/api/user`, { withCredentials: true });
+    const userData = response.data;
+    setUser({ username: userData.username, id: userData.id });
+    console.log('User set in store:', useAppStore.getState().user);
+    setMessage('Authentication successful!');
+    const intendedPath = document.referrer.includes('/polls/manage') ? '/polls/manage' : '/polls/new';
+    router.push(intendedPath);
+  };
+
+  // Lets the browser suggest passkeys from the username field's autofill
+  useEffect(() => {
+    startConditionalAuth()
+      .then((signedIn) => signedIn && completeLogin())
+      .catch((error) => {
+        // Aborted when the Login button starts its own request
+        if ((error as Error).name !== 'AbortError') {
+          setMessage(`Error: ${(error as Error).message}`);
+        }
+      });
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, []);
+
   const handleLogin = async () => {
     try {
       await startAuth(username);
-      const response = await axios.get(`${process.env.NEXT_PUBLIC_BACKEND_URL}/api/user`, { withCredentials: true });
-      const userData = response.data;
-      setUser({ username: userData.username, id: userData.id });
-      console.log('User set in store:', useAppStore.getState().user);
-      setMessage('Authentication successful!');
-      const intendedPath = document.referrer.includes('/polls/manage') ? '/polls/manage' : '/polls/new';
-      router.push(intendedPath);
+      await completeLogin();
     } catch (error) {
       if ((error as Error).name === 'NotAllowedError') {
         setMessage('Authentication cancelled. Please try again.');
@@ -47,6 +64,7 @@ const LoginPage = () => {
           value={username}
           onChange={(e) => setUsername(e.target.value)}
           placeholder="Enter username"
+          autoComplete="username webauthn"
           className="w-full mt-6 p-3 rounded-md bg-[#1e1e2e] text-gray-200 border border-gray-600 focus:ring-2 focus:ring-cyan-500 focus:outline-none"
         />
         <button
@@ -1,6 +1,7 @@
 // lib/api.ts
 import axios, { AxiosResponse } from 'axios';
 import { useAppStore, Poll } from './store';
+import { reauthenticate } from './auth';
 
 const API_BASE_URL = `${process.env.NEXT_PUBLIC_BACKEND_URL}`;
 
@@ -9,6 +10,22 @@ const api = axios.create({
   withCredentials: true,
 });
 
+const REAUTH_REQUIRED = 'reauth_required';
+
+// Runs a sensitive request, confirming a passkey and retrying once if the
+// server asks for a fresh assertion
+const withReauth = async <T>(request: () => Promise<T>): Promise<T> => {
+  try {
+    return await request();
+  } catch (error) {
+    if (axios.isAxiosError(error) && error.response?.data?.code === REAUTH_REQUIRED) {
+      await reauthenticate();
+      return await request();
+    }
+    throw error;
+  }
+};
+
 interface CreatePollData {
   title: string;
   options: string[];
@@ -18,6 +35,14 @@ interface EditPollData {
   title: string;
   options: string[];
 }
+
+// One page of a poll listing
+interface PollPage {
+  // eslint-disable-next-line @typescript-eslint/no-explicit-any
+  polls: any[];
+  nextCursor: string | null;
+  total: number;
+}
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
 const normalizePoll = (poll: any): Poll => {
   const id = poll._id?.$oid || poll.id;
@@ -64,8 +89,8 @@ export const voteOnPoll = async (pollId: string, optionId: number): Promise<void
 
 export const fetchUserPolls = async (): Promise<Poll[]> => {
   try {
-    const response: AxiosResponse<Poll[]> = await api.get('/api/polls/manage');
-    const normalizedPolls = response.data.map(normalizePoll);
+    const response: AxiosResponse<PollPage> = await api.get('/api/polls/manage', { params: { limit: 100 } });
+    const normalizedPolls = response.data.polls.map(normalizePoll);
     useAppStore.getState().setPolls(normalizedPolls);
     return normalizedPolls;
   } catch (error) {
@@ -93,7 +118,7 @@ export const closePoll = async (pollId: string): Promise<void> => {
 
 export const resetPoll = async (pollId: string): Promise<void> => {
   try {
-    await api.post(`/api/polls/${pollId}/reset`);
+    await withReauth(() => api.post(`/api/polls/${pollId}/reset`));
     const currentPoll = useAppStore.getState().polls.find((p) => p.id === pollId);
     if (currentPoll) {
       useAppStore.getState().updatePoll({
@@ -108,7 +133,7 @@ export const resetPoll = async (pollId: string): Promise<void> => {
 
 export const deletePoll = async (pollId: string): Promise<void> => {
   try {
-    await api.post(`/api/polls/${pollId}/delete`);
+    await withReauth(() => api.post(`/api/polls/${pollId}/delete`));
     useAppStore.getState().setPolls(
       useAppStore.getState().polls.filter((p) => p.id !== pollId)
     );
@@ -139,8 +164,8 @@ export const logout = async (): Promise<void> => {
 
 export const fetchAllPolls = async (): Promise<Poll[]> => {
   try {
-    const response: AxiosResponse<Poll[]> = await api.get('/api/polls/all');
-    return response.data.map(normalizePoll);
+    const response: AxiosResponse<PollPage> = await api.get('/api/polls/all', { params: { limit: 100 } });
+    return response.data.polls.map(normalizePoll);
   } catch (error) {
     throw handleError(error, 'Failed to fetch all polls');
   }
@@ -148,7 +173,8 @@ export const fetchAllPolls = async (): Promise<Poll[]> => {
 
 function handleError(error: unknown, defaultMessage: string): Error {
   if (axios.isAxiosError(error)) {
-    const message = error.response?.data || defaultMessage;
+    // Error bodies are `{ code, message, details, request_id }`
+    const message = error.response?.data?.message || defaultMessage;
     return new Error(typeof message === 'string' ? message : defaultMessage);
   }
   return new Error('An unexpected error occurred');
@@ -2,6 +2,7 @@
 import {
   startRegistration,
   startAuthentication,
+  browserSupportsWebAuthnAutofill,
   RegistrationResponseJSON,
   AuthenticationResponseJSON,
   PublicKeyCredentialCreationOptionsJSON,
@@ -86,4 +87,69 @@ export async function startAuth(username: string): Promise<string> {
     throw new Error('Failed to finish authentication');
   }
   return await finishResponse.text();
-}
\ No newline at end of file
+}
+
+// Offer saved passkeys in the username field's autofill (conditional UI). The
+// browser keeps the request open until the user picks a passkey, so this only
+// resolves then. Returns false when the browser has no autofill for passkeys.
+export async function startConditionalAuth(): Promise<boolean> {
+  if (!(await browserSupportsWebAuthnAutofill())) {
+    return false;
+  }
+  const response = await fetch(`${API_BASE_URL}/login_discoverable_start`, {
+    method: 'POST',
+    credentials: 'include',
+  });
+  if (!response.ok) {
+    const errorText = await response.text();
+    console.error(`Start conditional auth failed: ${response.status} - ${errorText}`);
+    throw new Error('Failed to start authentication');
+  }
+  const responseData = await response.json();
+  const options: PublicKeyCredentialRequestOptionsJSON = responseData.publicKey;
+  const credential: AuthenticationResponseJSON = await startAuthentication({
+    optionsJSON: options,
+    useBrowserAutofill: true,
+  });
+
+  const finishResponse = await fetch(`${API_BASE_URL}/login_discoverable_finish`, {
+    method: 'POST',
+    credentials: 'include',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(credential),
+  });
+  if (!finishResponse.ok) {
+    const errorText = await finishResponse.text();
+    console.error(`Finish conditional auth failed: ${finishResponse.status} - ${errorText}`);
+    throw new Error('Failed to finish authentication');
+  }
+  return true;
+}
+
+// Confirm a passkey again before a sensitive action
+export async function reauthenticate(): Promise<void> {
+  const response = await fetch(`${API_BASE_URL}/reauth_start`, {
+    method: 'POST',
+    credentials: 'include',
+  });
+  if (!response.ok) {
+    const errorText = await response.text();
+    console.error(`Start reauth failed: ${response.status} - ${errorText}`);
+    throw new Error('Failed to start reauthentication');
+  }
+  const responseData = await response.json();
+  const options: PublicKeyCredentialRequestOptionsJSON = responseData.publicKey;
+  const credential: AuthenticationResponseJSON = await startAuthentication({ optionsJSON: options });
+
+  const finishResponse = await fetch(`${API_BASE_URL}/reauth_finish`, {
+    method: 'POST',
+    credentials: 'include',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(credential),
+  });
+  if (!finishResponse.ok) {
+    const errorText = await finishResponse.text();
+    console.error(`Finish reauth failed: ${finishResponse.status} - ${errorText}`);
+    throw new Error('Failed to finish reauthentication');
+  }
+}